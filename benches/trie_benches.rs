@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use ecs_trie::Trie;
+use ecs_trie::{ArenaTrie, Delimiter, Trie, TrieBuilder};
 
 fn get_text() -> Vec<String> {
     use std::fs::File;
@@ -17,16 +17,22 @@ fn get_text() -> Vec<String> {
 }
 
 fn make_trie(words: &[String]) -> Trie<char> {
-    let mut trie = Trie::new();
-    for w in words {
-        trie.insert(&w.chars().collect::<Vec<_>>());
-    }
-    trie
+    words.iter().map(String::as_str).collect()
 }
 
 fn trie_insert(b: &mut Criterion) {
-    let words = get_text();
+    let mut words = get_text();
     b.bench_function("trie insert", |b| b.iter(|| make_trie(&words)));
+    // sorted input shares long prefixes with the previous word
+    words.sort();
+    b.bench_function("trie insert sorted", |b| b.iter(|| make_trie(&words)));
+}
+
+fn trie_from_reader(b: &mut Criterion) {
+    let text = std::fs::read_to_string("data/sun-rising.txt").unwrap();
+    b.bench_function("trie from_reader", |b| {
+        b.iter(|| Trie::from_reader(text.as_bytes(), Delimiter::Whitespace).unwrap())
+    });
 }
 
 fn trie_get(b: &mut Criterion) {
@@ -54,6 +60,185 @@ fn trie_insert_remove(b: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, trie_insert, trie_get, trie_insert_remove);
+fn trie_clone_from(b: &mut Criterion) {
+    let words = get_text();
+    let template = make_trie(&words);
+
+    b.bench_function("trie clone", |b| {
+        b.iter(|| template.clone())
+    });
+    let mut scratch = template.clone();
+    b.bench_function("trie clone_from", |b| {
+        b.iter(|| scratch.clone_from(&template))
+    });
+}
+
+fn arena_vs_map(b: &mut Criterion) {
+    use std::fs::File;
+    use std::io::Read;
+    let mut contents = String::new();
+    File::open("data/1984.txt")
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    let words = contents
+        .split_whitespace()
+        .map(|w| w.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    b.bench_function("1984 map insert", |b| {
+        b.iter(|| {
+            let mut trie = Trie::new();
+            for w in &words {
+                trie.insert(w);
+            }
+            trie
+        })
+    });
+    b.bench_function("1984 arena insert", |b| {
+        b.iter(|| {
+            let mut trie = ArenaTrie::new();
+            for w in &words {
+                trie.insert(w);
+            }
+            trie
+        })
+    });
+
+    let mut map = Trie::new();
+    let mut arena = ArenaTrie::new();
+    for w in &words {
+        map.insert(w);
+        arena.insert(w);
+    }
+    b.bench_function("1984 map contains", |b| {
+        b.iter(|| words.iter().filter(|w| map.contains(w)).count())
+    });
+    b.bench_function("1984 arena contains", |b| {
+        b.iter(|| words.iter().filter(|w| arena.contains(w)).count())
+    });
+    let compiled = map.compile();
+    b.bench_function("1984 compiled contains", |b| {
+        b.iter(|| words.iter().filter(|w| compiled.contains(w)).count())
+    });
+}
+
+fn trie_top_k(b: &mut Criterion) {
+    let words = get_text();
+    let mut trie = Trie::new();
+    for (i, w) in words.iter().enumerate() {
+        trie.insert_weighted(&w.chars().collect::<Vec<_>>(), (i * 7919 % 1009) as u32);
+    }
+
+    b.bench_function("top 10 of 't'", |b| b.iter(|| trie.top_k(&['t'], 10)));
+    b.bench_function("search and sort 't'", |b| {
+        b.iter(|| {
+            let found = trie.search(&['t']);
+            let mut all = found.as_collected()
+                .into_iter()
+                .map(|seq| (seq.to_vec(), trie.weight(seq).unwrap()))
+                .collect::<Vec<_>>();
+            all.sort_by_key(|seq| std::cmp::Reverse(seq.1));
+            all.truncate(10);
+            all
+        })
+    });
+}
+
+fn trie_search_refs(b: &mut Criterion) {
+    use std::fs::File;
+    use std::io::Read;
+    let mut contents = String::new();
+    File::open("data/1984.txt")
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    // each line is a sequence of word tokens
+    let mut trie = Trie::new();
+    for line in contents.lines() {
+        let tokens = line.split_whitespace().map(String::from).collect::<Vec<_>>();
+        trie.insert(&tokens);
+    }
+    let prefix = ["the".to_string()];
+
+    b.bench_function("String tokens search", |b| b.iter(|| trie.search(&prefix)));
+    b.bench_function("String tokens search_refs", |b| b.iter(|| trie.search_refs(&prefix)));
+}
+
+fn trie_bloom_misses(b: &mut Criterion) {
+    let words = get_text().iter().map(|w| w.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+    let plain = TrieBuilder::new().build_from_iter(words.iter());
+    let bloom = TrieBuilder::new().bloom_filter(0.01).build_from_iter(words.iter());
+    // 99 misses for every hit
+    let queries = (0..words.len() * 99)
+        .map(|i| format!("{}~{}", i, i % 7).chars().collect::<Vec<_>>())
+        .chain(words.iter().cloned())
+        .collect::<Vec<_>>();
+
+    b.bench_function("contains 99% misses", |b| {
+        b.iter(|| queries.iter().filter(|q| plain.contains(q)).count())
+    });
+    b.bench_function("contains 99% misses bloom", |b| {
+        b.iter(|| queries.iter().filter(|q| bloom.contains(q)).count())
+    });
+}
+
+fn trie_prepared_queries(b: &mut Criterion) {
+    let text = std::fs::read_to_string("data/1984.txt").unwrap();
+    let words = text.split_whitespace().map(|w| w.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+    let trie = TrieBuilder::new().build_from_iter(words.iter());
+
+    // membership, then how far it matches, then completions for each word
+    b.bench_function("3 queries per word", |b| {
+        b.iter(|| {
+            words.iter()
+                .map(|w| trie.contains(w) as usize + trie.match_depth(w) + trie.search(w).as_collected().len())
+                .sum::<usize>()
+        })
+    });
+    b.bench_function("3 queries per word prepared", |b| {
+        b.iter(|| {
+            words.iter()
+                .map(|w| {
+                    let query = trie.prepare(w);
+                    trie.contains_prepared(&query) as usize
+                        + trie.match_depth_prepared(&query)
+                        + trie.search_prepared(&query).as_collected().len()
+                })
+                .sum::<usize>()
+        })
+    });
+}
+
+fn trie_search_into(b: &mut Criterion) {
+    let words = get_text();
+    let trie = make_trie(&words);
+    let prefixes = words.iter()
+        .map(|w| w.chars().take(2).collect::<Vec<_>>())
+        .cycle()
+        .take(10_000)
+        .collect::<Vec<_>>();
+
+    b.bench_function("10k searches", |b| {
+        b.iter(|| {
+            let mut out = Vec::new();
+            for p in &prefixes {
+                out = trie.search(p).into_response_vec();
+            }
+            out
+        })
+    });
+    b.bench_function("10k searches reusing a buffer", |b| {
+        let mut out = Vec::new();
+        b.iter(|| {
+            for p in &prefixes {
+                trie.search_into(p, &mut out);
+            }
+            out.len()
+        })
+    });
+}
+
+criterion_group!(benches, trie_insert, trie_from_reader, trie_get, trie_insert_remove, trie_clone_from, arena_vs_map, trie_top_k, trie_search_refs, trie_bloom_misses, trie_prepared_queries, trie_search_into);
 
 criterion_main!(benches);
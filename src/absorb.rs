@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::{Node, Trie};
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Moves every sequence of `other` into `self`, leaving `self` storing
+    /// the union of both.
+    ///
+    /// Node keys are hashes of the path so a node of `other` belongs under the
+    /// same key in `self`. A path `self` doesn't have is moved across with its
+    /// whole subtree and a path both have keeps the node of `self`, nothing is
+    /// cloned. A sequence stored in both keeps its weight in `self`.
+    ///
+    /// When `self` normalizes, limits the length or number of sequences, keeps
+    /// a suffix index or has an observer each sequence of `other` goes through
+    /// `insert_owned` instead, cloning the elements of every sequence once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::from(&["cat", "cow"][..]);
+    /// trie.absorb(Trie::from(&["cart", "dog"][..]));
+    ///
+    /// assert_eq!(trie.sequence_count(), 4);
+    /// assert_eq!(trie, Trie::from(&["cat", "cow", "cart", "dog"][..]));
+    /// ```
+    pub fn absorb(&mut self, mut other: Self) {
+        let plain = self.normalizer.is_none()
+            && self.max_len.is_none()
+            && self.lru.is_none()
+            && self.suffixes.is_none()
+            && self.observer.is_none();
+        if !plain {
+            for seq in other.sequences() {
+                self.insert_owned(seq);
+            }
+            return;
+        }
+
+        let mut added = 0;
+        for start in std::mem::take(&mut other.starts) {
+            added += self.absorb_node(&mut other.children, start, None);
+        }
+        self.seq_len += added;
+        debug_assert_invariants!(self);
+    }
+
+    /// Moves the node at `key` of `other`, with everything below it, into
+    /// `self` under `parent` returning how many more sequences `self` stores.
+    fn absorb_node(&mut self, other: &mut HashMap<u64, Node<T>, S>, key: u64, parent: Option<u64>) -> usize {
+        let mut node = other.remove(&key).expect("every child of `other` is in its map");
+        let mine = match self.children.get_mut(&key) {
+            Some(mine) => mine,
+            None => {
+                let added = node.descendant_terminals;
+                match parent {
+                    Some(parent) => self.children.get_mut(&parent).expect("the parent was absorbed first").add_child(key),
+                    None => self.starts.push(key),
+                }
+                self.move_subtree(other, key, node);
+                return added;
+            }
+        };
+
+        let mut added = 0;
+        if node.terminal && !mine.terminal {
+            mine.terminal = true;
+            mine.weight = node.weight;
+            added += 1;
+        }
+        for child in std::mem::take(&mut node.children) {
+            added += self.absorb_node(other, child, Some(key));
+        }
+        self.children.get_mut(&key).expect("the node is in `self`").descendant_terminals += added;
+        added
+    }
+
+    /// Inserts `node` at `key` and its descendants from `other`, none of them
+    /// are in `self`.
+    fn move_subtree(&mut self, other: &mut HashMap<u64, Node<T>, S>, key: u64, node: Node<T>) {
+        let mut moved = vec![key];
+        let mut stack = node.children.clone();
+        self.children.insert(key, node);
+        while let Some(key) = stack.pop() {
+            let node = other.remove(&key).expect("every child of `other` is in its map");
+            stack.extend(node.children.iter().copied());
+            self.children.insert(key, node);
+            moved.push(key);
+        }
+        self.len += moved.len();
+        self.bloom_insert(&moved);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use super::*;
+
+    thread_local! {
+        static CLONES: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counts its clones on this thread.
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct Counted(char);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONES.with(|c| c.set(c.get() + 1));
+            Counted(self.0)
+        }
+    }
+
+    fn counted(words: &[&str]) -> Trie<Counted> {
+        let mut trie = Trie::new();
+        for w in words {
+            trie.insert_owned(w.chars().map(Counted).collect());
+        }
+        trie
+    }
+
+    fn clones() -> usize {
+        CLONES.with(|c| c.replace(0))
+    }
+
+    #[test]
+    fn absorb_moves_without_cloning() {
+        let mine = ["cat", "cart", "cow", "d"];
+        let theirs = ["car", "cart", "cowboy", "dog", "emu", "d"];
+        let mut union = counted(&mine);
+        let mut other = counted(&theirs);
+        other.insert_weighted(&"emu".chars().map(Counted).collect::<Vec<_>>(), 7);
+        clones();
+
+        union.absorb(other);
+        assert_eq!(clones(), 0);
+        assert_eq!(union.check_invariants(), Ok(()));
+
+        let mut all = mine.to_vec();
+        all.extend(theirs.iter());
+        let expected = counted(&all);
+        assert_eq!(union, expected);
+        assert_eq!(union.sequence_count(), 8);
+        assert_eq!(union.node_count(), expected.node_count());
+        assert_eq!(union.count_prefix(&[Counted('c')]), 5);
+        assert_eq!(union.weight(&[Counted('e'), Counted('m'), Counted('u')]), Some(7));
+
+        // inserting the other trie's sequences clones every element
+        let mut inserted = counted(&mine);
+        let other = counted(&theirs);
+        clones();
+        for seq in other.sequences() {
+            inserted.insert(&seq);
+        }
+        assert!(clones() > 0);
+        assert_eq!(inserted, union);
+    }
+
+    #[test]
+    fn absorb_into_empty_and_with_indexes() {
+        let mut trie = Trie::new();
+        trie.absorb(Trie::from(&["cat", "cow"][..]));
+        assert_eq!(trie, Trie::from(&["cat", "cow"][..]));
+        trie.absorb(Trie::new());
+        assert_eq!(trie.sequence_count(), 2);
+
+        // moved nodes go into the bloom filter
+        let mut trie = crate::TrieBuilder::new().bloom_filter(0.01).build();
+        trie.insert(&['c', 'a', 't']);
+        trie.absorb(Trie::from(&["cats", "dog"][..]));
+        assert!(trie.contains(&['c', 'a', 't', 's']) && trie.contains(&['d', 'o']));
+
+        let mut trie = crate::TrieBuilder::new().suffix_index(true).bloom_filter(0.01).build();
+        trie.insert(&['c', 'a', 't']);
+        trie.absorb(Trie::from(&["bat", "cat"][..]));
+        assert_eq!(trie.sequences(), vec![vec!['c', 'a', 't'], vec!['b', 'a', 't']]);
+        assert_eq!(trie.search_suffix(&['a', 't']).as_collected().len(), 2);
+
+        let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        trie.insert(&['c', 'a', 't']);
+        trie.absorb(Trie::from(&["CAT", "Dog"][..]));
+        assert_eq!(trie.sequences(), vec![vec!['c', 'a', 't'], vec!['d', 'o', 'g']]);
+        assert_eq!(trie.check_invariants(), Ok(()));
+    }
+}
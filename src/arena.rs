@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::Found;
+
+/// A node in an `ArenaTrie`, children are indexes into the arena.
+#[derive(Debug, Clone)]
+struct ArenaNode<T> {
+    val: T,
+    children: Vec<u32>,
+    terminal: bool,
+}
+
+/// A trie backed by a `Vec` of nodes linked by `u32` indexes.
+///
+/// Only the first element of a sequence is hashed, to find its start, every
+/// later step is a scan of the current node's (usually few) children, so no
+/// prefix is ever hashed. Slots freed by `remove` are reused by later inserts.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_trie::ArenaTrie;
+/// let mut trie = ArenaTrie::new();
+/// trie.insert(&['c', 'a', 't']);
+/// trie.insert(&['c', 'o', 'w']);
+///
+/// assert!(trie.contains(&['c', 'o']));
+/// assert_eq!(
+///     trie.search(&['c']).as_collected().as_slice(),
+///     &[ ['c', 'a', 't'], ['c', 'o', 'w'] ]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ArenaTrie<T> {
+    nodes: Vec<Option<ArenaNode<T>>>,
+    /// Indexes of `None` slots in `nodes`.
+    free: Vec<u32>,
+    /// Start indexes in insertion order.
+    starts: Vec<u32>,
+    start_map: HashMap<T, u32>,
+    /// Number of live nodes.
+    len: usize,
+}
+
+impl<T> Default for ArenaTrie<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            starts: Vec::new(),
+            start_map: HashMap::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> ArenaTrie<T>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    fn node(&self, idx: u32) -> &ArenaNode<T> {
+        self.nodes[idx as usize].as_ref().expect("index of a live node")
+    }
+
+    fn node_mut(&mut self, idx: u32) -> &mut ArenaNode<T> {
+        self.nodes[idx as usize].as_mut().expect("index of a live node")
+    }
+
+    fn alloc(&mut self, val: T) -> u32 {
+        let node = Some(ArenaNode { val, children: Vec::new(), terminal: false });
+        self.len += 1;
+        match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx as usize] = node;
+                idx
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    fn child(&self, idx: u32, val: &T) -> Option<u32> {
+        self.node(idx).children.iter().copied().find(|c| self.node(*c).val == *val)
+    }
+
+    /// The indexes of the nodes along `seq`, stops at the first missing element.
+    fn path(&self, seq: &[T]) -> Vec<u32> {
+        let mut path = Vec::with_capacity(seq.len());
+        if let Some(first) = seq.first() {
+            let mut idx = match self.start_map.get(first) {
+                Some(idx) => *idx,
+                None => return path,
+            };
+            path.push(idx);
+            for val in &seq[1..] {
+                idx = match self.child(idx, val) {
+                    Some(idx) => idx,
+                    None => break,
+                };
+                path.push(idx);
+            }
+        }
+        path
+    }
+
+    /// Inserts `seq` returning `true` if it wasn't already stored, like
+    /// `Trie::insert`.
+    pub fn insert(&mut self, seq: &[T]) -> bool {
+        let first = match seq.first() {
+            Some(first) => first,
+            None => return false,
+        };
+        let mut idx = match self.start_map.get(first) {
+            Some(idx) => *idx,
+            None => {
+                let idx = self.alloc(first.clone());
+                self.starts.push(idx);
+                self.start_map.insert(first.clone(), idx);
+                idx
+            }
+        };
+        for val in &seq[1..] {
+            idx = match self.child(idx, val) {
+                Some(idx) => idx,
+                None => {
+                    let child = self.alloc(val.clone());
+                    self.node_mut(idx).children.push(child);
+                    child
+                }
+            };
+        }
+        let node = self.node_mut(idx);
+        !std::mem::replace(&mut node.terminal, true)
+    }
+
+    /// Returns `true` if the path `seq` exists, like `Trie::contains`.
+    pub fn contains(&self, seq: &[T]) -> bool {
+        !seq.is_empty() && self.path(seq).len() == seq.len()
+    }
+
+    /// Returns all of the stored sequences starting with `prefix` depth first.
+    pub fn search(&self, prefix: &[T]) -> Found<T> {
+        let mut found = Found::new();
+        let path = self.path(prefix);
+        if prefix.is_empty() || path.len() != prefix.len() {
+            return found;
+        }
+        found.extend(prefix.iter().cloned());
+        self._search(path[path.len() - 1], &mut found);
+        found
+    }
+
+    fn _search(&self, idx: u32, found: &mut Found<T>) {
+        let node = self.node(idx);
+        if node.terminal {
+            found.branch_end();
+        }
+        for child in node.children.iter() {
+            found.push_val(self.node(*child).val.clone());
+            self._search(*child, found);
+            found.branch_split();
+        }
+    }
+
+    /// Removes `seq` returning `true` if it was stored, nodes left on no other
+    /// sequence's path are freed.
+    pub fn remove(&mut self, seq: &[T]) -> bool {
+        let path = self.path(seq);
+        match path.last() {
+            Some(last) if path.len() == seq.len() && self.node(*last).terminal => {
+                self.node_mut(*last).terminal = false;
+            }
+            _ => return false,
+        }
+        // free from the end until a node is still needed
+        for (i, idx) in path.iter().enumerate().rev() {
+            let node = self.node(*idx);
+            if node.terminal || !node.children.is_empty() {
+                break;
+            }
+            self.nodes[*idx as usize] = None;
+            self.free.push(*idx);
+            self.len -= 1;
+            if i == 0 {
+                self.starts.retain(|s| s != idx);
+                self.start_map.remove(&seq[0]);
+            } else {
+                self.node_mut(path[i - 1]).children.retain(|c| c != idx);
+            }
+        }
+        true
+    }
+
+    /// Iterates over every stored sequence in the same order as
+    /// `Trie::sequences`, walking the arena by index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::ArenaTrie;
+    /// let mut trie = ArenaTrie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'a']);
+    ///
+    /// let mut iter = trie.iter();
+    /// assert_eq!(iter.next(), Some(vec!['c', 'a']));
+    /// assert_eq!(iter.next(), Some(vec!['c', 'a', 't']));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter(&self) -> ArenaIter<'_, T> {
+        ArenaIter {
+            trie: self,
+            stack: self.starts.iter().rev().map(|idx| (1, *idx)).collect(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Returns every stored sequence in the same order as `Trie::sequences`.
+    pub fn sequences(&self) -> Vec<Vec<T>> {
+        self.iter().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.starts.clear();
+        self.start_map.clear();
+        self.len = 0;
+    }
+}
+
+/// Every stored sequence of an `ArenaTrie` depth first, made by
+/// `ArenaTrie::iter`.
+#[derive(Debug, Clone)]
+pub struct ArenaIter<'a, T> {
+    trie: &'a ArenaTrie<T>,
+    /// `(depth, index)` of the nodes still to visit.
+    stack: Vec<(usize, u32)>,
+    /// The path to the last visited node.
+    path: Vec<T>,
+}
+
+impl<'a, T> Iterator for ArenaIter<'a, T>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    type Item = Vec<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((depth, idx)) = self.stack.pop() {
+            let node = self.trie.node(idx);
+            self.path.truncate(depth - 1);
+            self.path.push(node.val.clone());
+            self.stack.extend(node.children.iter().rev().map(|c| (depth + 1, *c)));
+            if node.terminal {
+                return Some(self.path.clone());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Trie;
+
+    fn words(path: &str) -> Vec<Vec<char>> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect())
+            .collect()
+    }
+
+    #[test]
+    fn arena_insert_search_remove() {
+        let mut trie = ArenaTrie::new();
+        assert!(trie.insert(&['c', 'a', 't']));
+        assert!(trie.insert(&['c', 'a', 'r', 't']));
+        assert!(trie.insert(&['c', 'o', 'w']));
+        assert!(!trie.insert(&['c', 'o', 'w']));
+        assert!(!trie.insert(&[]));
+        assert_eq!(trie.len(), 7);
+        assert_eq!(
+            trie.search(&['c']).as_collected(),
+            vec![&['c', 'a', 't'][..], &['c', 'a', 'r', 't'], &['c', 'o', 'w']]
+        );
+
+        assert!(!trie.remove(&['c', 'a']));
+        assert!(trie.remove(&['c', 'a', 'r', 't']));
+        assert!(!trie.contains(&['c', 'a', 'r']));
+        assert_eq!(trie.len(), 5);
+
+        // freed slots are reused
+        trie.insert(&['c', 'a', 'b']);
+        assert_eq!(trie.nodes.len(), 7);
+
+        assert!(trie.remove(&['c', 'o', 'w']));
+        assert!(trie.remove(&['c', 'a', 't']));
+        assert!(trie.remove(&['c', 'a', 'b']));
+        assert!(trie.is_empty());
+        assert!(trie.starts.is_empty());
+    }
+
+    #[test]
+    fn arena_matches_trie() {
+        let words = words("data/1984.txt");
+        let mut arena = ArenaTrie::new();
+        let mut trie = Trie::new();
+        for w in words.iter() {
+            assert_eq!(arena.insert(w), trie.insert(w));
+        }
+        assert_eq!(arena.len(), trie.len);
+        assert_eq!(arena.sequences(), trie.sequences());
+        assert!(arena.iter().eq(trie.sequences()));
+        for w in words.iter() {
+            assert!(arena.contains(w));
+        }
+        for w in words.iter().step_by(50) {
+            assert_eq!(arena.search(w).as_collected(), trie.search(w).as_collected());
+        }
+
+        for w in words.iter().step_by(2) {
+            assert_eq!(arena.remove(w), trie.remove(w));
+        }
+        assert_eq!(arena.len(), trie.len);
+        assert_eq!(arena.sequences(), trie.sequences());
+    }
+}
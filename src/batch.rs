@@ -0,0 +1,263 @@
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+
+use crate::key::sequence_key;
+use crate::{PreHashedMap, Trie};
+
+/// One change in a batch given to `Trie::apply_batch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op<T> {
+    Insert(Vec<T>),
+    /// Fails the batch unless the sequence is stored when the op is reached.
+    Remove(Vec<T>),
+}
+
+/// What a successful `Trie::apply_batch` changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    /// Inserts of a sequence that wasn't already stored.
+    pub inserted: usize,
+    pub removed: usize,
+}
+
+/// Why `Trie::apply_batch` rejected a batch, `index` is the position of the
+/// first failing op.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchError {
+    /// An op with an empty sequence.
+    Empty { index: usize },
+    /// A `Remove` of a sequence that isn't stored at that point in the batch.
+    NotStored { index: usize },
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::Empty { index } => write!(f, "op {} has an empty sequence", index),
+            BatchError::NotStored { index } => write!(f, "op {} removes a sequence that isn't stored", index),
+        }
+    }
+}
+
+impl Error for BatchError {}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default + Clone,
+{
+    /// Applies every op in order or, if any of them fails, none of them.
+    ///
+    /// The whole batch is checked before the trie is touched, against an
+    /// overlay of which sequences the earlier ops store or remove, so a
+    /// rejected batch costs no copying. A trie made by `with_max_sequences` may
+    /// evict a sequence part way through, it applies the batch to a copy that
+    /// replaces the trie on success.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::{BatchError, Op, Trie};
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    ///
+    /// let ops = [Op::Insert(vec!['c', 'o', 'w']), Op::Remove(vec!['c', 'a', 'r'])];
+    /// assert_eq!(trie.apply_batch(&ops), Err(BatchError::NotStored { index: 1 }));
+    /// assert!(!trie.contains(&['c', 'o', 'w']));
+    ///
+    /// let ops = [Op::Insert(vec!['c', 'o', 'w']), Op::Remove(vec!['c', 'a', 't'])];
+    /// let summary = trie.apply_batch(&ops).unwrap();
+    /// assert_eq!((summary.inserted, summary.removed), (1, 1));
+    /// assert_eq!(trie.sequences(), vec![vec!['c', 'o', 'w']]);
+    /// ```
+    pub fn apply_batch(&mut self, ops: &[Op<T>]) -> Result<BatchSummary, BatchError> {
+        let mut applied = Vec::new();
+        if self.lru.is_some() {
+            // the copy holds the observer so evictions are recorded for it
+            let mut copy = self.clone();
+            copy.observer = self.observer.take();
+            match copy.apply_ops(ops, &mut applied) {
+                Ok(summary) => {
+                    *self = copy;
+                    self.notify_batch(ops, applied);
+                    return Ok(summary);
+                }
+                Err(err) => {
+                    self.observer = copy.observer.take();
+                    return Err(err);
+                }
+            }
+        }
+        self.check_batch(ops)?;
+        let summary = self.apply_ops(ops, &mut applied)?;
+        self.notify_batch(ops, applied);
+        Ok(summary)
+    }
+
+    /// Tells the observer about each op once the whole batch is applied,
+    /// `applied` holds whether each op changed the trie and what it evicted.
+    fn notify_batch(&mut self, ops: &[Op<T>], applied: Vec<(bool, Vec<Vec<T>>)>) {
+        if self.observer.is_none() {
+            return;
+        }
+        for (op, (changed, evicted)) in ops.iter().zip(applied) {
+            match op {
+                Op::Insert(seq) => self.notify_insert(seq, changed),
+                Op::Remove(seq) => self.notify_remove(seq, changed),
+            }
+            for seq in evicted {
+                self.notify_remove(&seq, true);
+            }
+        }
+    }
+
+    /// Fails like `apply_ops` would without changing anything, tracking
+    /// whether each sequence the batch touches is stored by its key.
+    fn check_batch(&self, ops: &[Op<T>]) -> Result<(), BatchError> {
+        let mut overlay = PreHashedMap::default();
+        for (index, op) in ops.iter().enumerate() {
+            let (seq, insert) = match op {
+                Op::Insert(seq) => (seq, true),
+                Op::Remove(seq) => (seq, false),
+            };
+            let seq = &*self.normalize(seq);
+            if seq.is_empty() {
+                return Err(BatchError::Empty { index });
+            }
+            // an insert `max_sequence_len` rejects changes nothing
+            let seq = match self.limited(seq) {
+                Some(seq) if insert => seq,
+                None if insert => continue,
+                _ => seq,
+            };
+            let key = sequence_key(seq);
+            let stored = *overlay.entry(key).or_insert_with(|| {
+                self.children.get(&key).is_some_and(|node| node.is_terminal())
+            });
+            if !insert && !stored {
+                return Err(BatchError::NotStored { index });
+            }
+            overlay.insert(key, insert);
+        }
+        Ok(())
+    }
+
+    /// Applies `ops` in order stopping at the first failure, earlier ops stay
+    /// applied. Whether each op changed the trie is pushed to `applied` with
+    /// the sequences it evicted.
+    fn apply_ops(
+        &mut self,
+        ops: &[Op<T>],
+        applied: &mut Vec<(bool, Vec<Vec<T>>)>,
+    ) -> Result<BatchSummary, BatchError> {
+        let mut summary = BatchSummary::default();
+        for (index, op) in ops.iter().enumerate() {
+            match op {
+                Op::Insert(seq) | Op::Remove(seq) if seq.is_empty() => {
+                    return Err(BatchError::Empty { index });
+                }
+                Op::Insert(seq) => {
+                    let inserted = self.insert_unobserved(seq);
+                    if inserted {
+                        summary.inserted += 1;
+                    }
+                    applied.push((inserted, self.take_evicted()));
+                }
+                Op::Remove(seq) => {
+                    if !self.remove_unobserved(seq).0 {
+                        return Err(BatchError::NotStored { index });
+                    }
+                    summary.removed += 1;
+                    applied.push((true, Vec::new()));
+                }
+            }
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn word(w: &str) -> Vec<char> {
+        w.chars().collect()
+    }
+
+    fn encoded(trie: &Trie<char>) -> Vec<u8> {
+        let mut out = Vec::new();
+        trie.write_binary(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn batch_failing_op_changes_nothing() {
+        let mut trie = Trie::new();
+        for w in ["cat", "cart", "cow"].iter() {
+            trie.insert(&word(w));
+        }
+        let before = trie.clone();
+        let bytes = encoded(&trie);
+
+        let ops = vec![
+            Op::Insert(word("car")),
+            Op::Remove(word("cart")),
+            Op::Remove(word("cart")),
+            Op::Insert(word("dog")),
+        ];
+        assert_eq!(trie.apply_batch(&ops), Err(BatchError::NotStored { index: 2 }));
+        assert_eq!(encoded(&trie), bytes);
+        assert_eq!(format!("{:?}", trie), format!("{:?}", before));
+        assert_eq!(trie.node_count(), before.node_count());
+
+        let ops = vec![Op::Remove(word("cow")), Op::Insert(vec![]), Op::Insert(word("dog"))];
+        assert_eq!(trie.apply_batch(&ops), Err(BatchError::Empty { index: 1 }));
+        assert_eq!(encoded(&trie), bytes);
+    }
+
+    #[test]
+    fn batch_sees_earlier_ops() {
+        let mut trie = Trie::new();
+        trie.insert(&word("cat"));
+
+        // removing what the batch inserted, then inserting it again
+        let ops = vec![
+            Op::Insert(word("cow")),
+            Op::Remove(word("cow")),
+            Op::Insert(word("cat")),
+            Op::Remove(word("cat")),
+            Op::Insert(word("cat")),
+        ];
+        assert_eq!(trie.apply_batch(&ops), Ok(BatchSummary { inserted: 2, removed: 2 }));
+        assert_eq!(trie.sequences(), vec![word("cat")]);
+        assert!(trie.check_invariants().is_ok());
+        assert_eq!(trie.apply_batch(&[]), Ok(BatchSummary::default()));
+    }
+
+    #[test]
+    fn batch_normalized() {
+        let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        trie.insert(&word("Cat"));
+        let ops = vec![Op::Remove(word("CAT")), Op::Insert(word("COW"))];
+        assert!(trie.apply_batch(&ops).is_ok());
+        assert_eq!(trie.sequences(), vec![word("cow")]);
+    }
+
+    #[test]
+    fn batch_with_max_sequences() {
+        let mut trie = Trie::with_max_sequences(2);
+        trie.insert(&word("cat"));
+        trie.insert(&word("cow"));
+        let bytes = encoded(&trie);
+
+        // inserting "dog" evicts "cat" so removing it fails
+        let ops = vec![Op::Insert(word("dog")), Op::Remove(word("cat"))];
+        assert_eq!(trie.apply_batch(&ops), Err(BatchError::NotStored { index: 1 }));
+        assert_eq!(encoded(&trie), bytes);
+
+        let ops = vec![Op::Insert(word("dog")), Op::Remove(word("cow"))];
+        assert_eq!(trie.apply_batch(&ops), Ok(BatchSummary { inserted: 1, removed: 1 }));
+        assert_eq!(trie.sequences(), vec![word("dog")]);
+    }
+}
@@ -0,0 +1,312 @@
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, Read, Write};
+
+use crate::key::child_key;
+use crate::node::Node;
+use crate::Trie;
+
+const MAGIC: &[u8; 4] = b"FTRI";
+const VERSION: u8 = 1;
+const TERMINAL: u8 = 0b1;
+
+/// An element that can be written to and read from the binary format used by
+/// `Trie::write_binary`.
+pub trait BinaryElement: Sized {
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()>;
+    fn read_from<R: Read>(r: &mut R) -> Result<Self, DecodeError>;
+}
+
+macro_rules! binary_int {
+    ($($int:ty),*) => {$(
+        impl BinaryElement for $int {
+            fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                w.write_all(&self.to_le_bytes())
+            }
+
+            fn read_from<R: Read>(r: &mut R) -> Result<Self, DecodeError> {
+                let mut buf = [0; std::mem::size_of::<$int>()];
+                r.read_exact(&mut buf)?;
+                Ok(<$int>::from_le_bytes(buf))
+            }
+        }
+    )*};
+}
+
+binary_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl BinaryElement for char {
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u32).write_to(w)
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self, DecodeError> {
+        std::char::from_u32(u32::read_from(r)?)
+            .ok_or(DecodeError::Corrupt("invalid char"))
+    }
+}
+
+/// Length prefixed UTF-8.
+impl BinaryElement for String {
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (self.len() as u64).write_to(w)?;
+        w.write_all(self.as_bytes())
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self, DecodeError> {
+        let len = u64::read_from(r)?;
+        let mut buf = Vec::new();
+        // `take` so a corrupt length can't make us allocate it up front
+        r.take(len).read_to_end(&mut buf)?;
+        if buf.len() as u64 != len {
+            return Err(DecodeError::Truncated);
+        }
+        String::from_utf8(buf).map_err(|_| DecodeError::Corrupt("invalid UTF-8"))
+    }
+}
+
+/// The ways reading a binary trie can fail.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The input doesn't start with the format's magic bytes.
+    BadMagic,
+    /// Written by a newer, unknown version of the format.
+    UnsupportedVersion(u8),
+    /// The input ended before the trie was complete.
+    Truncated,
+    /// The bytes don't describe a valid trie.
+    Corrupt(&'static str),
+    Io(io::Error),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::BadMagic => write!(f, "not a binary trie, bad magic bytes"),
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            DecodeError::Truncated => write!(f, "input ended unexpectedly"),
+            DecodeError::Corrupt(why) => write!(f, "corrupt trie: {}", why),
+            DecodeError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            DecodeError::Truncated
+        } else {
+            DecodeError::Io(err)
+        }
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: BinaryElement + Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Writes the trie in a compact binary format, a header followed by every
+    /// node in depth first order so `read_binary` can rebuild it in one pass.
+    ///
+    /// The normalizer, if any, and sequence weights are not written.
+    pub fn write_binary<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let mut nodes = Vec::with_capacity(self.children.len());
+        let mut stack = self.starts.iter()
+            .rev()
+            .filter_map(|key| self.children.get(key))
+            .collect::<Vec<_>>();
+        while let Some(node) = stack.pop() {
+            nodes.push(node);
+            stack.extend(node.children.iter().rev().map(|key| &self.children[key]));
+        }
+
+        w.write_all(MAGIC)?;
+        VERSION.write_to(&mut w)?;
+        (nodes.len() as u64).write_to(&mut w)?;
+        (self.starts.len() as u64).write_to(&mut w)?;
+        for node in nodes {
+            node.val.write_to(&mut w)?;
+            let flags = if node.terminal { TERMINAL } else { 0 };
+            flags.write_to(&mut w)?;
+            (node.children.len() as u32).write_to(&mut w)?;
+        }
+        w.flush()
+    }
+}
+
+impl<T> Trie<T>
+where
+    T: BinaryElement + Eq + Hash + Clone + Debug,
+{
+    /// Reads a trie written by `write_binary`. Truncated or corrupt input is
+    /// reported as a `DecodeError`.
+    pub fn read_binary<R: Read>(mut r: R) -> Result<Self, DecodeError> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        let version = u8::read_from(&mut r)?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let node_count = u64::read_from(&mut r)?;
+        let mut starts_left = u64::read_from(&mut r)?;
+
+        let mut trie = Trie::new();
+        // (key, children still to be read) for each node on the current path
+        let mut stack: Vec<(u64, u32)> = Vec::new();
+        loop {
+            while let Some((_, 0)) = stack.last() {
+                stack.pop();
+            }
+            match stack.last_mut() {
+                Some((_, left)) => *left -= 1,
+                None if starts_left == 0 => break,
+                None => starts_left -= 1,
+            }
+            if trie.children.len() as u64 == node_count {
+                return Err(DecodeError::Corrupt("more nodes than the header claims"));
+            }
+
+            let val = T::read_from(&mut r)?;
+            let flags = u8::read_from(&mut r)?;
+            if flags & !TERMINAL != 0 {
+                return Err(DecodeError::Corrupt("unknown node flags"));
+            }
+            let terminal = flags & TERMINAL != 0;
+            let child_count = u32::read_from(&mut r)?;
+            if !terminal && child_count == 0 {
+                return Err(DecodeError::Corrupt("a leaf that ends no sequence"));
+            }
+
+            let key = child_key(stack.last().map(|(parent, _)| *parent), &val);
+            match stack.last() {
+                Some((parent, _)) => {
+                    let parent = trie.children.get_mut(parent).expect("parent was inserted");
+                    parent.children.push(key);
+                    parent.child_size += 1;
+                }
+                None => trie.starts.push(key),
+            }
+            let node = Node {
+                val,
+                children: Vec::new(),
+                child_size: 0,
+                terminal,
+                descendant_terminals: 0,
+                weight: 0,
+            };
+            if trie.children.insert(key, node).is_some() {
+                return Err(DecodeError::Corrupt("duplicate node"));
+            }
+            trie.len += 1;
+            stack.push((key, child_count));
+            if terminal {
+                trie.seq_len += 1;
+                for (key, _) in stack.iter() {
+                    let node = trie.children.get_mut(key).expect("path was inserted");
+                    node.descendant_terminals += 1;
+                }
+            }
+        }
+
+        if trie.children.len() as u64 != node_count {
+            return Err(DecodeError::Corrupt("fewer nodes than the header claims"));
+        }
+        Ok(trie)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn words() -> Trie<char> {
+        let mut trie = Trie::new();
+        for word in std::fs::read_to_string("words.txt").unwrap().split_whitespace() {
+            trie.insert_str(word);
+        }
+        trie
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let trie = words();
+        let mut buf = Vec::new();
+        trie.write_binary(&mut buf).unwrap();
+
+        let read = Trie::<char>::read_binary(buf.as_slice()).unwrap();
+        assert_eq!(read, trie);
+        assert_eq!(read.sequences(), trie.sequences());
+        assert_eq!(read.len, trie.len);
+
+        let mut tokens = Trie::new();
+        tokens.insert(&["let".to_string(), "x".to_string()]);
+        tokens.insert(&["let".to_string(), "mut".to_string(), "y".to_string()]);
+        let mut buf = Vec::new();
+        tokens.write_binary(&mut buf).unwrap();
+        assert_eq!(Trie::<String>::read_binary(buf.as_slice()).unwrap(), tokens);
+    }
+
+    #[test]
+    fn binary_bad_input() {
+        let mut buf = Vec::new();
+        words().write_binary(&mut buf).unwrap();
+
+        for len in (0..buf.len()).step_by(97) {
+            assert!(Trie::<char>::read_binary(&buf[..len]).is_err());
+        }
+        assert!(matches!(
+            Trie::<char>::read_binary(&b"garbage in, garbage out"[..]),
+            Err(DecodeError::BadMagic)
+        ));
+
+        // random bytes after a valid header
+        let mut garbage = buf[..5].to_vec();
+        let mut rand = crate::tests::xorshift(0x2545_f491);
+        for _ in 0..4096 {
+            garbage.push(rand() as u8);
+        }
+        assert!(Trie::<char>::read_binary(garbage.as_slice()).is_err());
+        assert!(Trie::<String>::read_binary(garbage.as_slice()).is_err());
+    }
+
+    #[test]
+    fn binary_bad_flags() {
+        let mut trie = Trie::new();
+        trie.insert(&['a']);
+        let mut buf = Vec::new();
+        trie.write_binary(&mut buf).unwrap();
+        // the only node's flags come before its u32 child count
+        let flags = buf.len() - 5;
+        assert_eq!(buf[flags], TERMINAL);
+        assert_eq!(Trie::<char>::read_binary(buf.as_slice()).unwrap(), trie);
+
+        // not terminal and no children
+        buf[flags] = 0;
+        assert!(matches!(
+            Trie::<char>::read_binary(buf.as_slice()),
+            Err(DecodeError::Corrupt("a leaf that ends no sequence"))
+        ));
+        // bits that mean nothing yet
+        for bits in [0b10, 0b11, 0x80, 0xff].iter() {
+            buf[flags] = *bits;
+            assert!(matches!(
+                Trie::<char>::read_binary(buf.as_slice()),
+                Err(DecodeError::Corrupt("unknown node flags"))
+            ));
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::Trie;
+
+/// The smallest number of keys a filter is sized for.
+const MIN_CAPACITY: usize = 64;
+
+/// A Bloom filter over the keys of every node, set by
+/// `TrieBuilder::bloom_filter`. A key the filter doesn't have is certainly not
+/// in the trie, one it has usually is.
+///
+/// Bits can't be unset so removed keys stay in the filter as stale keys, once
+/// they outnumber the live ones it is rebuilt from the map. It is also rebuilt
+/// twice as big when the trie outgrows it.
+#[derive(Debug, Clone)]
+pub(crate) struct Bloom {
+    bits: Vec<u64>,
+    hashes: u32,
+    rate: f64,
+    /// Keys the filter was sized for.
+    capacity: usize,
+    /// Keys removed from the trie but still set.
+    stale: usize,
+}
+
+impl Bloom {
+    /// An empty filter for `capacity` keys with a `rate` chance of a false
+    /// positive when full.
+    pub(crate) fn new(rate: f64, capacity: usize) -> Self {
+        let capacity = capacity.max(MIN_CAPACITY);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(capacity as f64) * rate.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let hashes = (bits / capacity as f64 * ln2).round().max(1.0) as u32;
+        let words = (bits as usize).div_ceil(64);
+        Self { bits: vec![0; words], hashes, rate, capacity, stale: 0 }
+    }
+
+    /// The bits of `key`, double hashing the key with a mix of itself.
+    fn positions(&self, key: u64) -> impl Iterator<Item = usize> {
+        let len = self.bits.len() as u64 * 64;
+        let step = mix(key) | 1;
+        (0..self.hashes as u64).map(move |i| (key.wrapping_add(i.wrapping_mul(step)) % len) as usize)
+    }
+
+    pub(crate) fn insert(&mut self, key: u64) {
+        for bit in self.positions(key).collect::<Vec<_>>() {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// `false` only if `key` was never inserted.
+    pub(crate) fn might_contain(&self, key: u64) -> bool {
+        self.positions(key).all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+        self.stale = 0;
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.bits.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+/// splitmix64's finalizer, the second hash is this of the key.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Adds the keys of newly made nodes to the filter, growing it once the
+    /// trie has more nodes than it was sized for.
+    pub(crate) fn bloom_insert(&mut self, keys: &[u64]) {
+        let len = self.len;
+        let bloom = match self.bloom.as_mut() {
+            Some(bloom) => bloom,
+            None => return,
+        };
+        if len + bloom.stale > bloom.capacity {
+            self.rebuild_bloom();
+        } else {
+            keys.iter().for_each(|key| bloom.insert(*key));
+        }
+    }
+
+    /// Notes that `removed` nodes were removed, rebuilding the filter once
+    /// more of its keys are stale than live.
+    pub(crate) fn bloom_forget(&mut self, removed: usize) {
+        let len = self.len;
+        if let Some(bloom) = self.bloom.as_mut() {
+            bloom.stale += removed;
+            if bloom.stale > len.max(MIN_CAPACITY) {
+                self.rebuild_bloom();
+            }
+        }
+    }
+
+    /// Refills the filter from the map, sized for twice the current nodes.
+    pub(crate) fn rebuild_bloom(&mut self) {
+        if let Some(bloom) = self.bloom.as_mut() {
+            *bloom = Bloom::new(bloom.rate, self.len * 2);
+            self.children.keys().for_each(|key| bloom.insert(*key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::key::sequence_key;
+    use crate::TrieBuilder;
+
+    fn words(path: &str) -> Vec<Vec<char>> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect())
+            .collect()
+    }
+
+    /// Every node's key must be in the filter, a miss there is a false negative.
+    fn no_false_negatives(trie: &Trie<char>) {
+        let bloom = trie.bloom.as_ref().unwrap();
+        assert!(trie.children.keys().all(|key| bloom.might_contain(*key)));
+    }
+
+    #[test]
+    fn bloom_no_false_negatives() {
+        let words = words("data/1984.txt");
+        let mut trie = TrieBuilder::new().bloom_filter(0.01).build();
+        let plain = TrieBuilder::new().build_from_iter(words.iter());
+        assert!(plain.bloom.is_none());
+        for w in words.iter() {
+            trie.insert(w);
+        }
+        no_false_negatives(&trie);
+        for w in words.iter() {
+            assert!(trie.contains(w));
+            assert!(trie.contains(&w[..1]));
+        }
+
+        // removing enough to rebuild the filter several times
+        let mut plain = plain;
+        for w in words.iter().step_by(2) {
+            assert_eq!(trie.remove(w), plain.remove(w));
+        }
+        no_false_negatives(&trie);
+        for w in words.iter() {
+            assert_eq!(trie.contains(w), plain.contains(w));
+        }
+        trie.clear();
+        assert!(!trie.contains(&words[0]));
+        trie.insert(&words[0]);
+        assert!(trie.contains(&words[0]));
+    }
+
+    #[test]
+    fn bloom_rejects_misses() {
+        let trie = TrieBuilder::new()
+            .bloom_filter(0.01)
+            .build_from_iter(words("data/1984.txt").iter());
+        let bloom = trie.bloom.as_ref().unwrap();
+
+        let misses = (0..10_000u32)
+            .map(|i| format!("zq{}", i).chars().collect::<Vec<_>>())
+            .filter(|seq| !trie.contains(seq))
+            .collect::<Vec<_>>();
+        let passed = misses.iter().filter(|seq| bloom.might_contain(sequence_key(seq))).count();
+        // at most the configured rate with some slack
+        assert!(passed < misses.len() / 50, "{} of {}", passed, misses.len());
+    }
+
+    #[test]
+    fn bloom_grows_and_clones() {
+        let mut trie = TrieBuilder::new().capacity(1).bloom_filter(0.05).build();
+        for i in 0..2000u32 {
+            trie.insert(&i.to_string().chars().collect::<Vec<_>>());
+        }
+        assert!(trie.bloom.as_ref().unwrap().capacity >= trie.node_count());
+        no_false_negatives(&trie);
+
+        let copy = trie.clone();
+        no_false_negatives(&copy);
+        assert!(copy.contains(&['1', '9', '9', '9']));
+        assert!(!copy.contains(&['x']));
+    }
+}
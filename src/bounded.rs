@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::key::sequence_key;
+use crate::{PreHashedMap, Trie};
+
+/// Recency bookkeeping for a trie made with `Trie::with_max_sequences`.
+#[derive(Debug, Clone)]
+pub(crate) struct Lru<T> {
+    pub(crate) max: usize,
+    clock: u64,
+    /// Stored sequences by when they were last used, the first is the least
+    /// recently used.
+    order: BTreeMap<u64, Vec<T>>,
+    /// The stamp of each stored sequence by its terminal key.
+    stamps: PreHashedMap<u64, u64>,
+    /// Sequences evicted since the observer was last told, only kept while
+    /// the trie has one.
+    evicted: Vec<Vec<T>>,
+}
+
+impl<T: Clone> Lru<T> {
+    fn new(max: usize) -> Self {
+        Self { max, clock: 0, order: BTreeMap::new(), stamps: PreHashedMap::default(), evicted: Vec::new() }
+    }
+
+    /// Marks the sequence ending at `key` as the most recently used.
+    pub(crate) fn touch(&mut self, key: u64, seq: &[T]) {
+        self.clock += 1;
+        let seq = match self.stamps.insert(key, self.clock) {
+            Some(old) => self.order.remove(&old).expect("every stamp is ordered"),
+            None => seq.to_vec(),
+        };
+        self.order.insert(self.clock, seq);
+    }
+
+    /// Drops a removed sequence.
+    pub(crate) fn forget(&mut self, key: u64) {
+        if let Some(stamp) = self.stamps.remove(&key) {
+            self.order.remove(&stamp);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.order.clear();
+        self.stamps.clear();
+        self.evicted.clear();
+    }
+}
+
+impl<T> Trie<T>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    /// Creates an empty `Trie` holding at most `max` sequences, inserting one
+    /// more removes the least recently used. A sequence is used when it is
+    /// inserted or passed to `touch`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::with_max_sequences(2);
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'o', 'w']);
+    /// trie.touch(&['c', 'a', 't']);
+    /// trie.insert(&['d', 'o', 'g']);
+    ///
+    /// assert!(trie.contains(&['c', 'a', 't']));
+    /// assert!(!trie.contains(&['c', 'o', 'w']));
+    /// ```
+    pub fn with_max_sequences(max: usize) -> Self {
+        assert!(max > 0, "a bounded trie must hold at least one sequence");
+        Trie { lru: Some(Lru::new(max)), ..Trie::new() }
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// The most sequences the trie will hold, `None` if unbounded.
+    pub fn max_sequences(&self) -> Option<usize> {
+        self.lru.as_ref().map(|lru| lru.max)
+    }
+
+    /// Marks `seq` as recently used so it is evicted last, returns `false` if
+    /// it isn't stored. Lookups through `&self` can't count as a use so call
+    /// this on a hit to keep a sequence around.
+    pub fn touch(&mut self, seq: &[T]) -> bool {
+        let seq = &*self.normalize(seq);
+        if !self.node(seq).is_some_and(|node| node.is_terminal()) {
+            return false;
+        }
+        if let Some(lru) = self.lru.as_mut() {
+            lru.touch(sequence_key(seq), seq);
+        }
+        true
+    }
+
+    /// Records that the stored `seq` was just inserted and evicts the least
+    /// recently used sequences past the limit.
+    pub(crate) fn touch_inserted(&mut self, seq: &[T]) {
+        let max = match self.lru.as_mut() {
+            Some(lru) => {
+                lru.touch(sequence_key(seq), seq);
+                lru.max
+            }
+            None => return,
+        };
+        while self.seq_len > max {
+            let oldest = self.lru.as_ref()
+                .and_then(|lru| lru.order.values().next().cloned())
+                .expect("more sequences than the limit");
+            // stored sequences are normalized already
+            let (removed, _) = self.remove_normalized(&oldest);
+            assert!(removed, "the least recently used sequence is stored");
+            if self.observer.is_some() {
+                self.lru.as_mut().expect("only a bounded trie evicts").evicted.push(oldest);
+            }
+        }
+    }
+
+    /// The sequences evicted since this was last called.
+    pub(crate) fn take_evicted(&mut self) -> Vec<Vec<T>> {
+        self.lru.as_mut().map(|lru| std::mem::take(&mut lru.evicted)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Lru;
+    use crate::Trie;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn keeps_most_recent() {
+        let text = std::fs::read_to_string("data/1984.txt").unwrap();
+        let mut words = text.split_whitespace().collect::<Vec<_>>();
+        words.sort_unstable();
+        words.dedup();
+        let words = &words[..400];
+
+        let n = words.len() / 2;
+        let mut trie = Trie::with_max_sequences(n);
+        for word in words {
+            trie.insert(&chars(word));
+            assert!(trie.sequence_count() <= n);
+        }
+        assert_eq!(trie.check_invariants(), Ok(()));
+        assert_eq!(trie.sequence_count(), n);
+
+        let mut kept = trie.sequences()
+            .into_iter()
+            .map(|seq| seq.into_iter().collect::<String>())
+            .collect::<Vec<_>>();
+        kept.sort_unstable();
+        assert_eq!(kept, words[n..]);
+    }
+
+    #[test]
+    fn touch_and_remove() {
+        let mut trie = Trie::with_max_sequences(3);
+        assert_eq!(trie.max_sequences(), Some(3));
+        for word in ["car", "cart", "cab"].iter() {
+            trie.insert(&chars(word));
+        }
+        assert!(trie.touch(&chars("car")));
+        assert!(!trie.touch(&chars("ca")));
+
+        // "cart" is now the oldest
+        trie.insert(&chars("cow"));
+        assert!(!trie.contains(&chars("cart")));
+        assert!(trie.contains(&chars("car")));
+
+        // re-inserting counts as a use, removed sequences aren't evicted twice
+        trie.insert(&chars("cab"));
+        assert!(trie.remove(&chars("car")));
+        trie.insert(&chars("dog"));
+        trie.insert(&chars("dot"));
+        assert_eq!(trie.sequence_count(), 3);
+        assert_eq!(trie.check_invariants(), Ok(()));
+        assert!(!trie.contains(&chars("cow")));
+        assert!(trie.contains(&chars("cab")));
+
+        trie.clear();
+        trie.insert(&chars("a"));
+        assert_eq!(trie.sequences(), vec![vec!['a']]);
+        assert_eq!(trie.lru.as_ref().unwrap().order.len(), 1);
+    }
+
+    #[test]
+    fn evicts_without_normalizing_again() {
+        // normalizing a stored sequence again would look up another one
+        let mut trie = Trie::with_normalizer(|c: &char| ((*c as u8) + 1) as char);
+        trie.lru = Some(Lru::new(2));
+        for word in ["abc", "xy", "mn"].iter() {
+            trie.insert(&chars(word));
+        }
+        assert_eq!(trie.sequences(), vec![chars("yz"), chars("no")]);
+        assert_eq!(trie.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn unbounded_by_default() {
+        let mut trie = Trie::new();
+        assert_eq!(trie.max_sequences(), None);
+        trie.insert(&chars("cat"));
+        assert!(trie.touch(&chars("cat")));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_max() {
+        let _ = Trie::<char>::with_max_sequences(0);
+    }
+}
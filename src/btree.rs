@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+
+use crate::storage::Storage;
+use crate::{Found, Node};
+
+/// A trie for elements that are `Ord` but not `Hash`, every node is kept in a
+/// `BTreeMap` under its whole path.
+///
+/// Each node keeps its children's elements sorted, so the walks it shares with
+/// `Trie` give results in lexicographic order instead of insertion order. A
+/// child is found by pushing its element onto the parent's path, a sequence of
+/// length n costs n paths.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_trie::OrderedTrie;
+/// let mut trie = OrderedTrie::new();
+/// trie.insert(&['c', 'o', 'w']);
+/// trie.insert(&['c', 'a', 't']);
+///
+/// assert!(trie.contains(&['c', 'o']));
+/// assert_eq!(
+///     trie.search(&['c']).as_collected().as_slice(),
+///     &[ ['c', 'a', 't'], ['c', 'o', 'w'] ]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct OrderedTrie<T> {
+    nodes: BTreeMap<Vec<T>, Node<T, T>>,
+    /// The first element of every stored sequence, sorted.
+    starts: Vec<T>,
+    /// Number of stored sequences.
+    seq_len: usize,
+}
+
+impl<T> Default for OrderedTrie<T> {
+    fn default() -> Self {
+        Self { nodes: BTreeMap::new(), starts: Vec::new(), seq_len: 0 }
+    }
+}
+
+impl<T> OrderedTrie<T>
+where
+    T: Ord + Clone,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of stored sequences.
+    pub fn sequence_count(&self) -> usize {
+        self.seq_len
+    }
+
+    /// Inserts `seq` returning `true` if it wasn't already stored.
+    pub fn insert(&mut self, seq: &[T]) -> bool {
+        if seq.is_empty() || self.nodes.get(seq).is_some_and(|node| node.terminal) {
+            return false;
+        }
+        for len in 1..=seq.len() {
+            let val = &seq[len - 1];
+            let node = self.nodes.entry(seq[..len].to_vec()).or_insert_with(|| Node::new(val.clone()));
+            node.descendant_terminals += 1;
+            node.terminal |= len == seq.len();
+            if node.descendant_terminals > 1 {
+                continue;
+            }
+            // new, link it into its parent in order
+            let siblings = match len {
+                1 => &mut self.starts,
+                _ => {
+                    let parent = self.nodes.get_mut(&seq[..len - 1]).expect("parent was inserted");
+                    parent.child_size += 1;
+                    &mut parent.children
+                }
+            };
+            if let Err(idx) = siblings.binary_search(val) {
+                siblings.insert(idx, val.clone());
+            }
+        }
+        self.seq_len += 1;
+        true
+    }
+
+    /// Returns `true` if the path `seq` exists, like `Trie::contains`.
+    pub fn contains(&self, seq: &[T]) -> bool {
+        self.nodes.contains_key(seq)
+    }
+
+    /// Returns all of the stored sequences starting with `prefix` in
+    /// lexicographic order.
+    pub fn search(&self, prefix: &[T]) -> Found<T> {
+        self.nodes.search(prefix)
+    }
+
+    /// Returns the number of stored sequences that begin with `prefix`,
+    /// including `prefix` itself when it is stored.
+    pub fn count_prefix(&self, prefix: &[T]) -> usize {
+        self.nodes.node(prefix).map_or(0, |node| node.descendant_terminals)
+    }
+
+    /// Removes `seq` returning `true` if it was stored, paths left on no other
+    /// sequence's path are removed.
+    pub fn remove(&mut self, seq: &[T]) -> bool {
+        match self.nodes.get_mut(seq) {
+            Some(node) if node.terminal => node.terminal = false,
+            _ => return false,
+        }
+        self.seq_len -= 1;
+        for len in (1..=seq.len()).rev() {
+            let node = self.nodes.get_mut(&seq[..len]).expect("path of a stored sequence");
+            node.descendant_terminals -= 1;
+            if node.descendant_terminals > 0 {
+                continue;
+            }
+            // nothing stored below, only on the removed sequence's path
+            self.nodes.remove(&seq[..len]);
+            let siblings = match len {
+                1 => &mut self.starts,
+                _ => {
+                    let parent = self.nodes.get_mut(&seq[..len - 1]).expect("path of a stored sequence");
+                    parent.child_size -= 1;
+                    &mut parent.children
+                }
+            };
+            if let Ok(idx) = siblings.binary_search(&seq[len - 1]) {
+                siblings.remove(idx);
+            }
+        }
+        true
+    }
+
+    /// Returns every stored sequence in lexicographic order.
+    pub fn sequences(&self) -> Vec<Vec<T>> {
+        self.nodes.sequences(&self.starts)
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.starts.clear();
+        self.seq_len = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+    use std::fmt::Debug;
+
+    use super::*;
+    use crate::Trie;
+
+    /// Ordered by `f64::total_cmp`, there's no `Hash`.
+    #[derive(Debug, Clone, Copy)]
+    struct Float(f64);
+
+    impl PartialEq for Float {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl Eq for Float {}
+
+    impl PartialOrd for Float {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Float {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.total_cmp(&other.0)
+        }
+    }
+
+    fn floats(vals: &[f64]) -> Vec<Float> {
+        vals.iter().map(|v| Float(*v)).collect()
+    }
+
+    /// What the shared suite needs of a backend.
+    trait Backend<T> {
+        fn insert(&mut self, seq: &[T]) -> bool;
+        fn remove(&mut self, seq: &[T]) -> bool;
+        fn contains(&self, seq: &[T]) -> bool;
+        fn search(&self, prefix: &[T]) -> Found<T>;
+        fn count_prefix(&self, prefix: &[T]) -> usize;
+        fn node_count(&self) -> usize;
+        fn sequence_count(&self) -> usize;
+    }
+
+    impl Backend<char> for Trie<char> {
+        fn insert(&mut self, seq: &[char]) -> bool { Trie::insert(self, seq) }
+        fn remove(&mut self, seq: &[char]) -> bool { Trie::remove(self, seq) }
+        fn contains(&self, seq: &[char]) -> bool { Trie::contains(self, seq) }
+        fn search(&self, prefix: &[char]) -> Found<char> { Trie::search(self, prefix) }
+        fn count_prefix(&self, prefix: &[char]) -> usize { Trie::count_prefix(self, prefix) }
+        fn node_count(&self) -> usize { Trie::node_count(self) }
+        fn sequence_count(&self) -> usize { Trie::sequence_count(self) }
+    }
+
+    impl<T: Ord + Clone> Backend<T> for OrderedTrie<T> {
+        fn insert(&mut self, seq: &[T]) -> bool { OrderedTrie::insert(self, seq) }
+        fn remove(&mut self, seq: &[T]) -> bool { OrderedTrie::remove(self, seq) }
+        fn contains(&self, seq: &[T]) -> bool { OrderedTrie::contains(self, seq) }
+        fn search(&self, prefix: &[T]) -> Found<T> { OrderedTrie::search(self, prefix) }
+        fn count_prefix(&self, prefix: &[T]) -> usize { OrderedTrie::count_prefix(self, prefix) }
+        fn node_count(&self) -> usize { self.len() }
+        fn sequence_count(&self) -> usize { OrderedTrie::sequence_count(self) }
+    }
+
+    /// The insert, search and remove cases every backend must pass. Words go
+    /// in sorted so insertion order and lexicographic order agree.
+    fn insert_search_remove<T, B>(mut trie: B, elem: fn(char) -> T)
+    where
+        T: Clone + PartialEq + Debug,
+        B: Backend<T>,
+    {
+        let seq = |word: &str| word.chars().map(elem).collect::<Vec<_>>();
+        let seqs = |words: &[&str]| words.iter().map(|w| seq(w)).collect::<Vec<_>>();
+        let search = |trie: &B, prefix: &str| trie.search(&seq(prefix)).into_response_vec();
+
+        let words = ["c", "cab", "cabin", "cabs", "car", "carrot", "cart", "carts", "cat"];
+        for w in words.iter() {
+            assert!(trie.insert(&seq(w)));
+        }
+        assert!(!trie.insert(&seq("cab")));
+        assert!(!trie.insert(&[]));
+        assert_eq!(trie.sequence_count(), words.len());
+        assert_eq!(trie.node_count(), 13);
+
+        assert_eq!(search(&trie, "c"), seqs(&words));
+        assert_eq!(search(&trie, "ca"), seqs(&words[1..]));
+        assert_eq!(search(&trie, "car"), seqs(&["car", "carrot", "cart", "carts"]));
+        assert_eq!(search(&trie, "cabi"), seqs(&["cabin"]));
+        assert!(search(&trie, "cax").is_empty());
+        assert!(search(&trie, "").is_empty());
+        assert_eq!(trie.search(&seq("cab")).exact(), Some(&seq("cab")[..]));
+        assert_eq!(trie.search(&seq("ca")).exact(), None);
+        assert_eq!(trie.count_prefix(&seq("car")), 4);
+        assert!(trie.contains(&seq("carro")));
+        assert!(!trie.contains(&seq("carts!")));
+
+        assert!(!trie.remove(&seq("ca")));
+        assert!(!trie.remove(&seq("carte")));
+        assert!(trie.remove(&seq("car")));
+        assert!(!trie.remove(&seq("car")));
+        assert!(trie.contains(&seq("car")));
+        assert!(trie.remove(&seq("carrot")));
+        assert!(!trie.contains(&seq("carr")));
+        assert!(trie.remove(&seq("c")));
+        assert_eq!(search(&trie, "c"), seqs(&["cab", "cabin", "cabs", "cart", "carts", "cat"]));
+        assert_eq!(trie.count_prefix(&seq("c")), 6);
+
+        for w in ["cab", "cabin", "cabs", "cart", "carts", "cat"].iter() {
+            assert!(trie.remove(&seq(w)));
+        }
+        assert_eq!(trie.sequence_count(), 0);
+        assert_eq!(trie.node_count(), 0);
+        assert!(search(&trie, "c").is_empty());
+    }
+
+    #[test]
+    fn backends_insert_search_remove() {
+        insert_search_remove(Trie::new(), |c| c);
+        insert_search_remove(OrderedTrie::new(), |c| c);
+        // no `Hash`, only the ordered backend takes it
+        insert_search_remove(OrderedTrie::new(), |c| Float(c as u32 as f64));
+    }
+
+    #[test]
+    fn ordered_insert_search_remove() {
+        let mut trie = OrderedTrie::new();
+        assert!(trie.insert(&floats(&[1.5, 2.0, 3.0])));
+        assert!(trie.insert(&floats(&[1.5, -2.0])));
+        assert!(trie.insert(&floats(&[1.5, 2.0])));
+        assert!(!trie.insert(&floats(&[1.5, 2.0])));
+        assert!(!trie.insert(&[]));
+        assert!(trie.insert(&floats(&[0.5])));
+        assert_eq!(trie.len(), 5);
+        assert_eq!(trie.sequence_count(), 4);
+
+        assert_eq!(trie.search(&floats(&[1.5])).as_collected(), vec![
+            &floats(&[1.5, -2.0])[..], &floats(&[1.5, 2.0]), &floats(&[1.5, 2.0, 3.0]),
+        ]);
+        assert_eq!(trie.search(&floats(&[1.5, 2.0])).exact(), Some(&floats(&[1.5, 2.0])[..]));
+        assert_eq!(trie.search(&floats(&[1.5])).exact(), None);
+        assert!(trie.contains(&floats(&[1.5])));
+        assert!(!trie.contains(&floats(&[2.0])));
+        assert!(trie.search(&floats(&[9.0])).as_collected().is_empty());
+
+        assert!(!trie.remove(&floats(&[1.5])));
+        assert!(trie.remove(&floats(&[1.5, 2.0])));
+        assert!(trie.contains(&floats(&[1.5, 2.0])));
+        assert!(trie.remove(&floats(&[1.5, 2.0, 3.0])));
+        assert!(!trie.contains(&floats(&[1.5, 2.0])));
+        assert_eq!(trie.len(), 3);
+        assert!(trie.remove(&floats(&[1.5, -2.0])));
+        assert!(trie.remove(&floats(&[0.5])));
+        assert!(trie.is_empty());
+        assert_eq!(trie.sequence_count(), 0);
+    }
+
+    #[test]
+    fn ordered_matches_trie() {
+        let words = std::fs::read_to_string("data/1984.txt")
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut ordered = OrderedTrie::new();
+        let mut trie = Trie::new();
+        for w in words.iter() {
+            assert_eq!(ordered.insert(w), trie.insert(w));
+        }
+        let sorted = |mut seqs: Vec<Vec<char>>| {
+            seqs.sort();
+            seqs
+        };
+        assert_eq!(ordered.len(), trie.node_count());
+        assert_eq!(ordered.sequences(), sorted(trie.sequences()));
+        for w in words.iter().step_by(50) {
+            let found = ordered.search(w).into_response_vec();
+            assert_eq!(found, sorted(trie.search(w).into_response_vec()));
+        }
+
+        for w in words.iter().step_by(2) {
+            assert_eq!(ordered.remove(w), trie.remove(w));
+        }
+        assert_eq!(ordered.len(), trie.node_count());
+        assert_eq!(ordered.sequences(), sorted(trie.sequences()));
+        ordered.clear();
+        assert!(ordered.is_empty());
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+use crate::key::child_key;
+use crate::{Bloom, Budget, NoopBuildHasher, Normalizer, Trie};
+
+/// What `insert` does with a sequence longer than
+/// `TrieBuilder::max_sequence_len`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthPolicy {
+    /// Inserts the sequence cut to the maximum length.
+    Truncate,
+    /// Doesn't insert it, `insert` returns `false`.
+    Reject,
+}
+
+/// Configures how a `Trie` is made, `Trie::new` is the same as
+/// `TrieBuilder::new().build()`.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_trie::TrieBuilder;
+/// let mut words = vec!["Cat", "car", "cat", "cow"];
+/// words.sort();
+///
+/// let trie = TrieBuilder::new()
+///     .capacity(16)
+///     .normalizer(|c: &char| c.to_ascii_lowercase())
+///     .sorted_input(true)
+///     .counting(true)
+///     .build_from_iter(words.iter().map(|w| w.chars().collect::<Vec<_>>()));
+///
+/// assert_eq!(trie.sequence_count(), 3);
+/// assert_eq!(trie.weight(&['c', 'a', 't']), Some(2));
+/// ```
+pub struct TrieBuilder<T, S = NoopBuildHasher> {
+    capacity: usize,
+    hasher: S,
+    normalizer: Option<Normalizer<T>>,
+    sorted_input: bool,
+    counting: bool,
+    suffix_index: bool,
+    max_len: Option<(usize, LengthPolicy)>,
+    bloom_rate: Option<f64>,
+    budget: Budget,
+}
+
+impl<T, S: Default> Default for TrieBuilder<T, S> {
+    fn default() -> Self {
+        Self {
+            capacity: 0,
+            hasher: S::default(),
+            normalizer: None,
+            sorted_input: false,
+            counting: false,
+            suffix_index: false,
+            max_len: None,
+            bloom_rate: None,
+            budget: Budget::default(),
+        }
+    }
+}
+
+impl<T> TrieBuilder<T>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, S> TrieBuilder<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Hashes the node keys with `hasher` instead of passing them through.
+    ///
+    /// Node keys are already FNV hashes of their path so by default the map
+    /// uses them as is. Anyone who can choose the stored sequences can also
+    /// choose keys that land in the same bucket, a keyed hasher like
+    /// `RandomState` spreads them again at the cost of hashing every lookup.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::hash_map::RandomState;
+    /// use ecs_trie::TrieBuilder;
+    ///
+    /// let mut trie = TrieBuilder::new().hasher(RandomState::new()).build();
+    /// trie.insert(&['c', 'a', 't']);
+    /// assert!(trie.contains(&['c', 'a', 't']));
+    /// ```
+    pub fn hasher<H>(self, hasher: H) -> TrieBuilder<T, H>
+    where
+        H: BuildHasher + Default,
+    {
+        TrieBuilder {
+            capacity: self.capacity,
+            hasher,
+            normalizer: self.normalizer,
+            sorted_input: self.sorted_input,
+            counting: self.counting,
+            suffix_index: self.suffix_index,
+            max_len: self.max_len,
+            bloom_rate: self.bloom_rate,
+            budget: self.budget,
+        }
+    }
+
+    /// Room for `nodes` nodes before the trie reallocates.
+    pub fn capacity(mut self, nodes: usize) -> Self {
+        self.capacity = nodes;
+        self
+    }
+
+    /// Passes every element through `normalizer`, see `Trie::with_normalizer`.
+    pub fn normalizer<F>(mut self, normalizer: F) -> Self
+    where
+        F: Fn(&T) -> T + Send + Sync + 'static,
+    {
+        self.normalizer = Some(Arc::new(normalizer));
+        self
+    }
+
+    /// `build_from_iter` reuses the keys of the part each sequence shares with
+    /// the one before it, for input where neighbours share long prefixes like
+    /// a sorted word list. Any order is still inserted correctly.
+    pub fn sorted_input(mut self, sorted: bool) -> Self {
+        self.sorted_input = sorted;
+        self
+    }
+
+    /// `build_from_iter` weights each sequence by how many times it occurs,
+    /// see `Trie::weight`.
+    pub fn counting(mut self, counting: bool) -> Self {
+        self.counting = counting;
+        self
+    }
+
+    /// Also keeps every sequence reversed so `Trie::ends_with` and
+    /// `Trie::search_suffix` don't have to check every stored sequence. Every
+    /// insert and remove then updates both.
+    pub fn suffix_index(mut self, suffix_index: bool) -> Self {
+        self.suffix_index = suffix_index;
+        self
+    }
+
+    /// Limits stored sequences to `max` elements, a longer one is truncated or
+    /// rejected by `policy` whenever it is inserted. `Trie::limited_count`
+    /// says how many were.
+    pub fn max_sequence_len(mut self, max: usize, policy: LengthPolicy) -> Self {
+        self.max_len = Some((max, policy));
+        self
+    }
+
+    /// Keeps a Bloom filter of every path in the trie so `Trie::contains`
+    /// rejects most misses without a map lookup, about `false_positive_rate`
+    /// of them still make one. Worth it when most lookups miss, it costs a
+    /// few bits per node and every insert and remove updates it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `false_positive_rate` isn't between 0 and 1.
+    pub fn bloom_filter(mut self, false_positive_rate: f64) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false positive rate must be between 0 and 1"
+        );
+        self.bloom_rate = Some(false_positive_rate);
+        self
+    }
+
+    /// `Trie::try_insert` fails rather than make the trie more than `nodes`
+    /// nodes, `insert` doesn't check it.
+    pub fn node_budget(mut self, nodes: usize) -> Self {
+        self.budget.nodes = Some(nodes);
+        self
+    }
+
+    /// `Trie::try_insert` fails rather than store more than `sequences`
+    /// sequences, `insert` doesn't check it.
+    pub fn sequence_budget(mut self, sequences: usize) -> Self {
+        self.budget.sequences = Some(sequences);
+        self
+    }
+
+    /// An empty `Trie` with the configured options.
+    pub fn build(self) -> Trie<T, S> {
+        let bloom = self.bloom_rate.map(|rate| Bloom::new(rate, self.capacity));
+        Trie {
+            children: HashMap::with_capacity_and_hasher(self.capacity, self.hasher),
+            normalizer: self.normalizer,
+            suffixes: if self.suffix_index { Some(Box::new(Trie::default())) } else { None },
+            max_len: self.max_len,
+            bloom,
+            budget: self.budget,
+            ..Trie::default()
+        }
+    }
+
+    /// A `Trie` holding every sequence of `seqs`, empty sequences are skipped.
+    pub fn build_from_iter<I, Q>(self, seqs: I) -> Trie<T, S>
+    where
+        I: IntoIterator<Item = Q>,
+        Q: AsRef<[T]>,
+    {
+        let sorted_input = self.sorted_input;
+        let counting = self.counting;
+        let mut trie = self.build();
+
+        let mut prev: Vec<T> = Vec::new();
+        let mut keys: Vec<u64> = Vec::new();
+        for seq in seqs {
+            let mut seq = trie.normalize(seq.as_ref()).into_owned();
+            match trie.limit(&seq).map(|limited| limited.len()) {
+                Some(len) => seq.truncate(len),
+                None => continue,
+            }
+            if seq.is_empty() {
+                continue;
+            }
+            let shared = if sorted_input {
+                prev.iter().zip(seq.iter()).take_while(|(a, b)| a == b).count()
+            } else {
+                0
+            };
+            keys.truncate(shared);
+            for val in seq[shared..].iter() {
+                keys.push(child_key(keys.last().copied(), val));
+            }
+
+            if trie.insert_path(&keys, seq.iter(), T::clone) {
+                trie.index_suffix(&seq);
+            }
+            if counting {
+                let node = trie.children.get_mut(&keys[keys.len() - 1]).expect("seq was just inserted");
+                node.weight = node.weight.saturating_add(1);
+            }
+            if sorted_input {
+                prev = seq;
+            }
+        }
+        debug_assert_invariants!(trie);
+        trie
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chars(words: &[&str]) -> Vec<Vec<char>> {
+        words.iter().map(|w| w.chars().collect()).collect()
+    }
+
+    #[test]
+    fn builder_defaults_match_new() {
+        let words = chars(&["cat", "car", "cow"]);
+        let mut trie = Trie::new();
+        for w in words.iter() {
+            trie.insert(w);
+        }
+        let built = TrieBuilder::new().build_from_iter(words.iter());
+        assert_eq!(built, trie);
+        assert_eq!(built.sequences(), trie.sequences());
+        assert_eq!(built.weight(&['c', 'a', 't']), Some(0));
+        assert!(TrieBuilder::<char>::new().build().is_empty());
+    }
+
+    #[test]
+    fn builder_capacity() {
+        let trie = TrieBuilder::<char>::new().capacity(100).build();
+        assert!(trie.children.capacity() >= 100);
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn builder_normalizer() {
+        let mut trie = TrieBuilder::new()
+            .normalizer(|c: &char| c.to_ascii_lowercase())
+            .build_from_iter(chars(&["CAT", "Cow"]));
+        assert!(trie.contains(&['c', 'a', 't']));
+        trie.insert(&['C', 'A', 'R']);
+        assert_eq!(trie.search(&['C', 'A']).as_collected(), vec![&['c', 'a', 't'][..], &['c', 'a', 'r']]);
+    }
+
+    #[test]
+    fn builder_hasher() {
+        use std::collections::hash_map::RandomState;
+
+        let words = chars(&["cat", "car", "cart", "cow"]);
+        let mut trie = TrieBuilder::new()
+            .hasher(RandomState::new())
+            .suffix_index(true)
+            .build_from_iter(words.iter());
+        let plain = TrieBuilder::new().build_from_iter(words.iter());
+        assert_eq!(trie.sequences(), plain.sequences());
+        assert_eq!(trie.search(&['c', 'a']).as_collected(), plain.search(&['c', 'a']).as_collected());
+        assert_eq!(trie.search_suffix(&['t']), plain.search_suffix(&['t']));
+
+        assert!(trie.remove(&['c', 'a', 'r']));
+        assert!(trie.contains(&['c', 'a', 'r', 't']));
+        trie.compact();
+        let frozen = trie.clone().freeze();
+        assert_eq!(frozen.thaw(), trie);
+        assert_eq!(trie.reader().sequence_count(), 3);
+        assert_eq!(trie.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn builder_sorted_input() {
+        let mut words = std::fs::read_to_string("data/1984.txt")
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let unsorted = TrieBuilder::new().build_from_iter(words.iter());
+
+        words.sort();
+        let sorted = TrieBuilder::new().sorted_input(true).build_from_iter(words.iter());
+        assert_eq!(sorted, unsorted);
+        assert_eq!(sorted.node_count(), unsorted.node_count());
+        assert!(sorted.check_invariants().is_ok());
+
+        // a hint only, unsorted input still builds the same trie
+        words.reverse();
+        assert_eq!(TrieBuilder::new().sorted_input(true).build_from_iter(words.iter()), unsorted);
+    }
+
+    #[test]
+    fn builder_counting() {
+        let trie = TrieBuilder::new()
+            .counting(true)
+            .build_from_iter(chars(&["cat", "car", "cat", "ca", "cat"]));
+        assert_eq!(trie.sequence_count(), 3);
+        assert_eq!(trie.weight(&['c', 'a', 't']), Some(3));
+        assert_eq!(trie.weight(&['c', 'a', 'r']), Some(1));
+        assert_eq!(trie.weight(&['c', 'a']), Some(1));
+        assert_eq!(trie.top_k(&['c'], 1), vec![(vec!['c', 'a', 't'], 3)]);
+    }
+
+    #[test]
+    fn builder_max_len_truncate() {
+        let mut trie = TrieBuilder::new()
+            .max_sequence_len(4, LengthPolicy::Truncate)
+            .build_from_iter(chars(&["cartwheel", "cat"]));
+        assert_eq!(trie.limited_count(), 1);
+        assert!(trie.contains(&['c', 'a', 'r', 't']));
+        assert!(!trie.contains(&['c', 'a', 'r', 't', 'w']));
+
+        // the truncated form is already stored
+        assert!(!trie.insert(&['c', 'a', 'r', 't', 'o', 'n']));
+        assert!(trie.insert_owned(vec!['c', 'o', 'w', 'b', 'o', 'y']));
+        trie.insert_weighted(&['c', 'o', 'w', 'l', 'i', 'c', 'k'], 5);
+        assert_eq!(trie.limited_count(), 4);
+        assert_eq!(trie.weight(&['c', 'o', 'w', 'b']), Some(0));
+        assert_eq!(trie.weight(&['c', 'o', 'w', 'l']), Some(5));
+        assert_eq!(trie.search(&['c']).as_collected(), vec![
+            &['c', 'a', 'r', 't'][..], &['c', 'a', 't'], &['c', 'o', 'w', 'b'], &['c', 'o', 'w', 'l'],
+        ]);
+        // sequences at the limit aren't counted
+        assert!(trie.insert(&['d', 'o', 'g', 's']));
+        assert_eq!(trie.limited_count(), 4);
+    }
+
+    #[test]
+    fn builder_max_len_reject() {
+        let mut trie = TrieBuilder::new()
+            .max_sequence_len(4, LengthPolicy::Reject)
+            .build_from_iter(chars(&["cartwheel", "cat"]));
+        assert_eq!(trie.limited_count(), 1);
+        assert_eq!(trie.sequences(), chars(&["cat"]));
+
+        assert!(!trie.insert(&['c', 'a', 'r', 't', 'o', 'n']));
+        assert!(!trie.insert_owned(vec!['c', 'o', 'w', 'b', 'o', 'y']));
+        trie.insert_weighted(&['c', 'o', 'w', 'l', 'i', 'c', 'k'], 5);
+        assert_eq!(trie.limited_count(), 4);
+        assert!(!trie.contains(&['c', 'a', 'r']));
+        assert!(!trie.contains(&['c', 'o']));
+        assert_eq!(trie.node_count(), 3);
+
+        assert!(trie.insert(&['c', 'a', 'r', 't']));
+        assert_eq!(trie.limited_count(), 4);
+        assert_eq!(trie.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn builder_options_combine() {
+        let mut words = chars(&["Cat", "cow", "CAT", "car", "", "cat"]);
+        words.sort();
+        let trie = TrieBuilder::new()
+            .capacity(8)
+            .normalizer(|c: &char| c.to_ascii_lowercase())
+            .sorted_input(true)
+            .counting(true)
+            .build_from_iter(words);
+        assert_eq!(trie.sequences(), chars(&["cat", "car", "cow"]));
+        assert_eq!(trie.weight(&['C', 'A', 'T']), Some(3));
+        assert_eq!(trie.weight(&['c', 'o', 'w']), Some(1));
+        assert!(trie.check_invariants().is_ok());
+    }
+}
@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+use std::hash::BuildHasher;
+
+use crate::{Found, Trie};
+
+/// Convenience methods for tries of binary data, nothing here assumes the
+/// bytes are text.
+impl<S: BuildHasher + Default> Trie<u8, S> {
+    pub fn insert_bytes<B: AsRef<[u8]>>(&mut self, bytes: B) -> bool {
+        self.insert(bytes.as_ref())
+    }
+
+    pub fn contains_bytes<B: AsRef<[u8]>>(&self, bytes: B) -> bool {
+        self.contains(bytes.as_ref())
+    }
+
+    pub fn search_bytes<B: AsRef<[u8]>>(&self, bytes: B) -> Found<u8> {
+        self.search(bytes.as_ref())
+    }
+
+    pub fn remove_bytes<B: AsRef<[u8]>>(&mut self, bytes: B) -> bool {
+        self.remove(bytes.as_ref())
+    }
+}
+
+impl Found<u8> {
+    /// The found sequences as owned byte strings.
+    pub fn as_byte_strings(&self) -> Vec<Vec<u8>> {
+        self.collected.clone()
+    }
+
+    /// The found sequences as text for display, invalid UTF-8 is replaced
+    /// with `U+FFFD`.
+    pub fn as_lossy_strings(&self) -> Vec<Cow<'_, str>> {
+        self.collected
+            .iter()
+            .map(|seq| String::from_utf8_lossy(seq))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Trie;
+
+    #[test]
+    fn binary_safe() {
+        let mut trie = Trie::new();
+        trie.insert_bytes(b"a\0b");
+        trie.insert_bytes(vec![b'a', 0xff, 0xfe]);
+        trie.insert_bytes("a/path");
+
+        assert!(trie.contains_bytes(b"a\0b"));
+        assert!(trie.contains_bytes([b'a', 0xff, 0xfe]));
+        assert!(!trie.contains_bytes(b"a\0c"));
+
+        let found = trie.search_bytes(b"a");
+        assert_eq!(
+            found.as_byte_strings(),
+            vec![b"a\0b".to_vec(), vec![b'a', 0xff, 0xfe], b"a/path".to_vec()]
+        );
+        assert_eq!(found.as_lossy_strings()[1], "a\u{fffd}\u{fffd}");
+
+        assert!(trie.remove_bytes(b"a/path"));
+        assert!(!trie.contains_bytes(b"a/path"));
+        assert!(trie.contains_bytes(b"a\0b"));
+    }
+}
@@ -0,0 +1,285 @@
+use std::fmt;
+use std::hash::BuildHasher;
+use std::io::{self, BufRead, Write};
+use std::iter::FromIterator;
+
+use crate::{Found, Trie};
+
+/// How `Trie::from_reader` splits its input into sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    /// Every whitespace separated token is a sequence.
+    Whitespace,
+    /// Every line is a sequence, without its line ending.
+    Lines,
+}
+
+/// Convenience methods for tries of text, each `&str` is split into its `char`s.
+impl<S: BuildHasher + Default> Trie<char, S> {
+    pub fn insert_str(&mut self, s: &str) -> bool {
+        self.insert(&s.chars().collect::<Vec<_>>())
+    }
+
+    pub fn contains_str(&self, s: &str) -> bool {
+        self.contains(&s.chars().collect::<Vec<_>>())
+    }
+
+    pub fn search_str(&self, s: &str) -> Found<char> {
+        self.search(&s.chars().collect::<Vec<_>>())
+    }
+
+    pub fn remove_str(&mut self, s: &str) -> bool {
+        self.remove(&s.chars().collect::<Vec<_>>())
+    }
+
+    /// Writes every stored word once in sorted order, separated by `sep`,
+    /// returning the number of words written. The output can be read back with
+    /// `from_reader`.
+    pub fn to_writer<W: Write>(&self, mut w: W, sep: &str) -> io::Result<usize> {
+        let mut words = self.sequences()
+            .into_iter()
+            .map(|seq| seq.into_iter().collect::<String>())
+            .collect::<Vec<_>>();
+        words.sort_unstable();
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                w.write_all(sep.as_bytes())?;
+            }
+            w.write_all(word.as_bytes())?;
+        }
+        w.flush()?;
+        Ok(words.len())
+    }
+}
+
+impl Trie<char> {
+    /// Builds a trie from the sequences in `reader` split by `delim`, reading a
+    /// line at a time. Empty sequences are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::{Delimiter, Trie};
+    /// let trie = Trie::from_reader("cat cow\ncart".as_bytes(), Delimiter::Whitespace).unwrap();
+    ///
+    /// assert!(trie.contains_str("cart"));
+    /// ```
+    pub fn from_reader<R: BufRead>(mut reader: R, delim: Delimiter) -> io::Result<Trie<char>> {
+        let mut trie = Trie::new();
+        let mut line = String::new();
+        let mut seq = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(trie);
+            }
+            let mut insert = |token: &str| {
+                seq.clear();
+                seq.extend(token.chars());
+                if !seq.is_empty() {
+                    trie.insert(&seq);
+                }
+            };
+            match delim {
+                Delimiter::Whitespace => line.split_whitespace().for_each(insert),
+                Delimiter::Lines => insert(line.trim_end_matches(&['\n', '\r'][..])),
+            }
+        }
+    }
+}
+
+impl<S: BuildHasher + Default> Trie<char, S> {
+    /// Inserts every word reusing one buffer for their `char`s.
+    fn insert_strs<I, W>(&mut self, words: I)
+    where
+        I: IntoIterator<Item = W>,
+        W: AsRef<str>,
+    {
+        let mut seq = Vec::new();
+        for word in words {
+            seq.clear();
+            seq.extend(word.as_ref().chars());
+            self.insert(&seq);
+        }
+    }
+}
+
+/// # Examples
+///
+/// ```
+/// use ecs_trie::Trie;
+/// let trie: Trie<char> = ["cat", "cow", "cart"].iter().copied().collect();
+///
+/// assert_eq!(
+///     trie.search_str("ca").as_collected(),
+///     vec![&['c', 'a', 't'][..], &['c', 'a', 'r', 't']]
+/// );
+/// ```
+impl<'a> FromIterator<&'a str> for Trie<char> {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let mut trie = Trie::new();
+        trie.insert_strs(iter);
+        trie
+    }
+}
+
+/// # Examples
+///
+/// ```
+/// use ecs_trie::Trie;
+/// let words = vec!["cat".to_string(), "cow".to_string()];
+/// let trie: Trie<char> = words.into_iter().collect();
+///
+/// assert!(trie.contains_str("cow"));
+/// assert_eq!(trie.sequence_count(), 2);
+/// ```
+impl FromIterator<String> for Trie<char> {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        let mut trie = Trie::new();
+        trie.insert_strs(iter);
+        trie
+    }
+}
+
+/// # Examples
+///
+/// ```
+/// use ecs_trie::Trie;
+/// let trie = Trie::from(&["cat", "cow", "cart"][..]);
+///
+/// assert!(trie.contains_str("cart"));
+/// assert_eq!(trie.sequence_count(), 3);
+/// ```
+impl From<&[&str]> for Trie<char> {
+    fn from(words: &[&str]) -> Self {
+        words.iter().copied().collect()
+    }
+}
+
+impl Found<char> {
+    /// Each found sequence as a `String`, in traversal order.
+    pub fn to_strings(&self) -> Vec<String> {
+        self.collected.iter().map(|seq| seq.iter().collect()).collect()
+    }
+}
+
+/// One found sequence per line, in traversal order.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_trie::Trie;
+/// let trie = Trie::from(&["cat", "cow"][..]);
+///
+/// println!("{}", trie.search(&['c']));
+/// // cat
+/// // cow
+/// assert_eq!(trie.search(&['c']).to_string(), "cat\ncow");
+/// ```
+impl fmt::Display for Found<char> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, seq) in self.collected.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            for c in seq {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use crate::{Delimiter, Trie};
+
+    #[test]
+    fn from_reader() {
+        let file = BufReader::new(File::open("data/sun-rising.txt").unwrap());
+        let trie = Trie::from_reader(file, Delimiter::Whitespace).unwrap();
+        assert!(trie.contains_str("BUSY"));
+        assert!(trie.contains_str("unruly"));
+        assert!(trie.contains_str("Sun,"));
+        assert!(!trie.contains_str("BUSY old"));
+
+        let file = BufReader::new(File::open("data/sun-rising.txt").unwrap());
+        let trie = Trie::from_reader(file, Delimiter::Lines).unwrap();
+        assert!(trie.contains_str("Why dost thou thus,"));
+        assert!(!trie.contains_str("Why dost thou thus,\n"));
+
+        let trie = Trie::from_reader("\n\na\r\n\n".as_bytes(), Delimiter::Lines).unwrap();
+        assert_eq!(trie.sequences(), vec![vec!['a']]);
+    }
+
+    #[test]
+    fn found_strings() {
+        let trie = Trie::from(&["cow", "cart", "car"][..]);
+        let found = trie.search_str("ca");
+        assert_eq!(found.to_strings(), vec!["car", "cart"]);
+        assert_eq!(found.to_string(), "car\ncart");
+        assert_eq!(trie.search_str("d").to_string(), "");
+        assert!(trie.search_str("d").to_strings().is_empty());
+        assert_eq!(format!("{}", trie.search_str("co")), "cow");
+    }
+
+    #[test]
+    fn to_writer_round_trip() {
+        let file = BufReader::new(File::open("data/1984.txt").unwrap());
+        let trie = Trie::from_reader(file, Delimiter::Whitespace).unwrap();
+
+        let mut out = Vec::new();
+        let count = trie.to_writer(&mut out, "\n").unwrap();
+        assert_eq!(count, trie.sequence_count());
+
+        let read = Trie::from_reader(out.as_slice(), Delimiter::Lines).unwrap();
+        assert_eq!(read, trie);
+
+        let mut trie = Trie::new();
+        trie.insert_str("cow");
+        trie.insert_str("cat");
+        trie.insert_str("ca");
+        let mut out = Vec::new();
+        trie.to_writer(&mut out, ", ").unwrap();
+        assert_eq!(out, b"ca, cat, cow");
+    }
+
+    #[test]
+    fn normalized_str() {
+        let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        trie.insert_str("Cat");
+        trie.insert_str("COW");
+
+        assert!(trie.contains_str("cAT"));
+        assert!(trie.contains_str("cat"));
+        assert!(!trie.contains_str("dog"));
+        assert_eq!(
+            trie.search_str("CA").as_collected().as_slice(),
+            &[['c', 'a', 't']]
+        );
+
+        let copy = trie.clone();
+        assert!(copy.contains_str("Cow"));
+        assert!(trie.remove_str("cOw"));
+        assert!(!trie.contains_str("cow"));
+    }
+
+    #[test]
+    fn collect_strs() {
+        let text = std::fs::read_to_string("data/sun-rising.txt").unwrap();
+        let file = BufReader::new(File::open("data/sun-rising.txt").unwrap());
+        let read = Trie::from_reader(file, Delimiter::Whitespace).unwrap();
+
+        let trie: Trie<char> = text.split_whitespace().collect();
+        assert_eq!(trie, read);
+        let owned: Trie<char> = text.split_whitespace().map(String::from).collect();
+        assert_eq!(owned, read);
+
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        assert_eq!(Trie::from(words.as_slice()), read);
+        assert!(Trie::from(&[][..]).is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+use std::ops::Range;
+
+use crate::{Found, Node, Normalizer, Trie};
+
+/// A node in a `CompiledTrie`, its children are the contiguous span
+/// `start..start + len` of the node array.
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledNode<T> {
+    pub(crate) val: T,
+    pub(crate) terminal: bool,
+    pub(crate) start: u32,
+    pub(crate) len: u32,
+}
+
+impl<T> CompiledNode<T> {
+    fn new(val: T, terminal: bool) -> Self {
+        Self { val, terminal, start: 0, len: 0 }
+    }
+
+    fn children(&self) -> Range<usize> {
+        self.start as usize..(self.start + self.len) as usize
+    }
+}
+
+/// A read only copy of a `Trie` with its nodes in one contiguous `Vec`, made
+/// by `Trie::compile`.
+///
+/// The starts are the first nodes, after them every node's children are stored
+/// side by side, level by level in the order of their parents. Only the first
+/// element of a query is hashed, to find its start, every later step scans a
+/// small span of the array instead of hashing the prefix and chasing a map
+/// entry. Queries give the same results as the source trie,
+/// including its normalizer, but later changes to the trie aren't seen.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_trie::Trie;
+/// let mut trie = Trie::new();
+/// trie.insert(&['c', 'a', 't']);
+/// trie.insert(&['c', 'o', 'w']);
+/// let compiled = trie.compile();
+///
+/// assert!(compiled.contains(&['c', 'o']));
+/// assert_eq!(compiled.longest_prefix(&['c', 'a', 't', 's']), Some(3));
+/// assert_eq!(
+///     compiled.search(&['c']).as_collected().as_slice(),
+///     &[ ['c', 'a', 't'], ['c', 'o', 'w'] ]
+/// );
+/// ```
+#[derive(Clone)]
+pub struct CompiledTrie<T> {
+    pub(crate) nodes: Vec<CompiledNode<T>>,
+    pub(crate) start_map: HashMap<T, u32>,
+    normalizer: Option<Normalizer<T>>,
+}
+
+impl<T: Debug> Debug for CompiledTrie<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledTrie")
+            .field("nodes", &self.nodes)
+            .field("starts", &self.start_map.len())
+            .finish()
+    }
+}
+
+impl<T> CompiledTrie<T>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn child(&self, idx: usize, val: &T) -> Option<usize> {
+        self.nodes[idx].children().find(|c| self.nodes[*c].val == *val)
+    }
+
+    /// The index of the node at the end of `seq`, `None` for an empty or
+    /// missing path.
+    fn find(&self, seq: &[T]) -> Option<usize> {
+        let (first, rest) = seq.split_first()?;
+        let mut idx = *self.start_map.get(first)? as usize;
+        for val in rest {
+            idx = self.child(idx, val)?;
+        }
+        Some(idx)
+    }
+
+    /// Returns `true` if the path `seq` exists, like `Trie::contains`.
+    pub fn contains(&self, seq: &[T]) -> bool {
+        self.find(&self.normalize(seq)).is_some()
+    }
+
+    /// The length of the longest stored sequence that is a prefix of `seq`,
+    /// `seq` itself when it is stored.
+    pub fn longest_prefix(&self, seq: &[T]) -> Option<usize> {
+        let seq = &*self.normalize(seq);
+        let (first, rest) = seq.split_first()?;
+        let mut idx = *self.start_map.get(first)? as usize;
+        let mut longest = if self.nodes[idx].terminal { Some(1) } else { None };
+        for (i, val) in rest.iter().enumerate() {
+            idx = match self.child(idx, val) {
+                Some(idx) => idx,
+                None => break,
+            };
+            if self.nodes[idx].terminal {
+                longest = Some(i + 2);
+            }
+        }
+        longest
+    }
+
+    /// Returns all of the stored sequences starting with `prefix`, the same
+    /// as `Trie::search`.
+    pub fn search(&self, prefix: &[T]) -> Found<T> {
+        let prefix = &*self.normalize(prefix);
+        let mut found = Found::new();
+        found.extend(prefix.iter().cloned());
+        if let Some(idx) = self.find(prefix) {
+            self._search(idx, &mut found);
+        }
+        found
+    }
+
+    fn _search(&self, idx: usize, found: &mut Found<T>) {
+        let node = &self.nodes[idx];
+        if node.terminal {
+            found.branch_end();
+        }
+        for child in node.children() {
+            found.push_val(self.nodes[child].val.clone());
+            self._search(child, found);
+            found.branch_split();
+        }
+    }
+
+    fn normalize<'a>(&self, seq: &'a [T]) -> Cow<'a, [T]> {
+        match &self.normalizer {
+            Some(normalize) => Cow::Owned(seq.iter().map(|t| normalize(t)).collect()),
+            None => Cow::Borrowed(seq),
+        }
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Copies the trie into a `CompiledTrie`, a read only layout that answers
+    /// `contains`, `longest_prefix` and `search` without hashing each prefix.
+    ///
+    /// # Panics
+    ///
+    /// If the trie has more than `u32::MAX` nodes.
+    pub fn compile(&self) -> CompiledTrie<T> {
+        let index = |i: usize| u32::try_from(i).expect("more than u32::MAX nodes");
+        let starts = self.starts.iter()
+            .filter_map(|key| self.children.get(key))
+            .collect::<Vec<_>>();
+        let mut nodes = Vec::with_capacity(self.len);
+        let mut start_map = HashMap::with_capacity(starts.len());
+        for (i, node) in starts.iter().enumerate() {
+            nodes.push(CompiledNode::new(node.to_value(), node.is_terminal()));
+            start_map.insert(node.to_value(), index(i));
+        }
+
+        // each node in index order reserves the span for its children at the
+        // end of the array, so the spans follow one another with no gaps,
+        // `sources` holds the node each compiled node came from
+        let mut sources: Vec<&Node<T>> = starts;
+        let mut idx = 0;
+        while idx < sources.len() {
+            let children = sources[idx].children(&self.children);
+            nodes[idx].start = index(nodes.len());
+            nodes[idx].len = index(children.len());
+            for child in children {
+                nodes.push(CompiledNode::new(child.to_value(), child.is_terminal()));
+                sources.push(child);
+            }
+            idx += 1;
+        }
+
+        CompiledTrie {
+            nodes,
+            start_map,
+            normalizer: self.normalizer.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn words(path: &str) -> Vec<Vec<char>> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect())
+            .collect()
+    }
+
+    #[test]
+    fn compiled_matches_source() {
+        let words = words("data/1984.txt");
+        let mut trie = Trie::new();
+        for w in words.iter() {
+            trie.insert(w);
+        }
+        let compiled = trie.compile();
+        assert_eq!(compiled.len(), trie.node_count());
+
+        let mut prefixes = HashSet::new();
+        for w in words.iter().collect::<HashSet<_>>() {
+            assert!(compiled.contains(w));
+            for i in 1..w.len() {
+                assert_eq!(compiled.contains(&w[..i]), trie.contains(&w[..i]));
+            }
+            let longest = (1..=w.len())
+                .rev()
+                .find(|i| trie.get(&w[..*i]).is_some_and(|n| n.is_terminal()));
+            assert_eq!(compiled.longest_prefix(w), longest);
+            prefixes.insert(&w[..w.len().min(2)]);
+        }
+        for prefix in prefixes {
+            assert_eq!(compiled.search(prefix).as_collected(), trie.search(prefix).as_collected());
+        }
+        assert!(!compiled.contains(&['#', '#']));
+        assert!(!compiled.contains(&[]));
+        assert!(compiled.search(&['#']).as_collected().is_empty());
+    }
+
+    #[test]
+    fn compiled_longest_prefix() {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a']);
+        trie.insert(&['c', 'a', 'r', 't']);
+        let compiled = trie.compile();
+
+        assert_eq!(compiled.longest_prefix(&['c']), None);
+        assert_eq!(compiled.longest_prefix(&['c', 'a', 'r']), Some(2));
+        assert_eq!(compiled.longest_prefix(&['c', 'a', 'r', 't', 's']), Some(4));
+        assert_eq!(compiled.longest_prefix(&['c', 'o']), None);
+        assert_eq!(compiled.longest_prefix(&[]), None);
+    }
+
+    #[test]
+    fn compiled_keeps_normalizer() {
+        let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        trie.insert(&['C', 'a', 'T']);
+        let compiled = trie.compile();
+
+        assert!(compiled.contains(&['c', 'A']));
+        assert_eq!(compiled.longest_prefix(&['C', 'A', 'T', 'S']), Some(3));
+        assert_eq!(compiled.search(&['C']).as_collected(), vec![&['c', 'a', 't'][..]]);
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt::{self, Debug, Display, Write};
+use std::hash::{BuildHasher, Hash};
+use std::io;
+
+use crate::node::{DepthFirst, Node};
+use crate::Trie;
+
+impl<T: Display, S: BuildHasher> Trie<T, S> {
+    /// Renders the trie as an indented tree, one node per line with children
+    /// indented beneath their parent, descending at most `max_depth` elements.
+    /// Terminal nodes are marked with a trailing `*`.
+    ///
+    /// Roots are taken in `starts` order and children in insertion order, so the
+    /// output is deterministic for a given sequence of inserts, see
+    /// `format_tree_sorted` for output that doesn't depend on it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'o', 'w']);
+    ///
+    /// assert_eq!(trie.format_tree(2), "c\n  a\n  o\n");
+    /// ```
+    pub fn format_tree(&self, max_depth: usize) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, max_depth, |w, val| write!(w, "{}", val), |_| {})
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    /// Like `format_tree` with the roots and each node's children in order of
+    /// their values, the same stored sequences always render the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'o', 'w']);
+    /// trie.insert(&['c', 'a', 't']);
+    ///
+    /// assert_eq!(trie.format_tree(2), "c\n  o\n  a\n");
+    /// assert_eq!(trie.format_tree_sorted(2), "c\n  a\n  o\n");
+    /// ```
+    pub fn format_tree_sorted(&self, max_depth: usize) -> String
+    where
+        T: Ord,
+    {
+        let mut out = String::new();
+        self.write_tree(
+            &mut out,
+            max_depth,
+            |w, val| write!(w, "{}", val),
+            |nodes| nodes.sort_by(|a, b| a.val.cmp(&b.val)),
+        )
+        .expect("writing to a String cannot fail");
+        out
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Display + Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Writes every stored sequence in traversal order, elements joined by
+    /// `elem_sep` and sequences separated by `seq_sep`. Returns the number of
+    /// sequences written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&[1, 2, 3]);
+    /// trie.insert(&[1, 4]);
+    ///
+    /// let mut out = Vec::new();
+    /// assert_eq!(trie.write_sequences(&mut out, " ", "\n").unwrap(), 2);
+    /// assert_eq!(out, b"1 2 3\n1 4");
+    /// ```
+    pub fn write_sequences<W: io::Write>(
+        &self,
+        mut w: W,
+        elem_sep: &str,
+        seq_sep: &str,
+    ) -> io::Result<usize> {
+        let mut count = 0;
+        self.try_for_each_sequence(|seq| {
+            if count > 0 {
+                w.write_all(seq_sep.as_bytes())?;
+            }
+            for (i, elem) in seq.iter().enumerate() {
+                if i > 0 {
+                    w.write_all(elem_sep.as_bytes())?;
+                }
+                write!(w, "{}", elem)?;
+            }
+            count += 1;
+            Ok::<_, io::Error>(())
+        })?;
+        w.flush()?;
+        Ok(count)
+    }
+}
+
+impl<T, S: BuildHasher> Trie<T, S> {
+    /// Writes each node depth first with an explicit stack so a long sequence
+    /// can't overflow the call stack, `order` arranges the roots and each
+    /// node's children before they are written.
+    fn write_tree<W, F, O>(&self, w: &mut W, max_depth: usize, fmt_val: F, order: O) -> fmt::Result
+    where
+        W: fmt::Write,
+        F: Fn(&mut W, &T) -> fmt::Result,
+        O: Fn(&mut Vec<&Node<T>>),
+    {
+        let mut level = self.starts.iter()
+            .filter_map(|key| self.children.get(key))
+            .collect::<Vec<_>>();
+        order(&mut level);
+        let mut stack = level.into_iter().rev().map(|node| (1, node)).collect::<Vec<_>>();
+        while let Some((depth, node)) = stack.pop() {
+            if depth > max_depth {
+                continue;
+            }
+            for _ in 1..depth {
+                w.write_str("  ")?;
+            }
+            fmt_val(w, &node.val)?;
+            writeln!(w, "{}", if node.terminal { "*" } else { "" })?;
+            if depth < max_depth {
+                let mut level = node.children.iter().map(|key| &self.children[key]).collect::<Vec<_>>();
+                order(&mut level);
+                stack.extend(level.into_iter().rev().map(|node| (depth + 1, node)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Length of the longest path from a start to a leaf.
+    fn max_depth(&self) -> usize {
+        DepthFirst::new(self).map(|(depth, _)| depth).max().unwrap_or(0)
+    }
+}
+
+impl<T: Debug, S: BuildHasher> Trie<T, S> {
+    /// The full internal structure, every node of the `children` map. This is
+    /// what `{:#?}` prints, for real dictionaries expect a lot of output.
+    pub fn dump(&self) -> String {
+        format!("{:#?}", self)
+    }
+}
+
+impl<T: Display, S: BuildHasher> Display for Trie<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_tree(f, usize::MAX, |f, val| write!(f, "{}", val), |_| {})
+    }
+}
+
+/// Summary stats and the first two levels of the tree, `{:#?}` dumps every node.
+impl<T: Debug, S: BuildHasher> Debug for Trie<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return f.debug_struct("Trie")
+                .field("starts", &self.starts)
+                .field("children", &self.children)
+                .field("len", &self.len)
+                .field("normalizer", &self.normalizer.is_some())
+                .field("max_sequences", &self.lru.as_ref().map(|lru| lru.max))
+                .finish();
+        }
+        writeln!(
+            f,
+            "Trie {{ nodes: {}, starts: {}, max_depth: {} }}",
+            self.children.len(),
+            self.starts.len(),
+            self.max_depth(),
+        )?;
+        self.write_tree(f, 2, |f, val| write!(f, "{:?}", val), |_| {})
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Trie;
+
+    fn three_words() -> Trie<char> {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 't']);
+        trie.insert(&['c', 'a', 'r', 't']);
+        trie.insert(&['c', 'o', 'w']);
+        trie
+    }
+
+    #[test]
+    fn display_tree() {
+        let expected = "\
+c
+  a
+    t*
+    r
+      t*
+  o
+    w*
+";
+        assert_eq!(three_words().to_string(), expected);
+    }
+
+    #[test]
+    fn debug_summary() {
+        let trie = three_words();
+        let expected = "\
+Trie { nodes: 7, starts: 1, max_depth: 4 }
+'c'
+  'a'
+  'o'
+";
+        assert_eq!(format!("{:?}", trie), expected);
+        assert!(trie.dump().starts_with("Trie {\n    starts: ["));
+        assert_eq!(trie.dump(), format!("{:#?}", trie));
+    }
+
+    #[test]
+    fn format_tree_depth() {
+        let trie = three_words();
+        assert_eq!(trie.format_tree(0), "");
+        assert_eq!(trie.format_tree(3), "c\n  a\n    t*\n    r\n  o\n    w*\n");
+        assert_eq!(trie.format_tree(usize::MAX), trie.to_string());
+    }
+
+    #[test]
+    fn format_tree_sorted_order() {
+        let mut trie = Trie::new();
+        for word in ["cow", "dog", "cart", "cat"].iter() {
+            trie.insert_str(word);
+        }
+        let sorted = "\
+c
+  a
+    r
+      t*
+    t*
+  o
+    w*
+d
+  o
+    g*
+";
+        assert_eq!(trie.format_tree_sorted(usize::MAX), sorted);
+        assert_eq!(Trie::from(&["dog", "cat", "cart", "cow"][..]).format_tree_sorted(usize::MAX), sorted);
+        assert_eq!(trie.format_tree_sorted(1), "c\nd\n");
+    }
+
+    #[test]
+    fn format_long_sequence() {
+        // deeper than recursion would manage on a test thread's stack
+        let mut trie = Trie::new();
+        trie.insert(&vec![0u8; 100_000]);
+        assert!(format!("{:?}", trie).starts_with("Trie { nodes: 100000, starts: 1, max_depth: 100000 }"));
+        assert_eq!(trie.format_tree(3), "0\n  0\n    0\n");
+    }
+}
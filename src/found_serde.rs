@@ -0,0 +1,55 @@
+use serde::{Serialize, Serializer};
+
+use crate::Found;
+
+/// Serializes as the list of collected sequences, each a list of elements.
+impl<T: Serialize> Serialize for Found<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.collected.iter())
+    }
+}
+
+/// Serializes a `Found<char>` as a list of strings instead of lists of one
+/// character strings, for `#[serde(serialize_with = "serialize_as_strings")]`.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_trie::{serialize_as_strings, Trie};
+/// let trie = Trie::from(&["cat", "cow"][..]);
+///
+/// let mut json = Vec::new();
+/// serialize_as_strings(&trie.search_str("c"), &mut serde_json::Serializer::new(&mut json)).unwrap();
+/// assert_eq!(json, br#"["cat","cow"]"#);
+/// ```
+pub fn serialize_as_strings<S: Serializer>(found: &Found<char>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(found.collected.iter().map(|seq| seq.iter().collect::<String>()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Trie;
+
+    #[test]
+    fn found_json() {
+        let trie = Trie::from(&["car", "cart", "cow", "dog"][..]);
+
+        let found = trie.search_str("ca");
+        assert_eq!(
+            serde_json::to_string(&found).unwrap(),
+            r#"[["c","a","r"],["c","a","r","t"]]"#
+        );
+
+        let mut json = Vec::new();
+        serialize_as_strings(&found, &mut serde_json::Serializer::new(&mut json)).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), r#"["car","cart"]"#);
+
+        let none = trie.search_str("x");
+        assert_eq!(serde_json::to_string(&none).unwrap(), "[]");
+
+        let mut ids = Trie::new();
+        ids.insert(&[1_u32, 2]);
+        assert_eq!(serde_json::to_string(&ids.search(&[1])).unwrap(), "[[1,2]]");
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::{NoopBuildHasher, Trie};
+
+/// An immutable `Trie` behind an `Arc`, made by `Trie::freeze`.
+///
+/// Cloning only bumps a reference count so a large trie can be handed to many
+/// threads cheaply. Every `&self` method of `Trie` is available through
+/// `Deref`, to change it `thaw` a mutable copy.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_trie::Trie;
+/// let mut trie = Trie::new();
+/// trie.insert(&['c', 'a', 't']);
+/// let frozen = trie.freeze();
+///
+/// let handle = {
+///     let frozen = frozen.clone();
+///     std::thread::spawn(move || frozen.contains(&['c', 'a']))
+/// };
+/// assert!(handle.join().unwrap());
+///
+/// let mut thawed = frozen.thaw();
+/// thawed.insert(&['c', 'o', 'w']);
+/// assert!(!frozen.contains(&['c', 'o', 'w']));
+/// ```
+pub struct FrozenTrie<T, S = NoopBuildHasher> {
+    trie: Arc<Trie<T, S>>,
+}
+
+impl<T, S> Clone for FrozenTrie<T, S> {
+    fn clone(&self) -> Self {
+        Self { trie: Arc::clone(&self.trie) }
+    }
+}
+
+impl<T, S> Deref for FrozenTrie<T, S> {
+    type Target = Trie<T, S>;
+    fn deref(&self) -> &Trie<T, S> {
+        &self.trie
+    }
+}
+
+impl<T: Debug, S: BuildHasher> Debug for FrozenTrie<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&*self.trie, f)
+    }
+}
+
+impl<T, S> From<Trie<T, S>> for FrozenTrie<T, S> {
+    fn from(trie: Trie<T, S>) -> Self {
+        Self { trie: Arc::new(trie) }
+    }
+}
+
+impl<T, S> FrozenTrie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Clone,
+{
+    /// A mutable deep copy of the trie.
+    pub fn thaw(&self) -> Trie<T, S> {
+        (*self.trie).clone()
+    }
+
+    /// Returns `true` if both share the same nodes, a clone of the other.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.trie, &other.trie)
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Moves the trie behind an `Arc` giving a read only `FrozenTrie` that is
+    /// cheap to clone and share between threads.
+    pub fn freeze(self) -> FrozenTrie<T, S> {
+        FrozenTrie::from(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn frozen_shared_across_threads() {
+        let text = std::fs::read_to_string("data/1984.txt").unwrap();
+        let words = text.split_whitespace()
+            .map(|w| w.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut trie = Trie::new();
+        for w in words.iter() {
+            trie.insert(w);
+        }
+        let original = trie.clone();
+        let frozen = trie.freeze();
+
+        let prefixes = words.iter().step_by(397).map(|w| w[..w.len().min(2)].to_vec()).collect::<Vec<_>>();
+        let expected = prefixes.iter()
+            .map(|p| original.search(p).as_collected().iter().map(|s| s.to_vec()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let handles = (0..4).map(|_| {
+            let frozen = frozen.clone();
+            let prefixes = prefixes.clone();
+            thread::spawn(move || {
+                prefixes.iter()
+                    .map(|p| frozen.search(p).as_collected().iter().map(|s| s.to_vec()).collect::<Vec<_>>())
+                    .collect::<Vec<_>>()
+            })
+        }).collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+
+        assert_eq!(frozen.sequences(), original.sequences());
+        assert_eq!(frozen.iter_with_depth().count(), original.node_count());
+        assert!(words.iter().all(|w| frozen.contains(w)));
+        assert!(frozen.clone().ptr_eq(&frozen));
+        assert_eq!(frozen.thaw(), original);
+    }
+}
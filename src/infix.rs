@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::node::Node;
+use crate::Trie;
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// The key of every node where a path ending in `fragment` stops, `fragment`
+    /// isn't empty.
+    ///
+    /// Every node holding `fragment[0]` is tried as the start of a match that
+    /// follows children for the rest of `fragment`.
+    fn infix_ends(&self, fragment: &[T]) -> HashSet<u64> {
+        let child = |node: &Node<T>, val: &T| {
+            node.children.iter().copied().find(|key| self.children[key].as_value() == val)
+        };
+        self.children.iter()
+            .filter(|(_, node)| *node.as_value() == fragment[0])
+            .filter_map(|(key, _)| {
+                fragment[1..].iter().try_fold(*key, |key, val| child(&self.children[&key], val))
+            })
+            .collect()
+    }
+
+    /// Returns `true` if `fragment` appears anywhere in a stored sequence, the
+    /// empty fragment appears in every sequence.
+    ///
+    /// Every node is checked as a possible start of `fragment` so this is
+    /// O(nodes * `fragment.len()` * children per node), use `contains` for a
+    /// prefix or `ends_with` for a suffix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["cart", "cow"][..]);
+    ///
+    /// assert!(trie.contains_infix(&['a', 'r']));
+    /// assert!(!trie.contains_infix(&['a', 'w']));
+    /// ```
+    pub fn contains_infix(&self, fragment: &[T]) -> bool {
+        let fragment = &*self.normalize(fragment);
+        if fragment.is_empty() {
+            return self.seq_len > 0;
+        }
+        // a node only exists on the path of some stored sequence
+        !self.infix_ends(fragment).is_empty()
+    }
+
+    /// Returns every stored sequence containing `fragment` anywhere, each once
+    /// and in traversal order. The empty fragment returns every sequence.
+    ///
+    /// Finding the matches costs the same as `contains_infix`, then one walk
+    /// over the trie collects the sequences at or below them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["cart", "art", "cow"][..]);
+    ///
+    /// let found = trie.search_infix(&['a', 'r']);
+    /// assert_eq!(found, vec![vec!['c', 'a', 'r', 't'], vec!['a', 'r', 't']]);
+    /// ```
+    pub fn search_infix(&self, fragment: &[T]) -> Vec<Vec<T>> {
+        let fragment = &*self.normalize(fragment);
+        if fragment.is_empty() {
+            return self.sequences();
+        }
+        let ends = self.infix_ends(fragment);
+        let mut found = Vec::new();
+        if ends.is_empty() {
+            return found;
+        }
+
+        // (depth, key, whether the path so far contains `fragment`)
+        let mut path = Vec::new();
+        let mut stack = self.starts.iter().rev().map(|key| (1, *key, false)).collect::<Vec<_>>();
+        while let Some((depth, key, matched)) = stack.pop() {
+            let node = &self.children[&key];
+            let matched = matched || ends.contains(&key);
+            path.truncate(depth - 1);
+            path.push(node.to_value());
+            if matched && node.is_terminal() {
+                found.push(path.clone());
+            }
+            stack.extend(node.children.iter().rev().map(|key| (depth + 1, *key, matched)));
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chars(w: &str) -> Vec<char> {
+        w.chars().collect()
+    }
+
+    fn infix(trie: &Trie<char>, fragment: &str) -> Vec<String> {
+        trie.search_infix(&chars(fragment)).iter().map(|seq| seq.iter().collect()).collect()
+    }
+
+    #[test]
+    fn infix_positions() {
+        let trie = Trie::from(&["cart", "start", "art", "car", "tar", "cow"][..]);
+
+        // middle
+        assert!(trie.contains_infix(&chars("ar")));
+        assert_eq!(infix(&trie, "ar"), vec!["car", "cart", "start", "art", "tar"]);
+        assert_eq!(infix(&trie, "ta"), vec!["start", "tar"]);
+        // start
+        assert_eq!(infix(&trie, "co"), vec!["cow"]);
+        // end
+        assert_eq!(infix(&trie, "rt"), vec!["cart", "start", "art"]);
+        // whole sequence
+        assert_eq!(infix(&trie, "start"), vec!["start"]);
+        // absent, including a fragment longer than anything stored
+        assert!(!trie.contains_infix(&chars("ac")));
+        assert!(infix(&trie, "ac").is_empty());
+        assert!(infix(&trie, "starting").is_empty());
+        assert!(infix(&trie, "x").is_empty());
+    }
+
+    #[test]
+    fn infix_matches_brute_force() {
+        let trie = Trie::from(&["banana", "bandana", "nab", "ananas", "an"][..]);
+        let every = trie.sequences();
+        for fragment in ["an", "ana", "nan", "a", "n", "band", "nas", "bb"].iter() {
+            let fragment = chars(fragment);
+            let expected = every.iter()
+                .filter(|seq| seq.windows(fragment.len()).any(|w| w == &fragment[..]))
+                .cloned()
+                .collect::<Vec<_>>();
+            assert_eq!(trie.contains_infix(&fragment), !expected.is_empty());
+            assert_eq!(trie.search_infix(&fragment), expected);
+        }
+    }
+
+    #[test]
+    fn infix_empty() {
+        let trie = Trie::from(&["cat"][..]);
+        assert!(trie.contains_infix(&[]));
+        assert_eq!(trie.search_infix(&[]), vec![chars("cat")]);
+
+        let empty = Trie::<char>::new();
+        assert!(!empty.contains_infix(&[]));
+        assert!(!empty.contains_infix(&['a']));
+        assert!(empty.search_infix(&['a']).is_empty());
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::node::Node;
+use crate::Trie;
+
+/// A broken structural rule found by `Trie::check_invariants`, `path` is the
+/// path of the offending node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation<T> {
+    /// A child key with no node in the map.
+    DanglingChild { path: Vec<T>, child: u64 },
+    /// `child_size` disagrees with the children actually stored.
+    ChildSize { path: Vec<T>, child_size: usize, children: usize },
+    /// A node that ends no sequence and leads to none.
+    NonTerminalLeaf { path: Vec<T> },
+    /// The cached terminal count disagrees with the subtree.
+    TerminalCount { path: Vec<T>, cached: usize, actual: usize },
+    /// An entry of `starts` with no node in the map.
+    MissingStart { key: u64 },
+    /// The same start is listed more than once.
+    DuplicateStart { key: u64 },
+    /// A node in the map that can't be reached from any start.
+    Unreachable { key: u64 },
+    /// The node counter disagrees with the map.
+    NodeCount { counted: usize, actual: usize },
+    /// The sequence counter disagrees with the terminal nodes.
+    SequenceCount { counted: usize, actual: usize },
+}
+
+/// Panics with every violation when the `check-invariants` feature is on in a
+/// debug build, otherwise does nothing. Given the `keys` of the path one
+/// insert or remove touched only that path is checked.
+macro_rules! debug_assert_invariants {
+    ($trie:expr) => {
+        #[cfg(all(debug_assertions, feature = "check-invariants"))]
+        {
+            if let Err(violations) = $trie.check_invariants() {
+                panic!("trie invariants violated: {:#?}", violations);
+            }
+        }
+    };
+    ($trie:expr, $keys:expr) => {
+        #[cfg(all(debug_assertions, feature = "check-invariants"))]
+        {
+            if let Err(violations) = $trie.check_path($keys) {
+                panic!("trie invariants violated: {:#?}", violations);
+            }
+        }
+    };
+}
+
+/// A node reached by `check_invariants`, `terminals` is summed up from its
+/// children once they have all been reached.
+struct Visit<'a, T> {
+    node: &'a Node<T>,
+    parent: Option<usize>,
+    terminals: usize,
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Walks the whole structure and reports every broken invariant.
+    ///
+    /// This is O(number of nodes) and meant for tests and debugging, enable the
+    /// `check-invariants` feature to have every change checked in debug builds.
+    pub fn check_invariants(&self) -> Result<(), Vec<InvariantViolation<T>>> {
+        let mut violations = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+
+        let mut starts = HashSet::new();
+        for key in self.starts.iter() {
+            if !starts.insert(key) {
+                violations.push(InvariantViolation::DuplicateStart { key: *key });
+            } else if self.children.contains_key(key) {
+                stack.push((*key, None));
+            } else {
+                violations.push(InvariantViolation::MissingStart { key: *key });
+            }
+        }
+        stack.reverse();
+
+        // pre-order with an explicit stack, a long sequence is as deep as it
+        // is long
+        let mut visits: Vec<Visit<'_, T>> = Vec::new();
+        while let Some((key, parent)) = stack.pop() {
+            let node = match self.children.get(&key) {
+                Some(node) => node,
+                None => {
+                    violations.push(InvariantViolation::DanglingChild {
+                        path: path_of(&visits, parent),
+                        child: key,
+                    });
+                    continue;
+                }
+            };
+            if !visited.insert(key) {
+                continue;
+            }
+            visits.push(Visit { node, parent, terminals: node.is_terminal() as usize });
+            let idx = visits.len() - 1;
+            if node.child_size != node.children.len() {
+                violations.push(InvariantViolation::ChildSize {
+                    path: path_of(&visits, Some(idx)),
+                    child_size: node.child_size,
+                    children: node.children.len(),
+                });
+            }
+            if node.children.is_empty() && !node.is_terminal() {
+                violations.push(InvariantViolation::NonTerminalLeaf { path: path_of(&visits, Some(idx)) });
+            }
+            stack.extend(node.children.iter().rev().map(|child| (*child, Some(idx))));
+        }
+
+        // children come after their parent so in reverse each node has its
+        // whole subtree counted before it is compared
+        let mut terminals = 0;
+        for idx in (0..visits.len()).rev() {
+            let visit = &visits[idx];
+            let (cached, actual, parent) = (visit.node.descendant_terminals, visit.terminals, visit.parent);
+            if cached != actual {
+                violations.push(InvariantViolation::TerminalCount {
+                    path: path_of(&visits, Some(idx)),
+                    cached,
+                    actual,
+                });
+            }
+            match parent {
+                Some(parent) => visits[parent].terminals += actual,
+                None => terminals += actual,
+            }
+        }
+
+        for key in self.children.keys().filter(|key| !visited.contains(*key)) {
+            violations.push(InvariantViolation::Unreachable { key: *key });
+        }
+        self.check_counts(terminals, &mut violations);
+        result(violations)
+    }
+
+    /// Checks what a single insert or remove of the sequence whose path is
+    /// `keys` can break: every node left on the path, its entry in `starts`
+    /// and the counters.
+    ///
+    /// A node's terminal count is compared to the cached counts of its
+    /// children, which is enough when the rest of the trie was valid before
+    /// the change. This is O(length of the path times its fan out) so it can
+    /// run after every change even on large tries.
+    #[cfg_attr(not(all(debug_assertions, feature = "check-invariants")), allow(dead_code))]
+    pub(crate) fn check_path(&self, keys: &[u64]) -> Result<(), Vec<InvariantViolation<T>>> {
+        let mut violations = Vec::new();
+        if let Some(first) = keys.first() {
+            let listed = self.starts.iter().filter(|key| *key == first).count();
+            match (self.children.contains_key(first), listed) {
+                (true, 0) => violations.push(InvariantViolation::Unreachable { key: *first }),
+                (false, 1..) => violations.push(InvariantViolation::MissingStart { key: *first }),
+                (true, 2..) => violations.push(InvariantViolation::DuplicateStart { key: *first }),
+                _ => {}
+            }
+        }
+
+        let mut path = Vec::with_capacity(keys.len());
+        for key in keys {
+            // a removal takes the end of the path with it
+            let node = match self.children.get(key) {
+                Some(node) => node,
+                None => break,
+            };
+            path.push(node.as_value());
+            if node.child_size != node.children.len() {
+                violations.push(InvariantViolation::ChildSize {
+                    path: owned(&path),
+                    child_size: node.child_size,
+                    children: node.children.len(),
+                });
+            }
+            if node.children.is_empty() && !node.is_terminal() {
+                violations.push(InvariantViolation::NonTerminalLeaf { path: owned(&path) });
+            }
+            let mut actual = node.is_terminal() as usize;
+            for child in node.children.iter() {
+                match self.children.get(child) {
+                    Some(child) => actual += child.descendant_terminals,
+                    None => violations.push(InvariantViolation::DanglingChild {
+                        path: owned(&path),
+                        child: *child,
+                    }),
+                }
+            }
+            if node.descendant_terminals != actual {
+                violations.push(InvariantViolation::TerminalCount {
+                    path: owned(&path),
+                    cached: node.descendant_terminals,
+                    actual,
+                });
+            }
+        }
+
+        let terminals = self.starts.iter()
+            .filter_map(|key| self.children.get(key))
+            .map(|node| node.descendant_terminals)
+            .sum();
+        self.check_counts(terminals, &mut violations);
+        result(violations)
+    }
+
+    /// Compares the counters to the map and to the `terminals` found.
+    fn check_counts(&self, terminals: usize, violations: &mut Vec<InvariantViolation<T>>) {
+        if self.len != self.children.len() {
+            violations.push(InvariantViolation::NodeCount {
+                counted: self.len,
+                actual: self.children.len(),
+            });
+        }
+        if self.seq_len != terminals {
+            violations.push(InvariantViolation::SequenceCount {
+                counted: self.seq_len,
+                actual: terminals,
+            });
+        }
+    }
+}
+
+fn result<T>(violations: Vec<InvariantViolation<T>>) -> Result<(), Vec<InvariantViolation<T>>> {
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+/// The path of the visit at `idx` by following the parents up, only built
+/// when reporting a violation.
+fn path_of<T: Eq + Hash + Clone + Debug>(visits: &[Visit<'_, T>], mut idx: Option<usize>) -> Vec<T> {
+    let mut path = Vec::new();
+    while let Some(i) = idx {
+        path.push(visits[i].node.as_value().clone());
+        idx = visits[i].parent;
+    }
+    path.reverse();
+    path
+}
+
+/// The path is only cloned when reporting a violation.
+fn owned<T: Clone>(path: &[&T]) -> Vec<T> {
+    path.iter().map(|t| (*t).clone()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::key::sequence_key;
+
+    fn words() -> Trie<char> {
+        let mut trie = Trie::new();
+        for word in ["car", "cart", "carrot", "cab", "cow", "dog"].iter() {
+            trie.insert_str(word);
+        }
+        trie
+    }
+
+    #[test]
+    fn valid_after_mutation() {
+        let mut trie = words();
+        assert_eq!(trie.check_invariants(), Ok(()));
+        for word in ["cart", "car", "dog", "cab", "carrot", "cow"].iter() {
+            trie.remove_str(word);
+            assert_eq!(trie.check_invariants(), Ok(()));
+        }
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn reports_violations() {
+        let mut trie = words();
+        trie.children.remove(&sequence_key(&['c', 'o', 'w']));
+        let r = trie.children.get_mut(&sequence_key(&['c', 'a', 'r'])).unwrap();
+        r.terminal = false;
+        r.child_size = 7;
+        trie.children.get_mut(&sequence_key(&['d', 'o', 'g'])).unwrap().terminal = false;
+
+        let violations = trie.check_invariants().unwrap_err();
+        assert!(violations.contains(&InvariantViolation::DanglingChild {
+            path: vec!['c', 'o'],
+            child: sequence_key(&['c', 'o', 'w']),
+        }));
+        assert!(violations.contains(&InvariantViolation::TerminalCount {
+            path: vec!['c', 'o'],
+            cached: 1,
+            actual: 0,
+        }));
+        assert!(violations.contains(&InvariantViolation::ChildSize {
+            path: vec!['c', 'a', 'r'],
+            child_size: 7,
+            children: 2,
+        }));
+        assert!(violations.contains(&InvariantViolation::TerminalCount {
+            path: vec!['c', 'a', 'r'],
+            cached: 3,
+            actual: 2,
+        }));
+        assert!(violations.contains(&InvariantViolation::NonTerminalLeaf {
+            path: vec!['d', 'o', 'g'],
+        }));
+        assert!(violations.contains(&InvariantViolation::NodeCount {
+            counted: 13,
+            actual: 12,
+        }));
+        assert!(violations.contains(&InvariantViolation::SequenceCount {
+            counted: 6,
+            actual: 3,
+        }));
+    }
+
+    #[test]
+    fn path_check_sees_only_the_path() {
+        let mut trie = words();
+        let keys = crate::key::path_keys(&['c', 'a', 'r', 't']).collect::<Vec<_>>();
+        assert_eq!(trie.check_path(&keys), Ok(()));
+
+        trie.children.get_mut(&sequence_key(&['d', 'o', 'g'])).unwrap().terminal = false;
+        trie.children.get_mut(&sequence_key(&['c', 'a'])).unwrap().descendant_terminals = 9;
+        let violations = trie.check_path(&keys).unwrap_err();
+        // "dog" is off the path
+        assert_eq!(violations, vec![
+            InvariantViolation::TerminalCount { path: vec!['c'], cached: 5, actual: 10 },
+            InvariantViolation::TerminalCount { path: vec!['c', 'a'], cached: 9, actual: 4 },
+        ]);
+
+        // a removed tail is fine, a start left out isn't
+        let gone = crate::key::path_keys(&['e', 'm', 'u']).collect::<Vec<_>>();
+        assert!(trie.check_path(&gone).is_ok());
+        let first = trie.starts.remove(0);
+        assert!(trie.check_path(&keys).unwrap_err().contains(&InvariantViolation::Unreachable { key: first }));
+    }
+
+    #[test]
+    fn long_sequence_checks() {
+        // deeper than a recursive walk would manage on a test thread's stack
+        let mut trie = Trie::new();
+        trie.insert(&vec![7u8; 100_000]);
+        assert_eq!(trie.check_invariants(), Ok(()));
+        trie.children.get_mut(&sequence_key(&vec![7u8; 99_999])).unwrap().child_size = 0;
+        assert_eq!(trie.check_invariants(), Err(vec![InvariantViolation::ChildSize {
+            path: vec![7; 99_999],
+            child_size: 0,
+            children: 1,
+        }]));
+    }
+}
@@ -0,0 +1,144 @@
+use std::error::Error;
+use std::fmt;
+use std::hash::BuildHasher;
+
+use serde_json::{json, Value};
+
+use crate::Trie;
+
+/// The ways reading a JSON word list with `Trie::from_json` can fail.
+#[derive(Debug)]
+pub enum JsonError {
+    /// The input isn't valid JSON.
+    Syntax(serde_json::Error),
+    /// Valid JSON that isn't a word list.
+    Shape(&'static str),
+    /// `count` disagrees with the number of distinct sequences.
+    Count { expected: u64, found: usize },
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Syntax(err) => write!(f, "invalid JSON: {}", err),
+            JsonError::Shape(why) => write!(f, "not a word list: {}", why),
+            JsonError::Count { expected, found } => {
+                write!(f, "count is {} but there are {} sequences", expected, found)
+            }
+        }
+    }
+}
+
+impl Error for JsonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JsonError::Syntax(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonError::Syntax(err)
+    }
+}
+
+/// A word list `{"count": N, "sequences": ["cat", "cow"]}`, it holds the stored
+/// sequences not the nodes so it doesn't depend on how the trie is laid out.
+impl<S: BuildHasher + Default> Trie<char, S> {
+    /// Writes every stored word in traversal order with their count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["cat", "cow"][..]);
+    ///
+    /// assert_eq!(trie.to_json(), r#"{"count":2,"sequences":["cat","cow"]}"#);
+    /// ```
+    pub fn to_json(&self) -> String {
+        let sequences = self.sequences()
+            .into_iter()
+            .map(|seq| seq.into_iter().collect::<String>())
+            .collect::<Vec<_>>();
+        json!({ "count": sequences.len(), "sequences": sequences }).to_string()
+    }
+}
+
+impl Trie<char> {
+    /// Builds a trie from a word list written by `to_json`. `count` is
+    /// optional, when present it must match the number of distinct non empty
+    /// words.
+    pub fn from_json(json: &str) -> Result<Trie<char>, JsonError> {
+        let value = serde_json::from_str::<Value>(json)?;
+        let sequences = value.get("sequences")
+            .ok_or(JsonError::Shape("missing \"sequences\""))?
+            .as_array()
+            .ok_or(JsonError::Shape("\"sequences\" is not an array"))?;
+
+        let mut trie = Trie::new();
+        let mut seq = Vec::new();
+        for word in sequences {
+            let word = word.as_str().ok_or(JsonError::Shape("a sequence is not a string"))?;
+            seq.clear();
+            seq.extend(word.chars());
+            trie.insert(&seq);
+        }
+
+        if let Some(count) = value.get("count") {
+            let expected = count.as_u64().ok_or(JsonError::Shape("\"count\" is not an integer"))?;
+            if expected != trie.sequence_count() as u64 {
+                return Err(JsonError::Count { expected, found: trie.sequence_count() });
+            }
+        }
+        Ok(trie)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let text = std::fs::read_to_string("data/sun-rising.txt").unwrap();
+        let trie = text.split_whitespace().collect::<Trie<char>>();
+
+        let read = Trie::from_json(&trie.to_json()).unwrap();
+        assert_eq!(read, trie);
+        assert_eq!(read.sequences(), trie.sequences());
+
+        let empty = Trie::<char>::new();
+        assert_eq!(empty.to_json(), r#"{"count":0,"sequences":[]}"#);
+        assert!(Trie::from_json(&empty.to_json()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn json_fixture() {
+        let json = std::fs::read_to_string("data/words.json").unwrap();
+        let trie = Trie::from_json(&json).unwrap();
+        assert_eq!(trie.sequence_count(), 5);
+        assert!(trie.contains_str("carrot"));
+        assert!(trie.contains_str("naïve"));
+        assert_eq!(trie.to_json(), r#"{"count":5,"sequences":["car","cart","carrot","cow","naïve"]}"#);
+    }
+
+    #[test]
+    fn json_errors() {
+        assert!(matches!(Trie::from_json("{\"sequences\": [\"cat\""), Err(JsonError::Syntax(_))));
+        assert!(matches!(Trie::from_json("[\"cat\"]"), Err(JsonError::Shape(_))));
+        assert!(matches!(Trie::from_json("{\"words\": []}"), Err(JsonError::Shape(_))));
+        assert!(matches!(Trie::from_json("{\"sequences\": \"cat\"}"), Err(JsonError::Shape(_))));
+        assert!(matches!(Trie::from_json("{\"sequences\": [\"cat\", 7]}"), Err(JsonError::Shape(_))));
+        assert!(matches!(
+            Trie::from_json("{\"count\": \"2\", \"sequences\": [\"cat\"]}"),
+            Err(JsonError::Shape(_))
+        ));
+        assert!(matches!(
+            Trie::from_json("{\"count\": 2, \"sequences\": [\"cat\", \"cat\"]}"),
+            Err(JsonError::Count { expected: 2, found: 1 })
+        ));
+        assert!(Trie::from_json("{\"sequences\": [\"cat\", \"cat\"]}").is_ok());
+    }
+}
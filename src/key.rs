@@ -2,17 +2,153 @@ use std::hash::{Hash, Hasher};
 
 use fnv::FnvHasher;
 
-pub(crate) fn make_key<T: Hash>(to_hash: (&[T], &T)) -> u64 {
-    let mut hasher = FnvHasher::default();
-    to_hash.hash(&mut hasher);
+/// FNV fed every integer little endian and every `usize` or `isize` as 64
+/// bits, the std `Hash` impls write native endian integers which would give a
+/// big endian or 32 bit target different keys.
+struct KeyHasher(FnvHasher);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.0.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.0.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.0.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// The key of the path ending in `val`, `parent` is the key of the path before
+/// it or `None` for a start.
+///
+/// A key is the FNV state after hashing every element of its path in order so
+/// a child's key carries on from its parent's, a whole path is hashed once
+/// instead of rehashing every prefix.
+pub(crate) fn child_key<T: Hash>(parent: Option<u64>, val: &T) -> u64 {
+    let mut hasher = KeyHasher(match parent {
+        Some(key) => FnvHasher::with_key(key),
+        None => FnvHasher::default(),
+    });
+    val.hash(&mut hasher);
     hasher.finish()
 }
 
-pub(crate) fn key_from_seq<T: Hash>(seq: &[T]) -> u64 {
-    let i = seq.len() - 1;
-    make_key((&seq[..i], &seq[i]))
+/// The key of every prefix of `seq` shortest first, the last is the key of
+/// `seq`.
+pub(crate) fn path_keys<T: Hash>(seq: &[T]) -> impl Iterator<Item = u64> + '_ {
+    seq.iter().scan(None, |parent, val| {
+        let key = child_key(*parent, val);
+        *parent = Some(key);
+        Some(key)
+    })
+}
+
+/// The `u64` key the trie stores the node at the end of `prefix` under, what
+/// `PreHashedMap` is keyed by.
+///
+/// It is FNV-1a with its standard offset basis fed every element of `prefix`
+/// in order, no per process seed is involved, so the same `prefix` gives the
+/// same key in every process and the algorithm won't change between patch
+/// versions. Integers written by `T`'s `Hash` impl are hashed little endian
+/// and `usize` lengths as 64 bits, so the std impls for integers, `char` and
+/// strings give the same key on every target. An empty `prefix` gives the
+/// offset basis, no node is stored under it.
+///
+/// Sequences can be sharded across tries by key range and looked up again the
+/// same way.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_trie::sequence_key;
+/// assert_ne!(sequence_key(&['c', 'a', 't']), sequence_key(&['c', 'a']));
+/// assert_eq!(sequence_key::<char>(&[]), 0xcbf2_9ce4_8422_2325);
+/// ```
+pub fn sequence_key<T: Hash>(prefix: &[T]) -> u64 {
+    path_keys(prefix).last().unwrap_or_else(|| FnvHasher::default().finish())
 }
 
-pub(crate) fn key_at_index<T: Hash>(idx: usize, seq: &[T]) -> u64 {
-    make_key((&seq[..idx], &seq[idx]))
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keys_chain() {
+        let seq = ['c', 'a', 't'];
+        let keys = path_keys(&seq).collect::<Vec<_>>();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[0], child_key(None, &'c'));
+        assert_eq!(keys[2], child_key(Some(keys[1]), &'t'));
+        assert_eq!(keys[1], sequence_key(&seq[..2]));
+        assert_eq!(keys[2], sequence_key(&seq));
+
+        // the same element under different parents
+        assert_ne!(sequence_key(&['a']), sequence_key(&['c', 'a']));
+        assert_ne!(sequence_key(&['a', 'c']), sequence_key(&['c', 'a']));
+        let words = |s: &[&str]| sequence_key(&s.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        assert_ne!(words(&["ab", "c"]), words(&["a", "bc"]));
+    }
+
+    #[test]
+    fn sequence_key_pinned() {
+        // changing any of these breaks every persisted or sharded key
+        assert_eq!(sequence_key::<u8>(&[]), 0xcbf2_9ce4_8422_2325);
+        // FNV-1a of the single byte "a"
+        assert_eq!(sequence_key(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(sequence_key(&[1u32, 2, 3]), 0xfd1f_0f43_81eb_0395);
+        assert_eq!(sequence_key(&['c', 'a', 't']), 0x702f_6114_4503_1873);
+        assert_eq!(sequence_key(&["ecs".to_string(), "trie".to_string()]), 0x5994_afa4_07b7_6b14);
+    }
+
+    #[test]
+    fn integers_hash_little_endian() {
+        let fnv = |bytes: &[u8]| {
+            let mut hasher = FnvHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(sequence_key(&[0x0102_0304u32]), fnv(&[4, 3, 2, 1]));
+        assert_eq!(sequence_key(&[-2i16]), fnv(&[0xfe, 0xff]));
+        assert_eq!(sequence_key(&[0x0102usize]), fnv(&[2, 1, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(sequence_key(&['a']), fnv(&[0x61, 0, 0, 0]));
+    }
 }
@@ -22,122 +22,438 @@
 //!           ^  ^ o's 
 //!          a's
 //! <br>
+use std::borrow::Cow;
 use std::fmt::Debug;
-use std::hash::{Hash, Hasher};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::collections::HashMap;
 use std::collections::hash_map::Entry;
+use std::ops::ControlFlow;
+use std::sync::Arc;
 
 use fnv::FnvHasher;
 
+#[macro_use]
+mod invariants;
+pub use invariants::InvariantViolation;
 mod key;
-use key::{make_key, key_from_seq, key_at_index};
+use key::path_keys;
+pub use key::sequence_key;
 mod node;
-use node::{Node};
+use node::{Node, DepthFirst};
+pub use node::{SubtreeIter, Terminals};
 mod noop_hash;
-pub use noop_hash::PreHashedMap;
+mod storage;
+use storage::Storage;
+pub use noop_hash::{NoopBuildHasher, PreHashedMap};
+mod display;
+mod chars;
+pub use chars::Delimiter;
+mod bytes;
+mod binary;
+mod ordered;
+mod arena;
+pub use arena::{ArenaIter, ArenaTrie};
+mod btree;
+pub use btree::OrderedTrie;
+mod prefix_ref;
+pub use prefix_ref::{NodeRef, PrefixRef};
+mod weighted;
+mod bounded;
+use bounded::Lru;
+mod frozen;
+pub use frozen::FrozenTrie;
+mod reader;
+pub use reader::TrieReader;
+mod compiled;
+pub use compiled::CompiledTrie;
+#[cfg(feature = "mmap")]
+mod mapped;
+#[cfg(feature = "mmap")]
+pub use mapped::{MappedElement, MappedTrie};
+mod builder;
+pub use builder::{LengthPolicy, TrieBuilder};
+mod stats;
+pub use stats::TrieStats;
+mod batch;
+pub use batch::{BatchError, BatchSummary, Op};
+mod suffix;
+mod infix;
+mod relations;
+mod absorb;
+mod prune;
+mod bloom;
+use bloom::Bloom;
+mod limits;
+use limits::Budget;
+pub use limits::InsertError;
+mod observer;
+pub use observer::TrieObserver;
+mod position;
+mod query;
+pub use query::QueryKey;
+#[cfg(feature = "serde_json")]
+mod json;
+#[cfg(feature = "serde_json")]
+pub use json::JsonError;
+#[cfg(feature = "serde")]
+mod found_serde;
+#[cfg(feature = "serde")]
+pub use found_serde::serialize_as_strings;
+pub use binary::{BinaryElement, DecodeError};
 
-#[derive(Debug, Clone)]
-pub struct Trie<T> {
+/// Applied to every element on the way into and out of the trie, see
+/// `Trie::with_normalizer`.
+pub type Normalizer<T> = Arc<dyn Fn(&T) -> T + Send + Sync>;
+
+pub struct Trie<T, S = NoopBuildHasher> {
     starts: Vec<u64>,
-    children: PreHashedMap<u64, Node<T>>,
-    /// number of unique items T inserted into the trie.
+    children: HashMap<u64, Node<T>, S>,
+    /// number of nodes, each unique prefix of the stored sequences.
     len: usize,
+    /// number of stored sequences, terminal nodes.
+    seq_len: usize,
+    normalizer: Option<Normalizer<T>>,
+    /// set for a trie with a maximum number of sequences.
+    lru: Option<Lru<T>>,
+    /// every stored sequence reversed, set by `TrieBuilder::suffix_index`.
+    suffixes: Option<Box<Trie<T, S>>>,
+    /// set by `TrieBuilder::max_sequence_len`.
+    max_len: Option<(usize, LengthPolicy)>,
+    /// number of inserts `max_len` truncated or rejected.
+    limited: usize,
+    /// set by `TrieBuilder::bloom_filter`.
+    bloom: Option<Bloom>,
+    /// limits `try_insert` checks, unlimited by default.
+    budget: Budget,
+    /// set by `set_observer`, never cloned.
+    observer: Option<Box<dyn TrieObserver<T>>>,
 }
-impl<T> Default for Trie<T> {
+impl<T, S: Default> Default for Trie<T, S> {
     fn default() -> Self {
         Self {
-            children: PreHashedMap::default(),
+            children: HashMap::default(),
             starts: Vec::default(),
             len: 0,
+            seq_len: 0,
+            normalizer: None,
+            lru: None,
+            suffixes: None,
+            max_len: None,
+            limited: 0,
+            bloom: None,
+            budget: Budget::default(),
+            observer: None,
+        }
+    }
+}
+
+impl<T: Clone, S: Clone + BuildHasher> Clone for Trie<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            children: self.children.clone(),
+            starts: self.starts.clone(),
+            len: self.len,
+            seq_len: self.seq_len,
+            normalizer: self.normalizer.clone(),
+            lru: self.lru.clone(),
+            suffixes: self.suffixes.clone(),
+            max_len: self.max_len,
+            limited: self.limited,
+            bloom: self.bloom.clone(),
+            budget: self.budget,
+            observer: None,
+        }
+    }
+
+    /// Reuses the map, `starts` and each surviving node's children `Vec` instead
+    /// of reallocating them.
+    fn clone_from(&mut self, source: &Self) {
+        self.starts.clone_from(&source.starts);
+        self.len = source.len;
+        self.seq_len = source.seq_len;
+        self.normalizer.clone_from(&source.normalizer);
+        self.lru.clone_from(&source.lru);
+        self.suffixes.clone_from(&source.suffixes);
+        self.max_len = source.max_len;
+        self.limited = source.limited;
+        self.bloom.clone_from(&source.bloom);
+        self.budget = source.budget;
+        self.observer = None;
+        // drop only the nodes `source` doesn't have, the rest are cloned into in place
+        self.children.retain(|key, _| source.children.contains_key(key));
+        for (key, node) in source.children.iter() {
+            match self.children.entry(*key) {
+                Entry::Occupied(mut entry) => entry.get_mut().clone_from(node),
+                Entry::Vacant(entry) => {
+                    entry.insert(node.clone());
+                }
+            }
         }
     }
 }
 
+/// Two tries are equal when they store the same set of sequences, regardless
+/// of the order they were inserted in.
+impl<T: Eq, S: BuildHasher> PartialEq for Trie<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        // node keys are hashes of the full path so a terminal key is the sequence
+        let terminals = |trie: &Self| trie.children.values().filter(|n| n.terminal).count();
+        terminals(self) == terminals(other)
+            && self.children.iter()
+                .filter(|(_, n)| n.terminal)
+                .all(|(key, _)| other.children.get(key).is_some_and(|n| n.terminal))
+    }
+}
+
+impl<T: Eq, S: BuildHasher> Eq for Trie<T, S> {}
+
+/// Hashes the set of stored sequences, consistent with `PartialEq`.
+///
+/// This is O(number of nodes), every node is visited once, but no sequence
+/// is rebuilt since each terminal node's key is already the hash of its
+/// sequence. The keys are combined by wrapping addition so insertion order
+/// does not matter.
+impl<T: Hash, S> Hash for Trie<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let (count, sum) = self.children.iter()
+            .filter(|(_, n)| n.terminal)
+            .fold((0_usize, 0_u64), |(count, sum), (key, _)| {
+                (count + 1, sum.wrapping_add(*key))
+            });
+        count.hash(state);
+        sum.hash(state);
+    }
+}
+
 impl<T> Trie<T> 
 where
     T: Eq + Hash + Clone + Debug,
 {
     pub fn new() -> Self {
-        Trie { children: PreHashedMap::default(), starts: Vec::default(), len: 0, }
+        Trie::default()
+    }
+
+    /// Creates an empty `Trie` that passes every element through `normalizer`
+    /// before inserting, looking up or removing it, so queries always agree
+    /// with the stored canonical form.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+    /// trie.insert(&['C', 'a', 'T']);
+    ///
+    /// assert!(trie.contains(&['c', 'A', 't']));
+    /// ```
+    pub fn with_normalizer<F>(normalizer: F) -> Self
+    where
+        F: Fn(&T) -> T + Send + Sync + 'static,
+    {
+        Trie { normalizer: Some(Arc::new(normalizer)), ..Trie::new() }
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// `seq` in canonical form, only allocates when a normalizer is set.
+    fn normalize<'a>(&self, seq: &'a [T]) -> Cow<'a, [T]> {
+        match &self.normalizer {
+            Some(normalize) => Cow::Owned(seq.iter().map(|t| normalize(t)).collect()),
+            None => Cow::Borrowed(seq),
+        }
+    }
+
+    /// `seq` as `max_sequence_len` allows it to be inserted, `None` if it is
+    /// rejected.
+    fn limited<'a>(&self, seq: &'a [T]) -> Option<&'a [T]> {
+        match self.max_len {
+            Some((max, policy)) if seq.len() > max => match policy {
+                LengthPolicy::Truncate => Some(&seq[..max]),
+                LengthPolicy::Reject => None,
+            },
+            _ => Some(seq),
+        }
+    }
+
+    /// `limited` counting each truncated or rejected `seq`.
+    fn limit<'a>(&mut self, seq: &'a [T]) -> Option<&'a [T]> {
+        let limited = self.limited(seq);
+        if limited.is_none_or(|limited| limited.len() < seq.len()) {
+            self.limited += 1;
+        }
+        limited
+    }
+
+    /// The number of inserts truncated or rejected because they were longer
+    /// than `TrieBuilder::max_sequence_len`.
+    pub fn limited_count(&self) -> usize {
+        self.limited
     }
 
+    /// Returns `true` if no sequences are stored.
     pub fn is_empty(&self) -> bool {
-        self.len == 0
-    }
-
-    fn _insert(&mut self, seq: &[T], val: Option<T>, mut idx: usize) {
-        if let Some(val) = val {
-            let key = make_key((&seq[..idx], &val));
-
-            if self.children.contains_key(&key) {
-                // add new keys to Node.children vec
-                // we just checked its in here
-                let node = self.children.get_mut(&key).unwrap();
-                node.update_children(seq, idx);
-                idx += 1;
-                if let Some(next) = seq.get(idx) {
-                    self._insert(seq, Some(next.clone()), idx);
-                    return;
-                }
-                return;
-            }
+        self.seq_len == 0
+    }
 
-            let terminal = seq.len() == idx + 1;
-            let node = Node::new(val, &seq, idx, terminal);
-            self.children.insert(key, node);
-            self.len += 1;
-            // if terminal { return };
-            idx += 1;
-            if let Some(next) = seq.get(idx) {
-                self._insert(seq, Some(next.clone()), idx)
+    /// Returns the number of nodes, one per distinct prefix of the stored
+    /// sequences.
+    pub fn node_count(&self) -> usize {
+        self.len
+    }
+
+    /// Inserts the sequence whose path is `keys`, taking one of `vals` per level
+    /// and making missing nodes from it with `into_val`. Returns `true` if the
+    /// sequence was not already stored, every node on the path then counts one
+    /// more terminal below it.
+    ///
+    /// A node only exists if its parent does so the nodes already in the trie
+    /// are a prefix of the path, found with a binary search. Below it the new
+    /// nodes are made without looking anything up.
+    fn insert_path<V, I, F>(&mut self, keys: &[u64], vals: I, into_val: F) -> bool
+    where
+        I: Iterator<Item = V>,
+        F: Fn(V) -> T,
+    {
+        let last = keys.len() - 1;
+        if self.children.get(&keys[last]).is_some_and(|node| node.is_terminal()) {
+            return false;
+        }
+        let existing = keys.partition_point(|key| self.children.contains_key(key));
+
+        for key in keys[..existing].iter() {
+            let node = self.children.get_mut(key).expect("the path exists up to `existing`");
+            node.descendant_terminals += 1;
+        }
+        match existing {
+            0 => self.starts.push(keys[0]),
+            // a stored prefix ending on a node that is already there, "car" after "cart"
+            _ if existing == keys.len() => {
+                self.children.get_mut(&keys[last]).expect("the whole path exists").terminal = true;
+            }
+            _ => {
+                let parent = self.children.get_mut(&keys[existing - 1]).expect("the path exists");
+                parent.add_child(keys[existing]);
+            }
+        }
+        for (idx, val) in vals.enumerate().skip(existing) {
+            let mut node = Node::new(into_val(val));
+            if let Some(next) = keys.get(idx + 1) {
+                node.add_child(*next);
             }
+            node.descendant_terminals = 1;
+            node.terminal = idx == last;
+            self.children.insert(keys[idx], node);
+            self.len += 1;
+        }
+        self.seq_len += 1;
+        self.bloom_insert(&keys[existing..]);
+        true
+    }
+
+    /// Inserts `seq` returning `true` if it wasn't already stored.
+    ///
+    /// A trie with a `TrieBuilder::max_sequence_len` inserts a longer `seq`
+    /// truncated or returns `false` without inserting it, depending on the
+    /// policy, `limited_count` counts both.
+    pub fn insert(&mut self, seq: &[T]) -> bool {
+        let inserted = self.insert_unobserved(seq);
+        self.notify_insert(seq, inserted);
+        inserted
+    }
+
+    /// `insert` without telling the observer.
+    fn insert_unobserved(&mut self, seq: &[T]) -> bool {
+        let seq = &*self.normalize(seq);
+        let mut inserted = false;
+        if let Some(seq) = self.limit(seq).filter(|seq| !seq.is_empty()) {
+            let keys = path_keys(seq).collect::<Vec<_>>();
+            inserted = self.insert_keys(seq, &keys);
+            debug_assert_invariants!(self, &keys);
         }
+        inserted
     }
 
-    pub fn insert(&mut self, seq: &[T]) {
-        if let Some(first) = seq.first() {
-            let key = make_key((&[], first));
-            if !self.starts.contains(&key) { self.starts.push(key) };
-            self._insert(seq, Some(first.clone()), 0)
+    /// Inserts the normalized `seq` whose path is `keys`, keeping the indexes
+    /// in sync, returning `true` if it wasn't already stored.
+    fn insert_keys(&mut self, seq: &[T], keys: &[u64]) -> bool {
+        let inserted = self.insert_path(keys, seq.iter(), T::clone);
+        if inserted {
+            self.index_suffix(seq);
         }
+        self.touch_inserted(seq);
+        inserted
     }
 
-    fn _search<'n>(
-        map: &PreHashedMap<u64, Node<T>>,
-        node: &'n Node<T>,
-        seq_key: &[T],
-        idx: usize,
-        found: &mut Found<T>
-    ) {        
-        // complete terminal branch no children
-        if node.is_terminal() && node.child_len() == 0 {
-            found.branch_end();
-            return;
-        // terminal but children after
-        } else if node.is_terminal() {
-            found.branch_end_continue();
+    /// Inserts `seq` moving its elements into the trie.
+    ///
+    /// `insert` clones the element of every node it creates. Nodes are keyed
+    /// by a hash of their path so here a new node takes its element and
+    /// elements already in the trie are dropped, nothing is cloned. The
+    /// exception is a trie made by `with_max_sequences` which keeps one copy of
+    /// the whole sequence to evict it later.
+    ///
+    /// Returns `true` if `seq` wasn't already stored, a length limit applies
+    /// the same as for `insert`.
+    pub fn insert_owned(&mut self, seq: Vec<T>) -> bool {
+        let observed = self.observer.as_ref().map(|_| seq.clone());
+        let inserted = self.insert_owned_unobserved(seq);
+        if let Some(seq) = observed {
+            self.notify_insert(&seq, inserted);
         }
-        // recurs iteratively over children
-        for n in node.children(map) {
-            found.push_val(n.to_value());
-            Trie::_search(map, n, seq_key, idx + 1, found);
+        inserted
+    }
 
-            // not terminal but has more than one child, if deeper than single
-            // node we need a some way of keeping track of what needs to be removed
-            // from temp vec
-            if !node.is_terminal() && node.child_len() > 1 {
-                found.branch_split(node.as_value());
+    fn insert_owned_unobserved(&mut self, seq: Vec<T>) -> bool {
+        let mut seq = match &self.normalizer {
+            Some(normalize) => seq.iter().map(|t| normalize(t)).collect(),
+            None => seq,
+        };
+        match self.max_len {
+            Some((max, policy)) if seq.len() > max => {
+                self.limited += 1;
+                match policy {
+                    LengthPolicy::Truncate => seq.truncate(max),
+                    LengthPolicy::Reject => return false,
+                }
+            }
+            _ => {}
+        }
+        if seq.is_empty() {
+            return false;
+        }
+        let keys = path_keys(&seq).collect::<Vec<_>>();
+        let recent = self.lru.as_ref().map(|_| seq.clone());
+        let reversed = self.suffixes.as_ref().map(|_| seq.iter().rev().cloned().collect());
+        let inserted = self.insert_path(&keys, seq.into_iter(), |val| val);
+        if inserted {
+            if let (Some(suffixes), Some(reversed)) = (self.suffixes.as_mut(), reversed) {
+                suffixes.insert_owned(reversed);
             }
         }
+        if let Some(seq) = recent {
+            self.touch_inserted(&seq);
+        }
+        debug_assert_invariants!(self, &keys);
+        inserted
     }
 
     // Returns `true` if `seq_key` is found.
     pub fn contains(&self, seq_key: &[T]) -> bool {
-        let key = key_from_seq(seq_key);
+        let seq_key = &*self.normalize(seq_key);
+        let key = sequence_key(seq_key);
+        if !self.bloom.as_ref().is_none_or(|bloom| bloom.might_contain(key)) {
+            return false;
+        }
         self.children.contains_key(&key)
     }
 
-    /// Returns all of the found sequences, walking
-    /// each branch depth first.
+    /// The length of the longest prefix of `query` that is a path in the trie,
+    /// stored or not, so `query[match_depth..]` is where it stops matching.
+    /// 0 when not even the first element is a start.
     ///
     /// # Examples
     ///
@@ -145,239 +461,1848 @@ where
     /// use ecs_trie::Trie;
     /// let mut trie = Trie::new();
     /// trie.insert(&['c', 'a', 't']);
-    /// trie.insert(&['c', 'o', 'w']);
-    /// 
-    /// let found = trie.search(&['c']);
-    /// 
-    /// assert_eq!(
-    ///     found.as_collected().as_slice(),
-    ///     &[ ['c', 'a', 't'], ['c', 'o', 'w'] ]
-    /// );
+    ///
+    /// assert_eq!(trie.match_depth(&['c', 'a', 'r', 't']), 2);
+    /// assert_eq!(trie.match_depth(&['d', 'o', 'g']), 0);
     /// ```
-    pub fn search(&self, seq_key: &[T]) -> Found<T> {
-        let key = key_from_seq(seq_key);
-
-        let mut res = Found::new();
-        res.extend(seq_key.iter().cloned());
-        if let Some(node) = self.children.get(&key) {
-            Trie::_search(&self.children, node, seq_key, 1, &mut res)
-        }
-        res
+    pub fn match_depth(&self, query: &[T]) -> usize {
+        let query = &*self.normalize(query);
+        path_keys(query)
+            .take_while(|key| self.children.contains_key(key))
+            .count()
     }
 
-    pub fn iter(&self) -> TrieIter<T> {
-        TrieIter {
-            trie: self,
-            current: None,
-            starts: &self.starts,
-            children: Vec::default(),
-            idx: 0,
-            next_idx: 0,
-        }
+    /// The lengths of the stored sequences that are prefixes of `seq`, shortest
+    /// first, found in one walk down `seq` that stops at the first element not
+    /// in the trie.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 'r']);
+    /// trie.insert(&['c', 'a', 'r', 't']);
+    ///
+    /// let input = "cartwheel".chars().collect::<Vec<_>>();
+    /// assert_eq!(trie.terminal_prefixes_of(&input), vec![3, 4]);
+    /// ```
+    pub fn terminal_prefixes_of(&self, seq: &[T]) -> Vec<usize> {
+        let seq = &*self.normalize(seq);
+        self.terminal_ends(seq).collect()
     }
 
-    /// Clears the `Trie`, note this leaves the previously
-    /// allocated capacity.
-    pub fn clear(&mut self) {
-        self.len = 0;
-        self.children.clear();
-        self.starts.clear();
-    }
-    /// `key` is child's key `entry` is child's parent node.
-    /// True when node has no children after _remove is called.
-    fn _remove(seq: &[T], key: u64, entry: Entry<u64, Node<T>>) -> bool {
-        let node = entry
-            .and_modify(|n| {
-                n.remove_child(&key);
-            })
-            // TODO Hacky?? we can't insert on a remove! we know all `keys` in `seq` are valid
-            // so if `or_insert_with` runs we have a bug
-            .or_insert_with(|| panic!("tried to remove a non existent child {:?}", seq));
-        node.child_len() == 0
+    /// `terminal_prefixes_of` for an already normalized `seq`.
+    fn terminal_ends<'a>(&'a self, seq: &'a [T]) -> impl Iterator<Item = usize> + 'a {
+        path_keys(seq)
+            .map_while(move |key| self.children.get(&key))
+            .enumerate()
+            .filter(|(_, node)| node.is_terminal())
+            .map(|(i, _)| i + 1)
     }
 
-    pub fn remove(&mut self, seq: &[T]) -> bool {
-        if seq.iter().enumerate()
-            .all(|(i, _)| {
-                let key = key_at_index(i, seq);
-                self.children.contains_key(&key)
-            })
-        {
-            let mut i = seq.len() - 1;
-            let mut key = key_at_index(i, seq);
-            
-            // since we know the sequence is in the trie if it is as long
-            // we can just clear 
-            if self.len == seq.len() {
-                self.clear();
-                return true;
-            }
-            while i > 0 {
-                if Self::_remove(seq, key, self.children.entry(key_at_index(i - 1, seq))) {
-                    self.len -= 1;
-                    println!("{:?}", self.children.remove(&key));
-                } else {
-                    println!("{:?}", self.children.remove(&key));
-                    self.len -= 1;
-                    return true;
+    /// Splits `input` into stored sequences, `None` if it can't be. Of the
+    /// ways to split it the one with the fewest sequences wins, ties going to
+    /// the one with the longest first sequence then the longest second and so
+    /// on. The empty input splits into no sequences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["cat", "cow"][..]);
+    ///
+    /// let words = trie.segment(&"catcow".chars().collect::<Vec<_>>()).unwrap();
+    /// assert_eq!(words, vec![vec!['c', 'a', 't'], vec!['c', 'o', 'w']]);
+    /// assert_eq!(trie.segment(&"catscow".chars().collect::<Vec<_>>()), None);
+    /// ```
+    pub fn segment(&self, input: &[T]) -> Option<Vec<Vec<T>>> {
+        let input = &*self.normalize(input);
+        // best[i] is the fewest sequences `input[i..]` splits into and the
+        // length of the first of them, filled from the end
+        let mut best: Vec<Option<(usize, usize)>> = vec![None; input.len() + 1];
+        best[input.len()] = Some((0, 0));
+        for i in (0..input.len()).rev() {
+            for len in self.terminal_ends(&input[i..]) {
+                if let Some((count, _)) = best[i + len] {
+                    // `fewest` already counts its own word, so `count < fewest` also
+                    // takes a tie, longer words come later and win it
+                    if best[i].is_none_or(|(fewest, _)| count < fewest) {
+                        best[i] = Some((count + 1, len));
+                    }
                 }
-                println!("{}", i);
-                i -= 1;
-                key = key_at_index(i, seq);
             }
-            true
-        } else {
-            false
         }
-    }
-}
 
-#[derive(Debug, Clone)]
-pub struct Found<T> {
-    roll_back: Vec<usize>,
-    temp: Vec<T>,
-    collected: Vec<Vec<T>>,
-}
-impl<T: Clone + PartialEq> Found<T> {
-    fn new() -> Self {
-        Self {
-            roll_back: vec![],
-            temp: vec![],
-            collected: vec![],
+        best[0]?;
+        let mut words = Vec::new();
+        let mut i = 0;
+        while i < input.len() {
+            let (_, len) = best[i].expect("every split point on the best path has a split");
+            words.push(input[i..i + len].to_vec());
+            i += len;
         }
+        Some(words)
     }
 
-    pub fn as_collected(&self) -> Vec<&[T]> {
-        self.collected
-            .iter()
-            .map(|seq| seq.as_slice())
-            .collect::<Vec<_>>()
+    /// The node at the end of `seq`, `None` for an empty or missing path.
+    fn node(&self, seq: &[T]) -> Option<&Node<T>> {
+        self.children.node(seq)
     }
 
-    fn extend<I: IntoIterator<Item = T>>(&mut self, i: I) {
-        self.temp.extend(i)
+    /// Returns `true` if `seq` is stored and no other sequence extends it.
+    pub fn is_leaf(&self, seq: &[T]) -> bool {
+        let seq = &*self.normalize(seq);
+        self.node(seq).is_some_and(|n| n.is_terminal() && n.child_len() == 0)
     }
 
-    fn push_val(&mut self, t: T) {
-        self.temp.push(t);
+    /// Returns `true` if the path `seq` continues past its end, whether or not
+    /// `seq` is itself stored.
+    pub fn has_extensions(&self, seq: &[T]) -> bool {
+        let seq = &*self.normalize(seq);
+        self.node(seq).is_some_and(|n| n.child_len() > 0)
     }
 
-    fn branch_end_continue(&mut self) {
-        self.collected.push(self.temp.clone());
+    /// Returns `true` if at least one stored sequence begins with `prefix`.
+    ///
+    /// Unlike `contains` this also checks that a terminal node exists at or
+    /// below the end of `prefix`.
+    pub fn starts_with(&self, prefix: &[T]) -> bool {
+        let prefix = &*self.normalize(prefix);
+        if prefix.is_empty() {
+            return self.starts.iter()
+                .filter_map(|key| self.children.get(key))
+                .any(|node| self.has_terminal(node));
+        }
+        self.node(prefix).is_some_and(|node| self.has_terminal(node))
     }
 
-    fn branch_split(&mut self, key: &T) {
-        if let Some(idx) = self.temp.iter().position(|item| key == item) {
-            let (start, _end) = self.temp.split_at(idx + 1);
-            self.temp = start.to_vec();
+    /// Returns the number of stored sequences that begin with `prefix`,
+    /// including `prefix` itself when it is stored.
+    ///
+    /// Every node caches the number of terminals below it so this is a single
+    /// lookup.
+    pub fn count_prefix(&self, prefix: &[T]) -> usize {
+        let prefix = &*self.normalize(prefix);
+        if prefix.is_empty() {
+            return self.starts.iter()
+                .filter_map(|key| self.children.get(key))
+                .map(|node| self.count_terminals(node))
+                .sum();
         }
+        self.node(prefix).map_or(0, |node| self.count_terminals(node))
     }
 
-    fn branch_end(&mut self) {
-        self.collected.push(self.temp.clone());
-        // remove last element
-        self.temp.pop();
+    /// Returns the number of stored sequences.
+    pub fn sequence_count(&self) -> usize {
+        self.seq_len
     }
-}
-#[derive(Debug, Clone)]
-pub struct TrieIter<'a, T> {
-    trie: &'a Trie<T>,
-    current: Option<&'a Node<T>>,
-    starts: &'a [u64],
-    children: Vec<u64>,
-    idx: usize,
-    next_idx: usize,
-}
-impl<'a, T> Iterator for TrieIter<'a, T> 
-where
-    T: Clone + Eq + Hash + Debug,
-{
-    type Item = &'a Node<T>;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.current.is_none() {
-            // this bails us out of the iteration
-            let key = self.starts.get(self.idx)?;
-            self.current = Some(self.trie.children.get(&key)?);
-            self.idx += 1;
-            // we know its there
-            self.children = self.current.unwrap()
-                .walk(self.trie)
-                .map(|n| n.key)
-                .collect::<Vec<_>>();
 
-            self.current
-        } else {
-            let key = self.children[self.next_idx];
-            self.current = self.trie.children.get(&key);
-            self.next_idx += 1;
-
-            if self.next_idx >= self.children.len() {
-                self.next_idx = 0;
-                let curr = self.current.take();
-                curr
-            } else {
-                self.current
-            }
-        }
+    /// Returns every stored sequence in traversal order, starts in the order
+    /// they were first inserted, a sequence before its extensions and children
+    /// in insertion order.
+    pub fn sequences(&self) -> Vec<Vec<T>> {
+        let mut seqs = Vec::new();
+        self.for_each_sequence(|seq| seqs.push(seq.to_vec()));
+        seqs
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs::File;
-    use std::io::Read;
+    /// Every stored sequence that is a proper prefix of another stored sequence,
+    /// "car" when "cart" is stored, in traversal order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["car", "cart", "cow"][..]);
+    ///
+    /// assert_eq!(trie.stems().collect::<Vec<_>>(), vec![vec!['c', 'a', 'r']]);
+    /// ```
+    pub fn stems(&self) -> impl Iterator<Item = Vec<T>> + '_ {
+        // a terminal counts itself so a stem has more than one
+        self.terminal_paths(|node| node.descendant_terminals > 1)
+    }
 
-    const DATA: &[&str] = &["data/1984.txt", "data/sun-rising.txt"];
+    /// Every stored sequence that no other stored sequence extends, in
+    /// traversal order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["car", "cart", "cow"][..]);
+    ///
+    /// assert_eq!(trie.leaves().collect::<Vec<_>>(), vec![vec!['c', 'a', 'r', 't'], vec!['c', 'o', 'w']]);
+    /// ```
+    pub fn leaves(&self) -> impl Iterator<Item = Vec<T>> + '_ {
+        self.terminal_paths(|node| node.child_len() == 0)
+    }
 
-    fn get_text(i: usize) -> Vec<String> {
-        let mut contents = String::new();
-        File::open(&DATA[i])
-            .unwrap()
-            .read_to_string(&mut contents)
-            .unwrap();
-        contents
-            .split_whitespace()
-            .map(|s| s.trim().to_string())
-            .collect()
+    /// The path of every node with whether it is a stored sequence, in
+    /// traversal order so a path always comes before the paths below it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["ca", "cat"][..]);
+    ///
+    /// assert_eq!(trie.paths().collect::<Vec<_>>(), vec![
+    ///     (vec!['c'], false),
+    ///     (vec!['c', 'a'], true),
+    ///     (vec!['c', 'a', 't'], true),
+    /// ]);
+    /// ```
+    pub fn paths(&self) -> impl Iterator<Item = (Vec<T>, bool)> + '_ {
+        DepthFirst::new(self).scan(Vec::new(), |path, (depth, node)| {
+            path.truncate(depth - 1);
+            path.push(node.to_value());
+            Some((path.clone(), node.is_terminal()))
+        })
     }
 
-    fn make_trie(words: &[String]) -> Trie<char> {
-        let mut trie = Trie::new();
-        for w in words {
-            trie.insert(&w.chars().collect::<Vec<_>>());
+    /// The stored sequences whose final node `keep` returns `true` for, only
+    /// those paths are copied.
+    fn terminal_paths<F>(&self, keep: F) -> impl Iterator<Item = Vec<T>> + '_
+    where
+        F: Fn(&Node<T>) -> bool + 'static,
+    {
+        DepthFirst::new(self)
+            .scan(Vec::new(), move |path, (depth, node)| {
+                path.truncate(depth - 1);
+                path.push(node.to_value());
+                Some(if node.is_terminal() && keep(node) { Some(path.clone()) } else { None })
+            })
+            .flatten()
+    }
+
+    /// Returns a new trie with only the stored sequences `f` returns `true`
+    /// for, leaving this one as it is. The normalizer is shared with the copy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'o']);
+    ///
+    /// let short = trie.to_filtered(|seq| seq.len() < 3);
+    /// assert_eq!(short.sequences(), vec![vec!['c', 'o']]);
+    /// assert_eq!(trie.sequence_count(), 2);
+    /// ```
+    pub fn to_filtered<F>(&self, f: F) -> Self
+    where
+        F: Fn(&[T]) -> bool,
+    {
+        let mut filtered = Trie { normalizer: self.normalizer.clone(), ..Trie::default() };
+        self.for_each_sequence(|seq| {
+            if f(seq) {
+                filtered.insert(seq);
+            }
+        });
+        filtered
+    }
+
+    /// Builds a new trie from every stored sequence with `f` applied to each
+    /// element, in traversal order.
+    ///
+    /// If `f` maps two elements to the same value sequences can merge, the new
+    /// trie then holds fewer sequences. The normalizer is not carried over.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['C', 'a', 't']);
+    ///
+    /// let lower = trie.map(|c| c.to_ascii_lowercase());
+    /// assert!(lower.contains(&['c', 'a', 't']));
+    /// assert_eq!(lower.sequence_count(), 1);
+    /// ```
+    pub fn map<U, F>(&self, f: F) -> Trie<U, S>
+    where
+        U: Eq + Hash + Clone + Debug,
+        F: Fn(&T) -> U,
+    {
+        let mut mapped = Trie::default();
+        let mut buf = Vec::new();
+        self.for_each_sequence(|seq| {
+            buf.clear();
+            buf.extend(seq.iter().map(&f));
+            mapped.insert(&buf);
+        });
+        mapped
+    }
+
+    /// Calls `f` with every stored sequence in traversal order.
+    fn for_each_sequence<F: FnMut(&[T])>(&self, mut f: F) {
+        let mut path = Vec::new();
+        for node in self.starts.iter().filter_map(|key| self.children.get(key)) {
+            self.walk_sequences(node, &mut path, &mut |seq| {
+                f(seq);
+                true
+            });
         }
-        trie
     }
 
-    #[test]
-    fn insert_find() {
-        let cmp_found = vec![ vec!['c', 'a', 't'], vec!['c', 'a', 'r', 't'], vec!['c', 'o', 'w']];
-        let mut trie = Trie::new();
-        trie.insert(&['c', 'a', 't']);
-        trie.insert(&['c', 'a', 'r', 't']);
-        trie.insert(&['c', 'o', 'w']);
-        let found = trie.search(&['c']);
-        println!("{:?}", found);
-        for (expected, found) in cmp_found.iter().zip(found.as_collected()) {
-            assert_eq!(&expected[..], found)
+    /// Calls `f` with every stored sequence in traversal order, stopping at the
+    /// first error.
+    fn try_for_each_sequence<E, F>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&[T]) -> Result<(), E>,
+    {
+        let mut path = Vec::new();
+        let mut res = Ok(());
+        for node in self.starts.iter().filter_map(|key| self.children.get(key)) {
+            let keep_going = self.walk_sequences(node, &mut path, &mut |seq| {
+                res = f(seq);
+                res.is_ok()
+            });
+            if !keep_going {
+                break;
+            }
         }
+        res
     }
 
-    #[test]
-    fn trie_iter() {
-        let ord = &['c', 'a', 't', 'o', 'w'];
+    /// Depth first walk of the subtree at `node` calling `f` with each terminal
+    /// sequence, `path` holds the elements above `node`. Stops as soon as `f`
+    /// returns `false`, which is passed back up.
+    fn walk_sequences<F>(&self, node: &Node<T>, path: &mut Vec<T>, f: &mut F) -> bool
+    where
+        F: FnMut(&[T]) -> bool,
+    {
+        path.push(node.to_value());
+        let mut keep_going = !node.is_terminal() || f(path);
+        for child in node.children(&self.children) {
+            if !keep_going {
+                break;
+            }
+            keep_going = self.walk_sequences(child, path, f);
+        }
+        path.pop();
+        keep_going
+    }
 
-        let mut trie = Trie::new();
-        trie.insert(&['c', 'a', 't']);
-        trie.insert(&['c', 'o', 'w']);
+    /// Returns up to `limit` stored sequences sharing the longest possible
+    /// prefix with `query`.
+    ///
+    /// `query` is followed as far as the trie allows and completions are taken
+    /// from the deepest node reached, falling back a level at a time when that
+    /// subtree holds no sequences. Empty when not even the first element of
+    /// `query` starts a sequence.
+    pub fn closest_by_prefix(&self, query: &[T], limit: usize) -> Vec<Vec<T>> {
+        let query = &*self.normalize(query);
+        let keys = path_keys(query)
+            .take_while(|key| self.children.contains_key(key))
+            .collect::<Vec<_>>();
+        let depth = keys.len();
 
-        for (i, n) in trie.iter().enumerate() {
-            assert_eq!(ord[i], n.val)
+        let mut found = Vec::new();
+        for d in (1..=depth).rev() {
+            if limit == 0 {
+                break;
+            }
+            let node = &self.children[&keys[d - 1]];
+            let mut path = query[..d - 1].to_vec();
+            self.walk_sequences(node, &mut path, &mut |seq| {
+                found.push(seq.to_vec());
+                found.len() < limit
+            });
+            if !found.is_empty() {
+                break;
+            }
         }
+        found
     }
 
-    #[test]
+    /// Returns the `n`th stored sequence in the order of `sequences`.
+    ///
+    /// Whole subtrees are skipped using their cached terminal count, so this
+    /// descends a single path looking at the siblings along the way.
+    pub fn nth_sequence(&self, mut n: usize) -> Option<Vec<T>> {
+        let mut path = Vec::new();
+        let mut level = self.starts.iter()
+            .filter_map(|key| self.children.get(key))
+            .collect::<Vec<_>>();
+        'descend: loop {
+            for node in level {
+                let count = self.count_terminals(node);
+                if n >= count {
+                    n -= count;
+                    continue;
+                }
+                path.push(node.to_value());
+                if node.is_terminal() {
+                    if n == 0 {
+                        return Some(path);
+                    }
+                    n -= 1;
+                }
+                level = node.children(&self.children);
+                continue 'descend;
+            }
+            return None;
+        }
+    }
+
+    /// Returns a uniformly random stored sequence, `None` if the trie is empty.
+    #[cfg(feature = "rand")]
+    pub fn random_sequence<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Option<Vec<T>> {
+        let count = self.sequence_count();
+        if count == 0 {
+            return None;
+        }
+        self.nth_sequence(rng.gen_range(0..count))
+    }
+
+    /// Returns the shortest prefix of the stored sequence `seq` that no other
+    /// stored sequence shares, `None` if `seq` is not stored.
+    ///
+    /// When `seq` is itself a prefix of other stored sequences ("car" with
+    /// "cart" stored) no proper prefix is unique, the full sequence is returned
+    /// since reaching its end is what tells it apart.
+    pub fn shortest_unique_prefix(&self, seq: &[T]) -> Option<Vec<T>> {
+        let seq = &*self.normalize(seq);
+        if !self.node(seq).is_some_and(|n| n.is_terminal()) {
+            return None;
+        }
+        for (i, key) in path_keys(seq).enumerate() {
+            let node = self.children.get(&key)?;
+            if self.count_terminals(node) == 1 {
+                return Some(seq[..=i].to_vec());
+            }
+        }
+        Some(seq.to_vec())
+    }
+
+    /// Number of terminal nodes in the subtree rooted at `node`.
+    fn count_terminals(&self, node: &Node<T>) -> usize {
+        node.descendant_terminals
+    }
+
+    /// `true` if `node` or any of its descendants is terminal.
+    fn has_terminal(&self, node: &Node<T>) -> bool {
+        node.descendant_terminals > 0
+    }
+
+    /// Returns all of the found sequences, walking
+    /// each branch depth first.
+    ///
+    /// When `seq_key` is itself stored it is the first result, before any of
+    /// its extensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'o', 'w']);
+    /// 
+    /// let found = trie.search(&['c']);
+    /// 
+    /// assert_eq!(
+    ///     found.as_collected().as_slice(),
+    ///     &[ ['c', 'a', 't'], ['c', 'o', 'w'] ]
+    /// );
+    /// ```
+    pub fn search(&self, seq_key: &[T]) -> Found<T> {
+        self.children.search(&self.normalize(seq_key))
+    }
+
+    /// Like `search` but the results are written to `out`, replacing what was
+    /// there. The sequences already in `out` are overwritten in place so a
+    /// buffer used for every search stops allocating once it's big enough.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["car", "cart", "cow"][..]);
+    /// let mut out = Vec::new();
+    ///
+    /// trie.search_into(&['c', 'a'], &mut out);
+    /// assert_eq!(out, vec![vec!['c', 'a', 'r'], vec!['c', 'a', 'r', 't']]);
+    /// trie.search_into(&['c', 'o'], &mut out);
+    /// assert_eq!(out, vec![vec!['c', 'o', 'w']]);
+    /// ```
+    pub fn search_into(&self, prefix: &[T], out: &mut Vec<Vec<T>>) {
+        let prefix = &*self.normalize(prefix);
+        let mut used = 0;
+        if let Some(node) = self.node(prefix) {
+            let mut path = prefix.to_vec();
+            Trie::_search_into(&self.children, node, &mut path, out, &mut used);
+        }
+        out.truncate(used);
+    }
+
+    /// `_search` writing over the first `used` sequences of `out`.
+    fn _search_into(
+        map: &HashMap<u64, Node<T>, S>,
+        node: &Node<T>,
+        path: &mut Vec<T>,
+        out: &mut Vec<Vec<T>>,
+        used: &mut usize,
+    ) {
+        if node.is_terminal() {
+            match out.get_mut(*used) {
+                Some(seq) => {
+                    seq.clear();
+                    seq.extend_from_slice(path);
+                }
+                None => out.push(path.clone()),
+            }
+            *used += 1;
+        }
+        for key in node.children.iter() {
+            let n = &map[key];
+            path.push(n.to_value());
+            Trie::_search_into(map, n, path, out, used);
+            path.pop();
+        }
+    }
+
+    /// Like `search` but only collects sequences at most `max_extra` elements
+    /// longer than `prefix`, nothing deeper is visited. 0 gives just `prefix`
+    /// if it is stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 'r']);
+    /// trie.insert(&['c', 'a', 'r', 'r', 'o', 't']);
+    ///
+    /// assert_eq!(trie.search_within(&['c', 'a'], 2).as_collected(), vec![&['c', 'a', 'r']]);
+    /// ```
+    pub fn search_within(&self, prefix: &[T], max_extra: usize) -> Found<T> {
+        let prefix = &*self.normalize(prefix);
+
+        let mut res = Found::new();
+        res.extend(prefix.iter().cloned());
+        if let Some(node) = self.node(prefix) {
+            self.children.search_below(node, &mut res, max_extra)
+        }
+        res
+    }
+
+    /// Returns what follows `prefix` in each stored sequence that extends it,
+    /// in the same order as `search`, without the prefix itself. A stored
+    /// `prefix` has nothing left to complete so it adds no empty remainder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'a', 'r', 't']);
+    /// trie.insert(&['c', 'o', 'w']);
+    ///
+    /// assert_eq!(trie.complete(&['c', 'a']), vec![vec!['t'], vec!['r', 't']]);
+    /// ```
+    pub fn complete(&self, prefix: &[T]) -> Vec<Vec<T>> {
+        let prefix = &*self.normalize(prefix);
+        let mut res = Found::new();
+        if let Some(node) = self.node(prefix) {
+            for n in node.children(&self.children) {
+                res.push_val(n.to_value());
+                self.children.search_below(n, &mut res, usize::MAX);
+                res.branch_split();
+            }
+        }
+        res.into_response_vec()
+    }
+
+    /// The stored sequence beginning with `prefix` when it is the only one,
+    /// `None` when there are none or more than one. `prefix` itself counts, a
+    /// stored `prefix` with extensions is ambiguous.
+    ///
+    /// The cached terminal counts answer whether there is exactly one, then
+    /// only the single path down to it is walked, once to size the result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["car", "cart", "cow"][..]);
+    ///
+    /// assert_eq!(trie.single_completion(&['c', 'o']), Some(vec!['c', 'o', 'w']));
+    /// assert_eq!(trie.single_completion(&['c', 'a']), None);
+    /// assert_eq!(trie.single_completion(&['d']), None);
+    /// ```
+    pub fn single_completion(&self, prefix: &[T]) -> Option<Vec<T>> {
+        let prefix = &*self.normalize(prefix);
+        let top = if prefix.is_empty() {
+            if self.seq_len != 1 {
+                return None;
+            }
+            self.starts.iter().filter_map(|key| self.children.get(key)).find(|n| self.has_terminal(n))?
+        } else {
+            self.node(prefix).filter(|n| self.count_terminals(n) == 1)?
+        };
+        // the only child with the terminal below it
+        let next = |node: &Node<T>| node.children.iter()
+            .filter_map(|key| self.children.get(key))
+            .find(|n| self.has_terminal(n))
+            .expect("a node counting a terminal below it has a child leading to it");
+
+        // walk down once for the length so the sequence is allocated once
+        let mut extra = prefix.is_empty() as usize;
+        let mut node = top;
+        while !node.is_terminal() {
+            node = next(node);
+            extra += 1;
+        }
+
+        let mut seq = Vec::with_capacity(prefix.len() + extra);
+        seq.extend_from_slice(prefix);
+        if prefix.is_empty() {
+            seq.push(top.to_value());
+        }
+        let mut node = top;
+        while !node.is_terminal() {
+            node = next(node);
+            seq.push(node.to_value());
+        }
+        Some(seq)
+    }
+
+    /// The first stored sequence beginning with `start` in traversal order,
+    /// the first result of `search(&[start])` without collecting the rest. See
+    /// `first_under` for the smallest when `T: Ord`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["mole", "mat", "mast", "cat"][..]);
+    ///
+    /// assert_eq!(trie.first_inserted_under(&'m'), Some(vec!['m', 'o', 'l', 'e']));
+    /// assert_eq!(trie.last_inserted_under(&'m'), Some(vec!['m', 'a', 's', 't']));
+    /// ```
+    pub fn first_inserted_under(&self, start: &T) -> Option<Vec<T>> {
+        let mut node = self.node(&self.normalize(std::slice::from_ref(start)))?;
+        let mut seq = vec![node.to_value()];
+        while !node.is_terminal() {
+            node = self.children.get(node.children.first()?)?;
+            seq.push(node.to_value());
+        }
+        Some(seq)
+    }
+
+    /// The last stored sequence beginning with `start` in traversal order, a
+    /// leaf reached by following the last child of each node.
+    pub fn last_inserted_under(&self, start: &T) -> Option<Vec<T>> {
+        let mut node = self.node(&self.normalize(std::slice::from_ref(start)))?;
+        let mut seq = vec![node.to_value()];
+        while let Some(key) = node.children.last() {
+            node = self.children.get(key)?;
+            seq.push(node.to_value());
+        }
+        Some(seq).filter(|_| node.is_terminal())
+    }
+
+    /// Like `search` but each result borrows the elements stored in the trie
+    /// instead of cloning them, clone only the results you keep.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&["let".to_string(), "x".to_string()]);
+    /// trie.insert(&["let".to_string(), "mut".to_string()]);
+    ///
+    /// let found = trie.search_refs(&["let".to_string()]);
+    /// assert_eq!(found.len(), 2);
+    /// assert_eq!(found[1], vec!["let", "mut"]);
+    /// ```
+    pub fn search_refs(&self, prefix: &[T]) -> Vec<Vec<&T>> {
+        let prefix = &*self.normalize(prefix);
+        let mut found = Vec::new();
+        if prefix.is_empty() {
+            return found;
+        }
+        // the prefix's own nodes so every element borrows from the trie
+        let mut path = Vec::with_capacity(prefix.len());
+        let mut last = None;
+        for key in path_keys(prefix) {
+            match self.children.get(&key) {
+                Some(node) => path.push(node.as_value()),
+                None => return found,
+            }
+            last = Some(key);
+        }
+        let depth = prefix.len();
+        let mut stack = vec![(depth, &self.children[&last.expect("prefix isn't empty")])];
+        while let Some((depth, node)) = stack.pop() {
+            path.truncate(depth - 1);
+            path.push(node.as_value());
+            if node.is_terminal() {
+                found.push(path.clone());
+            }
+            stack.extend(node.children.iter().rev().map(|key| (depth + 1, &self.children[key])));
+        }
+        found
+    }
+
+    /// Depth first iteration over every node, starts in insertion order and a
+    /// node before its descendants.
+    pub fn iter(&self) -> TrieIter<'_, T, S> {
+        TrieIter { nodes: DepthFirst::new(self), remaining: self.len }
+    }
+
+    /// Every stored sequence with the value of its last node, in traversal
+    /// order, without copying the sequences like `sequences` does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["cat", "ca", "cow"][..]);
+    ///
+    /// let mut terminals = trie.terminals();
+    /// let mut ends = String::new();
+    /// while let Some((path, last)) = terminals.next_terminal() {
+    ///     assert_eq!(path.last(), Some(last));
+    ///     ends.push(*last);
+    /// }
+    /// assert_eq!(ends, "atw");
+    /// ```
+    pub fn terminals(&self) -> Terminals<'_, T, S> {
+        Terminals::new(self)
+    }
+
+    /// The first element of every stored sequence, in the order they were
+    /// first inserted.
+    pub fn starts(&self) -> impl Iterator<Item = &T> + '_ {
+        self.starts.iter()
+            .filter_map(move |key| self.children.get(key))
+            .map(|node| node.as_value())
+    }
+
+    /// The elements that can directly follow `prefix` and whether each ends a
+    /// stored sequence, `None` if `prefix` is not in the trie. The empty prefix
+    /// gives the starts.
+    pub fn children_of(&self, prefix: &[T]) -> Option<Vec<(&T, bool)>> {
+        let prefix = &*self.normalize(prefix);
+        let children = if prefix.is_empty() {
+            self.starts.iter().filter_map(|key| self.children.get(key)).collect()
+        } else {
+            self.node(prefix)?.children(&self.children)
+        };
+        Some(children.into_iter().map(|n| (n.as_value(), n.is_terminal())).collect())
+    }
+
+    /// Each element that can directly follow `prefix` with the number of
+    /// stored sequences below it, most first and ties in insertion order. A
+    /// stored `prefix` isn't counted under any of them. The empty prefix counts
+    /// under each start and a missing prefix has none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'o', 'w']);
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'a', 'r']);
+    ///
+    /// assert_eq!(trie.completions_by_next(&['c']), vec![('a', 2), ('o', 1)]);
+    /// ```
+    pub fn completions_by_next(&self, prefix: &[T]) -> Vec<(T, usize)> {
+        let prefix = &*self.normalize(prefix);
+        let children = if prefix.is_empty() {
+            self.starts.iter().filter_map(|key| self.children.get(key)).collect()
+        } else {
+            match self.node(prefix) {
+                Some(node) => node.children(&self.children),
+                None => return Vec::new(),
+            }
+        };
+        let mut counts = children.into_iter()
+            .map(|n| (n.to_value(), self.count_terminals(n)))
+            .collect::<Vec<_>>();
+        // stable so equal counts stay in insertion order
+        counts.sort_by_key(|count| std::cmp::Reverse(count.1));
+        counts
+    }
+
+    /// Number of distinct first elements.
+    pub fn num_starts(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Each first element with the number of stored sequences beginning with
+    /// it, in the order of the starts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["cat", "cow", "dog"][..]);
+    ///
+    /// assert_eq!(trie.counts_by_start(), vec![('c', 2), ('d', 1)]);
+    /// ```
+    pub fn counts_by_start(&self) -> Vec<(T, usize)> {
+        self.starts.iter()
+            .map(|key| &self.children[key])
+            .map(|n| (n.to_value(), self.count_terminals(n)))
+            .collect()
+    }
+
+    /// Depth first iteration yielding `(depth, value, is_terminal)` for every
+    /// node, where depth is the length of the node's path. A node always comes
+    /// before its descendants.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'o']);
+    ///
+    /// let depths = trie.iter_with_depth().map(|(d, _, _)| d).collect::<Vec<_>>();
+    /// assert_eq!(depths, &[1, 2, 3, 2]);
+    /// ```
+    pub fn iter_with_depth(&self) -> impl Iterator<Item = (usize, &T, bool)> + '_ {
+        DepthFirst::new(self).map(|(depth, node)| (depth, node.as_value(), node.is_terminal()))
+    }
+
+    /// Depth first walk of everything below `prefix` yielding each node's full
+    /// path, its value and whether it is terminal, `None` if `prefix` is not in
+    /// the trie. The node at `prefix` itself is not yielded and the empty prefix
+    /// walks the whole trie.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'o']);
+    ///
+    /// let walk = trie.walk_subtree(&['c']).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(walk, vec![
+    ///     (vec!['c', 'a'], &'a', false),
+    ///     (vec!['c', 'a', 't'], &'t', true),
+    ///     (vec!['c', 'o'], &'o', true),
+    /// ]);
+    /// ```
+    pub fn walk_subtree(&self, prefix: &[T]) -> Option<SubtreeIter<'_, T, S>> {
+        let prefix = self.normalize(prefix);
+        let nodes = if prefix.is_empty() {
+            self.starts.iter().filter_map(|key| self.children.get(key)).collect()
+        } else {
+            self.node(&prefix)?.children(&self.children)
+        };
+        Some(SubtreeIter::new(&self.children, nodes, prefix.into_owned()))
+    }
+
+    /// Depth first walk calling `f` with each node's full path (ending in the
+    /// node's own value), its value and whether it is terminal.
+    ///
+    /// Returning `ControlFlow::Continue(VisitAction::SkipSubtree)` skips the
+    /// node's descendants and `ControlFlow::Break(())` ends the walk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ops::ControlFlow;
+    /// use ecs_trie::{Trie, VisitAction};
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'o', 'w']);
+    ///
+    /// let mut first = None;
+    /// trie.visit(|path, _, terminal| {
+    ///     if terminal {
+    ///         first = Some(path.to_vec());
+    ///         return ControlFlow::Break(());
+    ///     }
+    ///     ControlFlow::Continue(VisitAction::Descend)
+    /// });
+    /// assert_eq!(first, Some(vec!['c', 'a', 't']));
+    /// ```
+    pub fn visit<F>(&self, mut f: F)
+    where
+        F: FnMut(&[T], &T, bool) -> ControlFlow<(), VisitAction>,
+    {
+        let mut path = Vec::new();
+        let mut stack = self.starts.iter()
+            .rev()
+            .filter_map(|key| self.children.get(key))
+            .map(|node| (1, node))
+            .collect::<Vec<_>>();
+        while let Some((depth, node)) = stack.pop() {
+            path.truncate(depth - 1);
+            path.push(node.to_value());
+            match f(&path, node.as_value(), node.is_terminal()) {
+                ControlFlow::Break(()) => return,
+                ControlFlow::Continue(VisitAction::SkipSubtree) => {}
+                ControlFlow::Continue(VisitAction::Descend) => {
+                    let children = node.children(&self.children);
+                    stack.extend(children.into_iter().rev().map(|n| (depth + 1, n)));
+                }
+            }
+        }
+    }
+
+    /// Clears the `Trie`, note this leaves the previously
+    /// allocated capacity.
+    pub fn clear(&mut self) {
+        let cleared = self.observer.as_ref().map(|_| self.sequences());
+        self.clear_unobserved();
+        for seq in cleared.into_iter().flatten() {
+            self.notify_remove(&seq, true);
+        }
+    }
+
+    fn clear_unobserved(&mut self) {
+        self.len = 0;
+        self.seq_len = 0;
+        self.children.clear();
+        self.starts.clear();
+        if let Some(lru) = self.lru.as_mut() {
+            lru.clear();
+        }
+        if let Some(suffixes) = self.suffixes.as_mut() {
+            suffixes.clear();
+        }
+        if let Some(bloom) = self.bloom.as_mut() {
+            bloom.clear();
+        }
+    }
+
+    /// Clears the `Trie` and releases its allocated capacity, use `clear`
+    /// instead when the trie is about to be refilled.
+    pub fn clear_shrink(&mut self) {
+        self.clear();
+        self.children.shrink_to_fit();
+        self.starts.shrink_to_fit();
+        if let Some(suffixes) = self.suffixes.as_mut() {
+            suffixes.clear_shrink();
+        }
+    }
+
+    /// Shrinks every node's child list and the starts to fit, less than
+    /// `compact` as the map itself keeps its capacity.
+    pub fn shrink_children(&mut self) {
+        for node in self.children.values_mut() {
+            node.children.shrink_to_fit();
+        }
+        self.starts.shrink_to_fit();
+        if let Some(suffixes) = self.suffixes.as_mut() {
+            suffixes.shrink_children();
+        }
+    }
+
+    /// Rebuilds the storage to fit the trie as it is now, every stored sequence
+    /// and weight stays the same. Returns how many bytes `memory_usage` went
+    /// down by.
+    ///
+    /// Removals leave the map and each node's child list at their largest
+    /// size, after removing most of a trie this gives the memory back and
+    /// makes walking it faster.
+    pub fn compact(&mut self) -> usize {
+        let before = self.memory_usage();
+        let mut children = HashMap::with_capacity_and_hasher(self.len, S::default());
+        for (key, mut node) in self.children.drain() {
+            node.children.shrink_to_fit();
+            children.insert(key, node);
+        }
+        self.children = children;
+        self.starts.shrink_to_fit();
+        if let Some(suffixes) = self.suffixes.as_mut() {
+            suffixes.compact();
+        }
+        self.rebuild_bloom();
+        debug_assert_invariants!(self);
+        before.saturating_sub(self.memory_usage())
+    }
+
+    /// An estimate of the bytes allocated by the trie, counting the capacity of
+    /// the node map, the starts and every node's child list. Heap memory owned
+    /// by the elements themselves, a `String`'s buffer, is not counted.
+    pub fn memory_usage(&self) -> usize {
+        use std::mem::size_of;
+
+        let map = self.children.capacity() * (size_of::<u64>() + size_of::<Node<T>>());
+        let child_lists = self.children.values()
+            .map(|node| node.children.capacity() * size_of::<u64>())
+            .sum::<usize>();
+        let suffixes = self.suffixes.as_ref().map_or(0, |trie| trie.memory_usage());
+        let bloom = self.bloom.as_ref().map_or(0, |bloom| bloom.memory_usage());
+        size_of::<Self>() + map + child_lists + self.starts.capacity() * size_of::<u64>() + suffixes + bloom
+    }
+
+    /// Decides how removing the sequence whose path is `keys` changes the trie,
+    /// before anything is mutated.
+    fn branch_state(&self, keys: &[u64]) -> Remove {
+        // every node on the path, looked up once
+        let path = match keys.iter().map(|key| self.children.get(key)).collect::<Option<Vec<_>>>() {
+            Some(path) => path,
+            None => return Remove::NoMatch,
+        };
+        let last = match path.last() {
+            Some(node) if node.is_terminal() => node,
+            _ => return Remove::NoMatch,
+        };
+        if path.len() == 1 {
+            return Remove::Starts;
+        }
+        if last.child_len() > 0 {
+            return Remove::Stemish;
+        }
+        // the deepest node on the path that has to stay, it ends or leads to
+        // another sequence
+        let safe = path[..path.len() - 1].iter()
+            .rposition(|node| node.is_terminal() || node.child_len() > 1);
+        match safe {
+            Some(idx) => Remove::Terminal(idx),
+            // it is stored, so when it is the only sequence nothing else is
+            // left once it goes
+            None if self.seq_len == 1 => Remove::Rest,
+            None => Remove::Childless,
+        }
+    }
+
+    /// Removes a single element sequence.
+    fn _remove_start(&mut self, key: u64) {
+        let node = self.children.get_mut(&key).expect("branch_state checked the start");
+        if node.child_len() > 0 {
+            node.terminal = false;
+            node.weight = 0;
+        } else {
+            self.children.remove(&key);
+            self.len -= 1;
+            self.starts.retain(|k| *k != key);
+        }
+    }
+
+    /// Removes and returns a stored sequence, the first in traversal order, or
+    /// `None` if the trie is empty. Popping until `None` empties the trie.
+    pub fn pop_sequence(&mut self) -> Option<Vec<T>> {
+        let seq = self.nth_sequence(0)?;
+        let removed = self.remove(&seq);
+        debug_assert!(removed, "the first sequence is stored");
+        Some(seq)
+    }
+
+    /// Returns how removing `seq` would change the trie without removing it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::{RemoveKind, Trie};
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'a']);
+    ///
+    /// assert_eq!(trie.classify_removal(&['c', 'a']), RemoveKind::Stemish);
+    /// assert_eq!(trie.classify_removal(&['c', 'a', 't']), RemoveKind::Terminal);
+    /// assert_eq!(trie.classify_removal(&['c']), RemoveKind::NoMatch);
+    /// ```
+    pub fn classify_removal(&self, seq: &[T]) -> RemoveKind {
+        let seq = &*self.normalize(seq);
+        let keys = path_keys(seq).collect::<Vec<_>>();
+        self.branch_state(&keys).kind()
+    }
+
+    /// Removes `seq` returning `true` if it was stored.
+    pub fn remove(&mut self, seq: &[T]) -> bool {
+        self.remove_explain(seq).0
+    }
+
+    /// Removes `seq` like `remove` also returning which way the trie was
+    /// changed, useful when tracking down a removal that misbehaves.
+    pub fn remove_explain(&mut self, seq: &[T]) -> (bool, RemoveKind) {
+        let res = self.remove_unobserved(seq);
+        self.notify_remove(seq, res.0);
+        res
+    }
+
+    /// `remove_explain` without telling the observer.
+    pub(crate) fn remove_unobserved(&mut self, seq: &[T]) -> (bool, RemoveKind) {
+        let seq = self.normalize(seq);
+        self.remove_normalized(&seq)
+    }
+
+    /// Removes the already normalized `seq`.
+    pub(crate) fn remove_normalized(&mut self, seq: &[T]) -> (bool, RemoveKind) {
+        let keys = path_keys(seq).collect::<Vec<_>>();
+
+        let state = self.branch_state(&keys);
+        let nodes = self.len;
+        if state != Remove::NoMatch {
+            self.seq_len -= 1;
+            if let Some(lru) = self.lru.as_mut() {
+                lru.forget(keys[keys.len() - 1]);
+            }
+            self.unindex_suffix(seq);
+        }
+        if !matches!(state, Remove::NoMatch | Remove::Rest) {
+            // one fewer terminal below every node on the path, including any
+            // that are about to be removed
+            for key in keys.iter() {
+                let node = self.children.get_mut(key).expect("branch_state checked the path");
+                node.descendant_terminals -= 1;
+            }
+        }
+        match state {
+            Remove::NoMatch => return (false, RemoveKind::NoMatch),
+            Remove::Rest => self.clear_unobserved(),
+            Remove::Starts => self._remove_start(keys[0]),
+            Remove::Stemish => {
+                let last = keys[keys.len() - 1];
+                let node = self.children.get_mut(&last).expect("branch_state checked the path");
+                node.terminal = false;
+                node.weight = 0;
+            }
+            Remove::Terminal(idx) => {
+                for key in keys[idx + 1..].iter() {
+                    self.children.remove(key);
+                    self.len -= 1;
+                }
+                self.children.get_mut(&keys[idx])
+                    .expect("branch_state checked the path")
+                    .remove_child(&keys[idx + 1]);
+            }
+            Remove::Childless => {
+                for key in keys.iter() {
+                    self.children.remove(key);
+                    self.len -= 1;
+                }
+                self.starts.retain(|k| *k != keys[0]);
+            }
+        }
+        self.bloom_forget(nodes - self.len);
+        debug_assert_invariants!(self, &keys);
+        (true, state.kind())
+    }
+}
+
+/// The ways removing a sequence can change the trie, see `Trie::branch_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Remove {
+    /// The sequence isn't stored.
+    NoMatch,
+    /// The sequence is the only one stored, the trie is cleared.
+    Rest,
+    /// A single element sequence, its start node goes unless it has children.
+    Starts,
+    /// Other sequences extend it so only the terminal flag is cleared.
+    Stemish,
+    /// Nodes after this index are only on the sequence's path and are removed.
+    Terminal(usize),
+    /// Nothing on the path is shared, every node and the start are removed.
+    Childless,
+}
+
+/// How a removal changes the trie, returned by `Trie::classify_removal` and
+/// `Trie::remove_explain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RemoveKind {
+    /// The sequence isn't stored, nothing changes.
+    NoMatch,
+    /// The sequence is the only one stored, the trie is cleared.
+    Rest,
+    /// A single element sequence, its start node goes unless it has children.
+    Starts,
+    /// Other sequences extend it so only the terminal flag is cleared.
+    Stemish,
+    /// The end of the path that no other sequence uses is removed.
+    Terminal,
+    /// Nothing on the path is shared, every node and the start are removed.
+    Childless,
+}
+
+impl Remove {
+    fn kind(self) -> RemoveKind {
+        match self {
+            Remove::NoMatch => RemoveKind::NoMatch,
+            Remove::Rest => RemoveKind::Rest,
+            Remove::Starts => RemoveKind::Starts,
+            Remove::Stemish => RemoveKind::Stemish,
+            Remove::Terminal(_) => RemoveKind::Terminal,
+            Remove::Childless => RemoveKind::Childless,
+        }
+    }
+}
+
+/// Returned from a `Trie::visit` callback to choose whether the walk continues
+/// into the current node's children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisitAction {
+    Descend,
+    SkipSubtree,
+}
+
+#[derive(Debug, Clone)]
+pub struct Found<T> {
+    roll_back: Vec<usize>,
+    temp: Vec<T>,
+    collected: Vec<Vec<T>>,
+    /// The first collected sequence is the searched prefix.
+    exact: bool,
+}
+impl<T: Clone + PartialEq> Found<T> {
+    fn new() -> Self {
+        Self {
+            roll_back: vec![],
+            temp: vec![],
+            collected: vec![],
+            exact: false,
+        }
+    }
+
+    /// Takes the found sequences, in traversal order, for handing on without
+    /// copying them.
+    pub fn into_response_vec(self) -> Vec<Vec<T>> {
+        self.collected
+    }
+
+    /// Moves the found sequences into `out` after clearing it, keeping the
+    /// capacity `out` already has. See `Trie::search_into` to reuse the
+    /// sequences' own buffers too.
+    pub fn collect_into(self, out: &mut Vec<Vec<T>>) {
+        out.clear();
+        out.extend(self.collected);
+    }
+
+    /// The found sequences in traversal order, see `as_collected_ordered`.
+    pub fn as_collected(&self) -> Vec<&[T]> {
+        self.collected
+            .iter()
+            .map(|seq| seq.as_slice())
+            .collect::<Vec<_>>()
+    }
+
+    /// The found sequences in the order they were visited, which is
+    /// guaranteed: a stored sequence before its extensions and sibling
+    /// branches in the order they were first inserted. Removing a sequence
+    /// doesn't reorder the others, a branch that is removed and inserted again
+    /// moves after its siblings. Reverse it for the newest branches first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'o', 'w']);
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'u', 'b']);
+    ///
+    /// let found = trie.search(&['c']);
+    /// let newest = found.as_collected_ordered().rev().next();
+    /// assert_eq!(newest, Some(&['c', 'u', 'b'][..]));
+    /// ```
+    pub fn as_collected_ordered(&self) -> impl DoubleEndedIterator<Item = &[T]> + ExactSizeIterator {
+        self.collected.iter().map(|seq| seq.as_slice())
+    }
+
+    /// The searched prefix when it is itself stored, it is then always the
+    /// first found sequence. `None` when the prefix is only a path to longer
+    /// sequences, wasn't found, or the results didn't come from a prefix
+    /// search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["car", "cart", "cow"][..]);
+    ///
+    /// let found = trie.search(&['c', 'a', 'r']);
+    /// assert_eq!(found.exact(), Some(&['c', 'a', 'r'][..]));
+    /// assert_eq!(found.extensions().collect::<Vec<_>>(), vec![&['c', 'a', 'r', 't'][..]]);
+    /// assert_eq!(trie.search(&['c', 'a']).exact(), None);
+    /// ```
+    pub fn exact(&self) -> Option<&[T]> {
+        self.collected.first().filter(|_| self.exact).map(|seq| seq.as_slice())
+    }
+
+    /// The found sequences after `exact`, every one longer than the searched
+    /// prefix, in traversal order.
+    pub fn extensions(&self) -> impl Iterator<Item = &[T]> {
+        let skip = self.exact as usize;
+        self.collected[skip..].iter().map(|seq| seq.as_slice())
+    }
+
+    /// Returns `true` if `seq` is one of the found sequences.
+    pub fn contains_seq(&self, seq: &[T]) -> bool {
+        self.collected.iter().any(|found| found.as_slice() == seq)
+    }
+
+    /// Length of the longest prefix shared by every found sequence, 0 if
+    /// nothing was found.
+    pub fn common_prefix_len(&self) -> usize {
+        let (first, rest) = match self.collected.split_first() {
+            Some(split) => split,
+            None => return 0,
+        };
+        rest.iter().fold(first.len(), |len, seq| {
+            first[..len].iter().zip(seq.iter()).take_while(|(a, b)| a == b).count()
+        })
+    }
+
+    /// Groups the found sequences by their element at `depth`, groups in the
+    /// order they are first seen. Sequences too short to have an element at
+    /// `depth` are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["car", "cart", "cow"][..]);
+    ///
+    /// let found = trie.search_str("c");
+    /// let groups = found.group_by_next(1);
+    /// assert_eq!(groups[0].0, 'a');
+    /// assert_eq!(groups[0].1.len(), 2);
+    /// assert_eq!(groups[1], ('o', vec![&['c', 'o', 'w'][..]]));
+    /// ```
+    pub fn group_by_next(&self, depth: usize) -> Vec<(T, Vec<&[T]>)> {
+        let mut groups: Vec<(T, Vec<&[T]>)> = Vec::new();
+        for seq in self.collected.iter() {
+            let next = match seq.get(depth) {
+                Some(next) => next,
+                None => continue,
+            };
+            match groups.iter_mut().find(|(t, _)| t == next) {
+                Some((_, group)) => group.push(seq),
+                None => groups.push((next.clone(), vec![seq])),
+            }
+        }
+        groups
+    }
+
+    fn extend<I: IntoIterator<Item = T>>(&mut self, i: I) {
+        self.temp.extend(i)
+    }
+
+    /// Descends one level, `branch_split` rolls `temp` back to before `t`.
+    fn push_val(&mut self, t: T) {
+        self.roll_back.push(self.temp.len());
+        self.temp.push(t);
+    }
+
+    /// Collects the current path, it is a complete sequence.
+    fn branch_end(&mut self) {
+        // each terminal is visited once so this can't repeat, but a repeat
+        // would only ever be the sequence just collected
+        if self.collected.last() != Some(&self.temp) {
+            // nothing pushed yet, the searched node is stored itself
+            self.exact |= self.roll_back.is_empty() && self.collected.is_empty() && !self.temp.is_empty();
+            self.collected.push(self.temp.clone());
+        }
+    }
+
+    /// Rolls `temp` back to the depth before the last `push_val`.
+    fn branch_split(&mut self) {
+        if let Some(len) = self.roll_back.pop() {
+            self.temp.truncate(len);
+        }
+    }
+}
+impl<T: Clone + Eq + Hash> Found<T> {
+    /// Removes repeated sequences keeping the first of each. `search` never
+    /// collects a sequence twice, this is for results that were combined.
+    pub fn dedup(&mut self) {
+        let mut seen = std::collections::HashSet::with_capacity(self.collected.len());
+        let collected = std::mem::take(&mut self.collected);
+        self.collected = collected.into_iter().filter(|seq| seen.insert(seq.clone())).collect();
+    }
+}
+
+/// Results are equal when they found the same sequences the same number of
+/// times in any order, so the same search of tries built in a different order
+/// compares equal. Compare `as_collected` to check the order too.
+impl<T: Clone + Eq + Hash> PartialEq for Found<T> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.collected.len() != other.collected.len() {
+            return false;
+        }
+        let mut counts = std::collections::HashMap::<&[T], isize>::with_capacity(self.collected.len());
+        for seq in self.collected.iter() {
+            *counts.entry(seq).or_default() += 1;
+        }
+        for seq in other.collected.iter() {
+            match counts.get_mut(seq.as_slice()) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl<T: Clone + Eq + Hash> Eq for Found<T> {}
+
+/// Depth first iterator over every node returned by `Trie::iter`, knows
+/// exactly how many nodes are left.
+#[derive(Debug, Clone)]
+pub struct TrieIter<'a, T, S = NoopBuildHasher> {
+    nodes: DepthFirst<'a, T, S>,
+    remaining: usize,
+}
+
+impl<'a, T, S: BuildHasher> Iterator for TrieIter<'a, T, S> {
+    type Item = &'a Node<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, node) = self.nodes.next()?;
+        self.remaining -= 1;
+        Some(node)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T, S: BuildHasher> ExactSizeIterator for TrieIter<'a, T, S> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Read;
+
+    const DATA: &[&str] = &["data/1984.txt", "data/sun-rising.txt"];
+
+    fn get_text(i: usize) -> Vec<String> {
+        let mut contents = String::new();
+        File::open(&DATA[i])
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+            .split_whitespace()
+            .map(|s| s.trim().to_string())
+            .collect()
+    }
+
+    fn make_trie(words: &[String]) -> Trie<char> {
+        let mut trie = Trie::new();
+        for w in words {
+            trie.insert(&w.chars().collect::<Vec<_>>());
+        }
+        trie
+    }
+
+    fn assert_same(a: &Trie<char>, b: &Trie<char>) {
+        assert_eq!(a.starts, b.starts);
+        assert_eq!(a.len, b.len);
+        assert_eq!(a.children.len(), b.children.len());
+        for (key, node) in a.children.iter() {
+            let other = &b.children[key];
+            assert_eq!(node.val, other.val);
+            assert_eq!(node.children, other.children);
+            assert_eq!(node.terminal, other.terminal);
+        }
+    }
+
+    #[test]
+    fn insert_find() {
+        let cmp_found = vec![ vec!['c', 'a', 't'], vec!['c', 'a', 'r', 't'], vec!['c', 'o', 'w']];
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 't']);
+        trie.insert(&['c', 'a', 'r', 't']);
+        trie.insert(&['c', 'o', 'w']);
+        let found = trie.search(&['c']);
+        println!("{:?}", found);
+        for (expected, found) in cmp_found.iter().zip(found.as_collected()) {
+            assert_eq!(&expected[..], found)
+        }
+    }
+
+    fn get_words() -> Vec<String> {
+        let mut contents = String::new();
+        File::open("words.txt")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents.split_whitespace().map(|s| s.to_string()).collect()
+    }
+
+    /// A xorshift generator from `seed`, any fixed mix of inserts and removes
+    /// will do for the tests that churn a trie.
+    pub(crate) fn xorshift(mut seed: u32) -> impl FnMut() -> usize {
+        move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as usize
+        }
+    }
+
+    fn found_strings(found: &Found<char>) -> Vec<String> {
+        found.as_collected()
+            .into_iter()
+            .map(|seq| seq.iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn search_nested_branches() {
+        let words = ["car", "card", "care", "cart", "cab"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let trie = make_trie(&words);
+
+        assert_eq!(found_strings(&trie.search(&['c'])), words);
+        assert_eq!(found_strings(&trie.search(&['c', 'a', 'r'])), &words[..4]);
+        assert!(trie.search(&[]).as_collected().is_empty());
+    }
+
+    #[test]
+    fn search_multi_element_prefix() {
+        let words = ["cat", "cart", "cab", "carts", "carp", "cabin", "cabs"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let trie = make_trie(&words);
+
+        assert_eq!(
+            found_strings(&trie.search(&['c', 'a'])),
+            &["cat", "cart", "carts", "carp", "cab", "cabin", "cabs"]
+        );
+        assert_eq!(
+            found_strings(&trie.search(&['c', 'a', 'r'])),
+            &["cart", "carts", "carp"]
+        );
+        assert_eq!(
+            found_strings(&trie.search(&['c', 'a', 'b'])),
+            &["cab", "cabin", "cabs"]
+        );
+        assert_eq!(found_strings(&trie.search(&['c', 'a', 'b', 'i'])), &["cabin"]);
+        assert!(found_strings(&trie.search(&['c', 'a', 'x'])).is_empty());
+    }
+
+    #[test]
+    fn found_into_response_vec() {
+        let trie = make_trie(&["cat".to_string(), "cow".to_string()]);
+        let found = trie.search(&['c']);
+        let expected = found.as_collected().iter().map(|seq| seq.to_vec()).collect::<Vec<_>>();
+        assert_eq!(found.into_response_vec(), expected);
+        assert!(trie.search(&['x']).into_response_vec().is_empty());
+    }
+
+    #[test]
+    fn found_grouping() {
+        let words = ["car", "cart", "carrot", "cow"].iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        let trie = make_trie(&words);
+
+        let found = trie.search(&['c']);
+        assert_eq!(found.common_prefix_len(), 1);
+        assert_eq!(trie.search(&['c', 'a']).common_prefix_len(), 3);
+        assert_eq!(trie.search(&['c', 'o', 'w']).common_prefix_len(), 3);
+        assert_eq!(trie.search(&['x']).common_prefix_len(), 0);
+
+        let groups = found.group_by_next(1);
+        assert_eq!(groups, vec![
+            ('a', vec![&['c', 'a', 'r'][..], &['c', 'a', 'r', 't'], &['c', 'a', 'r', 'r', 'o', 't']]),
+            ('o', vec![&['c', 'o', 'w'][..]]),
+        ]);
+        // "car" has nothing at depth 3
+        let groups = found.group_by_next(3);
+        assert_eq!(groups.iter().map(|(t, g)| (*t, g.len())).collect::<Vec<_>>(), vec![('t', 1), ('r', 1)]);
+        assert!(found.group_by_next(10).is_empty());
+    }
+
+    #[test]
+    fn search_refs_matches_search() {
+        let trie = make_trie(&get_text(1));
+        for prefix in ["a", "th", "the", "s", "B", "unruly", "#"].iter() {
+            let prefix = prefix.chars().collect::<Vec<_>>();
+            let refs = trie.search_refs(&prefix)
+                .into_iter()
+                .map(|seq| seq.into_iter().copied().collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            assert_eq!(refs, trie.search(&prefix).as_collected());
+        }
+        assert!(trie.search_refs(&[]).is_empty());
+
+        let mut tokens = Trie::new();
+        tokens.insert(&["let".to_string(), "x".to_string()]);
+        let found = tokens.search_refs(&["let".to_string()]);
+        // borrowed from the nodes, not the query
+        let stored = tokens.get(&["let".to_string()]).unwrap().value();
+        assert!(std::ptr::eq(found[0][0], stored));
+    }
+
+    #[test]
+    fn insert_owned_clones() {
+        use std::cell::Cell;
+
+        thread_local!(static CLONES: Cell<usize> = const { Cell::new(0) });
+
+        #[derive(Debug, PartialEq, Eq, Hash)]
+        struct Counted(u32);
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                CLONES.with(|c| c.set(c.get() + 1));
+                Counted(self.0)
+            }
+        }
+        let clones = || CLONES.with(|c| c.replace(0));
+        let seq = |s: &[u32]| s.iter().map(|i| Counted(*i)).collect::<Vec<_>>();
+
+        // the slice API clones once per node it creates
+        let mut sliced = Trie::new();
+        sliced.insert(&seq(&[1, 2, 3, 4, 5]));
+        assert_eq!(clones(), 5);
+        sliced.insert(&seq(&[1, 2, 3, 4, 5]));
+        assert_eq!(clones(), 0);
+        sliced.insert(&seq(&[1, 2, 3, 7, 8]));
+        assert_eq!(clones(), 2);
+
+        let mut owned = Trie::new();
+        owned.insert_owned(seq(&[1, 2, 3, 4, 5]));
+        owned.insert_owned(seq(&[1, 2, 3, 4, 5]));
+        owned.insert_owned(seq(&[1, 2, 9]));
+        assert_eq!(clones(), 0);
+
+        owned.insert_owned(seq(&[1, 2, 3, 7, 8]));
+        sliced.insert(&seq(&[1, 2, 9]));
+        clones();
+        assert_eq!(owned, sliced);
+        assert_eq!(owned.sequences(), sliced.sequences());
+        assert_eq!(owned.node_count(), sliced.node_count());
+        assert_eq!(owned.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn insert_owned_matches_insert() {
+        let words = get_text(1);
+        let mut owned = Trie::new();
+        for w in words.iter() {
+            owned.insert_owned(w.chars().collect());
+        }
+        owned.insert_owned(Vec::new());
+        let sliced = make_trie(&words);
+        assert_eq!(owned.sequences(), sliced.sequences());
+        assert_eq!(owned.sequence_count(), sliced.sequence_count());
+        assert_eq!(owned.check_invariants(), Ok(()));
+
+        let mut lower = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        lower.insert_owned(vec!['C', 'a', 'T']);
+        assert!(lower.contains(&['c', 'a', 't']));
+
+        let mut bounded = Trie::with_max_sequences(1);
+        bounded.insert_owned(vec!['a']);
+        bounded.insert_owned(vec!['b']);
+        assert_eq!(bounded.sequences(), vec![vec!['b']]);
+    }
+
+    #[test]
+    fn search_never_repeats() {
+        let words = get_text(1);
+        let mut trie = Trie::new();
+        let mut rand = xorshift(0x9e37_79b9);
+        for _ in 0..2000 {
+            let x = rand();
+            let word = words[x % words.len()].chars().collect::<Vec<_>>();
+            if x.is_multiple_of(3) {
+                trie.remove(&word);
+            } else {
+                trie.insert(&word);
+            }
+            if x.is_multiple_of(7) {
+                let found = trie.search(&word[..1]);
+                let mut seqs = found.as_collected();
+                let count = seqs.len();
+                seqs.sort();
+                seqs.dedup();
+                assert_eq!(seqs.len(), count);
+            }
+        }
+
+        let mut found = trie.search(&[]);
+        found.collected.extend(trie.sequences());
+        found.collected.extend(trie.sequences());
+        found.dedup();
+        assert_eq!(found.collected, trie.sequences());
+    }
+
+    #[test]
+    fn search_matches_oracle() {
+        use std::collections::HashSet;
+
+        let words = get_words();
+        let trie = make_trie(&words);
+        let unique = words.iter().collect::<HashSet<_>>();
+        for word in words.iter() {
+            for len in 1..=word.chars().count().min(3) {
+                let prefix = word.chars().take(len).collect::<String>();
+                let expected = unique.iter()
+                    .filter(|w| w.starts_with(&prefix))
+                    .map(|w| w.to_string())
+                    .collect::<HashSet<_>>();
+                let found = found_strings(&trie.search(&prefix.chars().collect::<Vec<_>>()));
+
+                assert_eq!(found.len(), expected.len(), "duplicates searching {}", prefix);
+                assert_eq!(found.into_iter().collect::<HashSet<_>>(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn trie_iter() {
+        let ord = &['c', 'a', 't', 'o', 'w'];
+
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 't']);
+        trie.insert(&['c', 'o', 'w']);
+
+        for (i, n) in trie.iter().enumerate() {
+            assert_eq!(ord[i], n.val)
+        }
+    }
+
+    #[test]
+    fn trie_iter_multi_start() {
+        let shapes: &[&[&str]] = &[
+            &["cat", "cow", "dog", "do"],
+            &["a", "b", "c"],
+            &["a", "ab", "b", "ba", "c"],
+            &["dog", "do", "d", "cat", "c"],
+            &["x", "xyz", "y", "yx", "zzz", "z"],
+        ];
+        for words in shapes {
+            let trie = words.iter().copied().collect::<Trie<char>>();
+            let mut yielded = trie.iter().map(|n| n.val).collect::<Vec<_>>();
+            let mut nodes = trie.children.values().map(|n| n.val).collect::<Vec<_>>();
+            yielded.sort_unstable();
+            nodes.sort_unstable();
+            assert_eq!(yielded, nodes, "{:?}", words);
+
+            // same order as the other depth first walks
+            let depth_first = trie.iter_with_depth().map(|(_, v, _)| *v).collect::<Vec<_>>();
+            assert_eq!(trie.iter().map(|n| n.val).collect::<Vec<_>>(), depth_first);
+        }
+
+        let trie = ["cat", "cow", "dog", "do"].iter().copied().collect::<Trie<char>>();
+        let vals = trie.iter().map(|n| n.val).collect::<String>();
+        assert_eq!(vals, "catowdog");
+    }
+
+    #[test]
+    fn trie_iter_exact_size() {
+        for path in DATA.iter().chain(["words.txt"].iter()) {
+            let text = std::fs::read_to_string(path).unwrap();
+            let trie = text.split_whitespace().collect::<Trie<char>>();
+
+            let mut iter = trie.iter();
+            assert_eq!(iter.len(), trie.node_count());
+            assert_eq!(iter.size_hint(), (trie.node_count(), Some(trie.node_count())));
+            iter.next();
+            assert_eq!(iter.len(), trie.node_count() - 1);
+            assert_eq!(trie.iter().count(), trie.node_count());
+            assert_eq!(trie.iter().collect::<Vec<_>>().capacity(), trie.node_count());
+        }
+    }
+
+    #[test]
+    fn trie_iter_with_depth() {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 't']);
+        trie.insert(&['c', 'o', 'w']);
+
+        assert_eq!(
+            trie.iter_with_depth().collect::<Vec<_>>(),
+            vec![
+                (1, &'c', false),
+                (2, &'a', false),
+                (3, &'t', true),
+                (2, &'o', false),
+                (3, &'w', true),
+            ]
+        );
+    }
+
+    #[test]
+    fn trie_walk_subtree() {
+        let trie = make_trie(&get_text(1));
+        assert!(trie.walk_subtree(&['#', '#']).is_none());
+        assert_eq!(trie.walk_subtree(&[]).unwrap().count(), trie.node_count());
+
+        for prefix in ["a", "th", "the", "s", "B", "unruly"].iter() {
+            let prefix = prefix.chars().collect::<Vec<_>>();
+            let mut walked = trie.walk_subtree(&prefix)
+                .unwrap()
+                .filter(|(_, _, terminal)| *terminal)
+                .map(|(path, _, _)| path)
+                .collect::<Vec<_>>();
+            if trie.get(&prefix).unwrap().is_terminal() {
+                walked.insert(0, prefix.clone());
+            }
+            assert_eq!(walked, trie.search(&prefix).as_collected());
+        }
+
+        // every path ends in its value and extends the one before it or an ancestor
+        let walk = trie.walk_subtree(&['t']).unwrap().collect::<Vec<_>>();
+        for (path, val, terminal) in walk.iter() {
+            assert_eq!(path.last(), Some(*val));
+            assert_eq!(trie.get(path).unwrap().is_terminal(), *terminal);
+        }
+    }
+
+    #[test]
+    fn trie_visit() {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 't']);
+        trie.insert(&['c', 'o', 'w']);
+
+        let mut visited = vec![];
+        trie.visit(|_, val, _| {
+            visited.push(*val);
+            ControlFlow::Continue(VisitAction::Descend)
+        });
+        assert_eq!(visited, trie.iter().map(|n| n.val).collect::<Vec<_>>());
+
+        let mut paths = vec![];
+        trie.visit(|path, _, _| {
+            paths.push(path.iter().collect::<String>());
+            if path.len() == 2 {
+                return ControlFlow::Break(());
+            }
+            ControlFlow::Continue(VisitAction::Descend)
+        });
+        assert_eq!(paths, &["c", "ca"]);
+
+        // skip everything below a vowel
+        let mut terminals = vec![];
+        trie.insert(&['c', 'r', 'y']);
+        trie.visit(|path, val, terminal| {
+            if terminal {
+                terminals.push(path.to_vec());
+            }
+            if "aeiou".contains(*val) {
+                return ControlFlow::Continue(VisitAction::SkipSubtree);
+            }
+            ControlFlow::Continue(VisitAction::Descend)
+        });
+        assert_eq!(terminals, vec![vec!['c', 'r', 'y']]);
+
+        let words = get_text(0);
+        let trie = make_trie(&words);
+        let mut count = 0;
+        trie.visit(|_, _, _| {
+            count += 1;
+            ControlFlow::Continue(VisitAction::Descend)
+        });
+        assert_eq!(count, trie.iter_with_depth().count());
+    }
+
+    #[test]
     fn trie_remove() {
         let ord = &['c', 'a', 't', 'o', 'w'];
 
@@ -387,12 +2312,1131 @@ mod tests {
         trie.insert(&['c', 'o', 'w']);
 
         trie.remove(&['c', 'a', 'r', 't']);
-        for (i, n) in trie.iter().enumerate() {
-            assert_eq!(ord[i], n.val)
+        for (i, n) in trie.iter().enumerate() {
+            assert_eq!(ord[i], n.val)
+        }
+        trie.remove(&['c', 'o', 'w']);
+        trie.remove(&['c', 'a', 't']);
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn trie_clone_from() {
+        let small = make_trie(&get_text(1)[..20]);
+        let big = make_trie(&get_text(1));
+
+        let mut scratch = small.clone();
+        scratch.clone_from(&big);
+        assert_same(&scratch, &big);
+
+        scratch.clone_from(&small);
+        assert_same(&scratch, &small);
+    }
+
+    #[test]
+    fn trie_hash_eq() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash_of(trie: &Trie<char>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            trie.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut words = get_text(1);
+        let forward = make_trie(&words);
+        words.reverse();
+        let backward = make_trie(&words);
+        assert_eq!(forward, backward);
+        assert_eq!(hash_of(&forward), hash_of(&backward));
+
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 'r', 't']);
+        trie.insert(&['c', 'a', 'r']);
+        let mut other = Trie::new();
+        other.insert(&['c', 'a', 'r']);
+        other.insert(&['c', 'a', 'r', 't']);
+        assert_eq!(trie, other);
+        assert_eq!(hash_of(&trie), hash_of(&other));
+
+        other.insert(&['c', 'o', 'w']);
+        assert_ne!(trie, other);
+        assert_ne!(hash_of(&trie), hash_of(&other));
+    }
+
+    #[test]
+    fn trie_starts_with() {
+        let mut trie = Trie::new();
+        assert!(!trie.starts_with(&[]));
+        trie.insert(&['c', 'a', 't']);
+        trie.insert(&['c', 'o', 'w']);
+
+        assert!(trie.starts_with(&[]));
+        assert!(trie.starts_with(&['c']));
+        assert!(trie.starts_with(&['c', 'a']));
+        assert!(trie.starts_with(&['c', 'o', 'w']));
+        assert!(!trie.starts_with(&['c', 'o', 'w', 's']));
+        assert!(!trie.starts_with(&['d']));
+
+        trie.remove(&['c', 'a', 't']);
+        assert!(!trie.starts_with(&['c', 'a']));
+        assert!(trie.starts_with(&['c']));
+    }
+
+    #[test]
+    fn trie_count_prefix() {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 'r']);
+        trie.insert(&['c', 'a', 'r', 't']);
+        trie.insert(&['c', 'a', 'r', 'r', 'o', 't']);
+
+        assert_eq!(trie.count_prefix(&[]), 3);
+        assert_eq!(trie.count_prefix(&['c']), 3);
+        assert_eq!(trie.count_prefix(&['c', 'a', 'r']), 3);
+        assert_eq!(trie.count_prefix(&['c', 'a', 'r', 'r']), 1);
+        assert_eq!(trie.count_prefix(&['c', 'a', 'r', 't']), 1);
+        assert_eq!(trie.count_prefix(&['c', 'o']), 0);
+        assert_eq!(trie.count_prefix(&['d']), 0);
+    }
+
+    #[test]
+    fn trie_nth_sequence() {
+        let words = get_text(1);
+        let trie = make_trie(&words);
+        let seqs = trie.sequences();
+
+        assert_eq!(seqs.len(), trie.sequence_count());
+        assert_eq!(trie.nth_sequence(0).as_ref(), seqs.first());
+        for (i, seq) in seqs.iter().enumerate() {
+            assert_eq!(trie.nth_sequence(i).as_ref(), Some(seq));
+        }
+        assert_eq!(trie.nth_sequence(seqs.len()), None);
+        assert_eq!(Trie::<char>::new().nth_sequence(0), None);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn trie_random_sequence() {
+        use rand::{rngs::StdRng, SeedableRng};
+        use std::collections::HashSet;
+
+        let words = ["cat", "cart", "car", "cow", "dog"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let trie = make_trie(&words);
+        let mut rng = StdRng::seed_from_u64(1984);
+
+        let mut seen = HashSet::new();
+        for _ in 0..10_000 {
+            seen.insert(trie.random_sequence(&mut rng).unwrap());
+        }
+        assert_eq!(seen.len(), 5);
+    }
+
+    #[test]
+    fn trie_shortest_unique_prefix() {
+        let words = ["status", "stash", "stop", "stat"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let trie = make_trie(&words[..3]);
+        let unique = |trie: &Trie<char>, word: &str| {
+            trie.shortest_unique_prefix(&word.chars().collect::<Vec<_>>())
+                .map(|p| p.into_iter().collect::<String>())
+        };
+
+        assert_eq!(unique(&trie, "status").as_deref(), Some("stat"));
+        assert_eq!(unique(&trie, "stash").as_deref(), Some("stas"));
+        assert_eq!(unique(&trie, "stop").as_deref(), Some("sto"));
+        assert_eq!(unique(&trie, "sta"), None);
+        assert_eq!(unique(&trie, "start"), None);
+
+        // "stat" is a prefix of "status" so only the full word is unique
+        let trie = make_trie(&words);
+        assert_eq!(unique(&trie, "stat").as_deref(), Some("stat"));
+        assert_eq!(unique(&trie, "status").as_deref(), Some("statu"));
+    }
+
+    #[test]
+    fn trie_closest_by_prefix() {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 'r']);
+        trie.insert(&['c', 'a', 'r', 't']);
+        trie.insert(&['c', 'a', 'r', 'r', 'o', 't']);
+        trie.insert(&['c', 'o', 'w']);
+
+        let car_family = vec![
+            vec!['c', 'a', 'r'],
+            vec!['c', 'a', 'r', 't'],
+            vec!['c', 'a', 'r', 'r', 'o', 't'],
+        ];
+        assert_eq!(trie.closest_by_prefix(&['c', 'a', 'r', 'z'], 10), car_family);
+        assert_eq!(trie.closest_by_prefix(&['c', 'a', 'r', 'z'], 2), car_family[..2].to_vec());
+        assert_eq!(trie.closest_by_prefix(&['c', 'o', 'x'], 10), vec![vec!['c', 'o', 'w']]);
+        assert!(trie.closest_by_prefix(&['x'], 10).is_empty());
+        assert!(trie.closest_by_prefix(&['c'], 0).is_empty());
+    }
+
+    #[test]
+    fn trie_starts() {
+        let words = ["cat", "cow", "dog", "do", "d", "egg"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let mut trie = make_trie(&words);
+        assert_eq!(trie.starts().collect::<String>(), "cde");
+        assert_eq!(trie.num_starts(), 3);
+
+        assert!(trie.remove_str("cat"));
+        assert!(trie.remove_str("cow"));
+        assert_eq!(trie.starts().collect::<String>(), "de");
+
+        assert!(trie.remove_str("d"));
+        assert_eq!(trie.starts().collect::<String>(), "de");
+        assert!(trie.remove_str("do"));
+        assert!(trie.remove_str("dog"));
+        assert_eq!(trie.starts().collect::<String>(), "e");
+        assert_eq!(trie.num_starts(), 1);
+
+        assert!(trie.remove_str("egg"));
+        assert_eq!(trie.num_starts(), 0);
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn remove_shapes() {
+        let words = ["car", "cart", "carrot", "cab", "c"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let mut trie = make_trie(&words);
+
+        assert!(!trie.remove_str("ca"));
+        assert!(!trie.remove_str("carts"));
+        assert!(trie.remove_str("car"));
+        assert!(!trie.remove_str("car"));
+        assert_eq!(found_strings(&trie.search(&['c'])), &["c", "cart", "carrot", "cab"]);
+
+        assert!(trie.remove_str("carrot"));
+        assert_eq!(found_strings(&trie.search(&['c'])), &["c", "cart", "cab"]);
+        assert!(trie.remove_str("c"));
+        assert!(trie.remove_str("cart"));
+        assert_eq!(found_strings(&trie.search(&['c'])), &["cab"]);
+        assert_eq!(trie.len, 3);
+        assert!(trie.remove_str("cab"));
+        assert!(trie.is_empty());
+        assert!(trie.children.is_empty());
+    }
+
+    #[test]
+    fn remove_single_element_prefix() {
+        let mut trie = make_trie(&["cat".to_string()]);
+        // "c" is only a path of "cat"
+        assert_eq!(trie.remove_explain(&['c']), (false, RemoveKind::NoMatch));
+        assert_eq!(trie.sequence_count(), 1);
+        assert_eq!(trie.node_count(), 3);
+        assert_eq!(trie.count_prefix(&['c']), 1);
+
+        assert!(trie.insert(&['c']));
+        assert_eq!(trie.remove_explain(&['c']), (true, RemoveKind::Starts));
+        assert_eq!(trie.remove_explain(&['c']), (false, RemoveKind::NoMatch));
+        assert_eq!(trie.sequence_count(), 1);
+        assert_eq!(trie.node_count(), 3);
+        assert_eq!(found_strings(&trie.search(&['c'])), &["cat"]);
+
+        // a start that is only a stored sequence goes with it
+        assert!(trie.insert(&['d']));
+        assert!(trie.remove(&['d']));
+        assert!(!trie.remove(&['d']));
+        assert_eq!(trie.num_starts(), 1);
+        assert_eq!(trie.check_invariants(), Ok(()));
+    }
+
+    /// Every child key of every node and every start is in the map.
+    fn assert_no_dangling(trie: &Trie<char>) {
+        for key in trie.starts.iter().chain(trie.children.values().flat_map(|node| node.children.iter())) {
+            assert!(trie.children.contains_key(key), "dangling key {}", key);
+        }
+        assert_eq!(trie.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn remove_middle_word_both_orders() {
+        let words = ["car", "cart", "carrot"].iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        for order in [["cart", "carrot"], ["carrot", "cart"]].iter() {
+            let mut trie = make_trie(&words);
+            assert_no_dangling(&trie);
+            for (i, w) in order.iter().enumerate() {
+                assert!(trie.remove_str(w));
+                assert_no_dangling(&trie);
+                assert_eq!(trie.search(&['c', 'a', 'r']).as_collected().len(), 2 - i);
+            }
+            assert_eq!(found_strings(&trie.search(&['c', 'a', 'r'])), &["car"]);
+            assert_eq!(trie.node_count(), 3);
+
+            // the removed tails can come back
+            assert!(trie.insert_str(order[1]));
+            assert_no_dangling(&trie);
+            assert!(trie.remove_str("car"));
+            assert_no_dangling(&trie);
+            assert_eq!(found_strings(&trie.search(&['c'])), &[order[1]]);
+        }
+    }
+
+    #[test]
+    fn remove_kinds() {
+        let words = ["car", "cart", "carrot", "cab", "c", "dog"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let mut trie = make_trie(&words);
+
+        assert_eq!(trie.classify_removal(&[]), RemoveKind::NoMatch);
+        assert_eq!(trie.classify_removal(&['c', 'a']), RemoveKind::NoMatch);
+        // with_child, other sequences extend it
+        assert_eq!(trie.classify_removal(&['c', 'a', 'r']), RemoveKind::Stemish);
+        // with_terminal, the tail below a stored prefix goes
+        assert_eq!(trie.classify_removal(&['c', 'a', 'r', 't']), RemoveKind::Terminal);
+        // with_inner_terminal, the tail below a branch goes
+        assert_eq!(trie.classify_removal(&['c', 'a', 'b']), RemoveKind::Terminal);
+        // starts
+        assert_eq!(trie.classify_removal(&['c']), RemoveKind::Starts);
+        assert_eq!(trie.classify_removal(&['d', 'o', 'g']), RemoveKind::Childless);
+
+        // classifying doesn't change anything
+        assert_eq!(trie.sequence_count(), words.len());
+        assert_eq!(trie.remove_explain(&['d', 'o', 'g']), (true, RemoveKind::Childless));
+        assert_eq!(trie.remove_explain(&['d', 'o', 'g']), (false, RemoveKind::NoMatch));
+        assert_eq!(trie.remove_explain(&['c', 'a', 'r']), (true, RemoveKind::Stemish));
+        assert_eq!(trie.remove_explain(&['c']), (true, RemoveKind::Starts));
+
+        let mut trie = make_trie(&["cat".to_string()]);
+        assert_eq!(trie.remove_explain(&['c', 'a', 't']), (true, RemoveKind::Rest));
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn remove_rest_only_for_last_sequence() {
+        // the other sequences' nodes are shorter than the removed one
+        let words = ["a", "b", "cdef"].iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        let mut trie = make_trie(&words);
+        assert_eq!(trie.classify_removal(&['c', 'd', 'e', 'f']), RemoveKind::Childless);
+        assert_eq!(trie.remove_explain(&['a']), (true, RemoveKind::Starts));
+        assert_eq!(trie.remove_explain(&['c', 'd', 'e', 'f']), (true, RemoveKind::Childless));
+        assert_eq!(found_strings(&trie.search(&['b'])), &["b"]);
+        assert_eq!(trie.classify_removal(&['b']), RemoveKind::Starts);
+
+        // the last sequence left after others sharing its path went
+        let words = ["cart", "car", "cow"].iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        let mut trie = make_trie(&words);
+        assert!(trie.remove_str("cow"));
+        assert!(trie.remove_str("car"));
+        assert_eq!(trie.classify_removal(&['c', 'a', 'r', 't']), RemoveKind::Rest);
+        assert_eq!(trie.remove_explain(&['c', 'a', 'r', 't']), (true, RemoveKind::Rest));
+        assert!(trie.is_empty() && trie.children.is_empty() && trie.starts.is_empty());
+
+        // a stored prefix isn't the only sequence
+        let words = ["ca", "cat"].iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        let mut trie = make_trie(&words);
+        assert_eq!(trie.remove_explain(&['c', 'a', 't']), (true, RemoveKind::Terminal));
+        assert_eq!(trie.remove_explain(&['c', 'a']), (true, RemoveKind::Rest));
+        assert_eq!(trie.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn pop_until_empty() {
+        let words = get_text(1);
+        let mut trie = make_trie(&words);
+
+        let mut popped = Vec::new();
+        while let Some(seq) = trie.pop_sequence() {
+            popped.push(seq.into_iter().collect::<String>());
+        }
+        assert!(trie.is_empty());
+        assert_eq!(trie.node_count(), 0);
+        assert!(trie.children.is_empty() && trie.starts.is_empty());
+
+        let mut words = words;
+        words.sort();
+        words.dedup();
+        popped.sort();
+        assert_eq!(popped, words);
+    }
+
+    #[test]
+    fn trie_clear_shrink() {
+        let mut trie = make_trie(&get_text(0));
+        let full = trie.memory_usage();
+        assert!(full > trie.node_count() * std::mem::size_of::<Node<char>>());
+
+        // `clear` keeps the map's capacity around for reuse
+        let nodes = trie.node_count();
+        trie.clear();
+        assert!(trie.is_empty());
+        assert!(trie.children.capacity() >= nodes);
+        assert!(trie.memory_usage() > nodes * std::mem::size_of::<Node<char>>());
+
+        trie.clear_shrink();
+        assert!(trie.is_empty());
+        assert_eq!(trie.memory_usage(), std::mem::size_of::<Trie<char>>());
+        assert_eq!(trie.memory_usage(), Trie::<char>::new().memory_usage());
+
+        // still usable afterwards
+        trie.insert(&['c', 'a', 't']);
+        assert!(trie.contains(&['c', 'a', 't']));
+    }
+
+    #[test]
+    fn single_completion_cases() {
+        let mut trie = Trie::new();
+        assert_eq!(trie.single_completion(&[]), None);
+        trie.insert_str("cart");
+        assert_eq!(trie.single_completion(&[]), Some(vec!['c', 'a', 'r', 't']));
+        assert_eq!(trie.single_completion(&['c']), Some(vec!['c', 'a', 'r', 't']));
+        assert_eq!(trie.single_completion(&['c', 'a', 'r', 't']), Some(vec!['c', 'a', 'r', 't']));
+        assert_eq!(trie.single_completion(&['c', 'a', 'r', 't', 's']), None);
+
+        // a stored prefix with an extension is ambiguous, below it isn't
+        trie.insert_str("car");
+        assert_eq!(trie.single_completion(&['c', 'a']), None);
+        assert_eq!(trie.single_completion(&['c', 'a', 'r']), None);
+        assert_eq!(trie.single_completion(&['c', 'a', 'r', 't']), Some(vec!['c', 'a', 'r', 't']));
+        trie.remove(&['c', 'a', 'r', 't']);
+        assert_eq!(trie.single_completion(&['c']), Some(vec!['c', 'a', 'r']));
+
+        trie.insert_str("cow");
+        assert_eq!(trie.single_completion(&['c']), None);
+        assert_eq!(trie.single_completion(&[]), None);
+        assert_eq!(trie.single_completion(&['c', 'o']), Some(vec!['c', 'o', 'w']));
+
+        let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        trie.insert_str("Dog");
+        assert_eq!(trie.single_completion(&['D']), Some(vec!['d', 'o', 'g']));
+
+        // sized once, no room left over
+        let seq = trie.single_completion(&['d']).unwrap();
+        assert_eq!(seq.capacity(), seq.len());
+        assert_eq!(trie.single_completion(&[]).unwrap().capacity(), 3);
+    }
+
+    #[test]
+    fn search_into_reuses() {
+        let words = std::fs::read_to_string("data/1984.txt")
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut trie = Trie::new();
+        for w in words.iter() {
+            trie.insert(w);
+        }
+
+        let mut out = vec![vec!['x'; 32]; 3];
+        let mut collected = Vec::with_capacity(8);
+        for w in words.iter().step_by(97) {
+            let prefix = &w[..w.len().min(2)];
+            trie.search_into(prefix, &mut out);
+            assert_eq!(out, trie.search(prefix).into_response_vec());
+            trie.search(prefix).collect_into(&mut collected);
+            assert_eq!(collected, out);
+        }
+        trie.search_into(&['#'], &mut out);
+        assert!(out.is_empty());
+        trie.search_into(&[], &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn paths_in_order() {
+        let trie = make_trie(&["cat".to_string(), "cow".to_string()]);
+        let paths = trie.paths()
+            .map(|(path, terminal)| (path.into_iter().collect::<String>(), terminal))
+            .collect::<Vec<_>>();
+        let expected = [("c", false), ("ca", false), ("cat", true), ("co", false), ("cow", true)];
+        assert_eq!(paths, expected.iter().map(|(p, t)| (p.to_string(), *t)).collect::<Vec<_>>());
+
+        let trie = make_trie(&get_text(0));
+        let mut seen = std::collections::HashSet::new();
+        let mut count = 0;
+        for (path, _) in trie.paths() {
+            // the parent came first
+            assert!(path.len() == 1 || seen.contains(&path[..path.len() - 1]));
+            seen.insert(path);
+            count += 1;
+        }
+        assert_eq!(count, trie.node_count());
+        assert_eq!(trie.paths().filter(|(_, t)| *t).map(|(p, _)| p).collect::<Vec<_>>(), trie.sequences());
+    }
+
+    #[test]
+    fn found_eq_any_order() {
+        let words = get_text(0);
+        let forward = make_trie(&words);
+        let mut reversed = words.clone();
+        reversed.reverse();
+        let reversed = make_trie(&reversed);
+
+        for w in words.iter().step_by(301) {
+            let prefix = w.chars().take(2).collect::<Vec<_>>();
+            let (a, b) = (forward.search(&prefix), reversed.search(&prefix));
+            assert_eq!(a, b);
+            let all = a.as_collected();
+            assert!(all.iter().all(|seq| b.contains_seq(seq)));
+        }
+
+        let found = forward.search(&['t', 'h', 'e']);
+        assert!(found.contains_seq(&['t', 'h', 'e']));
+        assert!(!found.contains_seq(&['t', 'h']));
+        assert_ne!(found, forward.search(&['t', 'h']));
+        assert_ne!(found, Found::new());
+
+        // the same sequences a different number of times
+        let mut twice = Found::new();
+        twice.collected = vec![vec!['a'], vec!['a'], vec!['b']];
+        let mut once = Found::new();
+        once.collected = vec![vec!['a'], vec!['b'], vec!['b']];
+        assert_ne!(twice, once);
+        once.collected[2] = vec!['a'];
+        assert_eq!(twice, once);
+    }
+
+    #[test]
+    fn inserted_under_matches_search() {
+        let trie = make_trie(&get_text(1));
+        for start in trie.paths().filter(|(path, _)| path.len() == 1).map(|(path, _)| path[0]) {
+            let found = trie.search(&[start]).into_response_vec();
+            assert_eq!(trie.first_inserted_under(&start).as_ref(), found.first());
+            assert_eq!(trie.last_inserted_under(&start).as_ref(), found.last());
+        }
+        assert_eq!(trie.first_inserted_under(&'~'), None);
+        assert_eq!(trie.last_inserted_under(&'~'), None);
+    }
+
+    #[test]
+    fn found_exact_match() {
+        let trie = make_trie(&["car", "cart", "carrot", "cow", "dog"].iter().map(|w| w.to_string()).collect::<Vec<_>>());
+
+        // prefix is a word with extensions
+        let found = trie.search(&['c', 'a', 'r']);
+        assert_eq!(found.exact(), Some(&['c', 'a', 'r'][..]));
+        assert_eq!(found.extensions().count(), 2);
+        assert_eq!(found.as_collected()[0], &['c', 'a', 'r']);
+
+        // prefix isn't a word
+        let found = trie.search(&['c']);
+        assert_eq!(found.exact(), None);
+        assert_eq!(found.extensions().count(), 4);
+        assert_eq!(trie.search(&['x']).exact(), None);
+        assert_eq!(trie.search(&['x']).extensions().count(), 0);
+
+        // a word with no extensions
+        let found = trie.search(&['d', 'o', 'g']);
+        assert_eq!(found.exact(), Some(&['d', 'o', 'g'][..]));
+        assert_eq!(found.extensions().count(), 0);
+
+        // the other prefix searches agree
+        assert_eq!(trie.search_within(&['c', 'a', 'r'], 1).exact(), Some(&['c', 'a', 'r'][..]));
+        assert_eq!(trie.search_prepared(&trie.prepare(&['d', 'o', 'g'])).exact(), Some(&['d', 'o', 'g'][..]));
+        assert_eq!(trie.get(&['c', 'o', 'w']).unwrap().completions().exact(), Some(&['c', 'o', 'w'][..]));
+        assert_eq!(trie.compile().search(&['c', 'a', 'r']).exact(), Some(&['c', 'a', 'r'][..]));
+        assert_eq!(trie.search_suffix(&['d', 'o', 'g']).exact(), None);
+        let mut arena = crate::ArenaTrie::new();
+        arena.insert(&['c', 'a', 't']);
+        assert_eq!(arena.search(&['c', 'a', 't']).exact(), Some(&['c', 'a', 't'][..]));
+        assert_eq!(arena.search(&['c', 'a']).exact(), None);
+
+        // only the search itself decides, not what ends up first
+        let mut found = trie.search(&['c', 'o', 'w']);
+        found.dedup();
+        assert_eq!(found.exact(), Some(&['c', 'o', 'w'][..]));
+    }
+
+    #[test]
+    fn trie_compact() {
+        let words = get_text(0);
+        let mut trie = make_trie(&words);
+        trie.insert_weighted(&['c', 'a', 't'], 7);
+        for (i, w) in words.iter().enumerate() {
+            if i % 10 != 0 {
+                trie.remove(&w.chars().collect::<Vec<_>>());
+            }
+        }
+        let kept = trie.clone();
+        let capacity = trie.children.capacity();
+        let before = trie.memory_usage();
+
+        let reclaimed = trie.compact();
+        assert_eq!(reclaimed, before - trie.memory_usage());
+        assert!(trie.memory_usage() < before / 2);
+        assert!(trie.children.capacity() < capacity / 4);
+        assert_eq!(trie.sequences(), kept.sequences());
+        assert_eq!(trie.iter().collect::<Vec<_>>(), kept.iter().collect::<Vec<_>>());
+        assert_eq!(trie.weight(&['c', 'a', 't']), kept.weight(&['c', 'a', 't']));
+        assert_eq!(trie.check_invariants(), Ok(()));
+
+        // nothing left to give back
+        assert!(trie.compact() < 64);
+        trie.insert(&['z', 'z']);
+        assert!(trie.contains(&['z', 'z']));
+    }
+
+    #[test]
+    fn trie_shrink_children() {
+        let words = std::fs::read_to_string("words.txt").unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut trie = Trie::new();
+        for round in 0..3 {
+            for w in words.iter() {
+                trie.insert(w);
+            }
+            for w in words.iter().skip(round).step_by(2) {
+                trie.remove(w);
+            }
+        }
+        let kept = trie.sequences();
+        let capacity = trie.children.capacity();
+        let before = trie.memory_usage();
+
+        trie.shrink_children();
+        assert!(trie.memory_usage() < before);
+        assert_eq!(trie.children.capacity(), capacity);
+        assert!(trie.children.values().all(|node| node.children.capacity() == node.children.len()));
+        assert_eq!(trie.starts.capacity(), trie.starts.len());
+        assert_eq!(trie.sequences(), kept);
+        assert_eq!(trie.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn remove_child_shrinks() {
+        let mut trie = Trie::new();
+        for c in "abcdefghij".chars() {
+            trie.insert(&['x', c]);
+        }
+        let x = sequence_key(&['x']);
+        assert!(trie.children[&x].children.capacity() >= 10);
+        for c in "abcdefgh".chars() {
+            trie.remove(&['x', c]);
+        }
+        assert!(trie.children[&x].children.capacity() < 8);
+        assert_eq!(trie.search(&['x']).as_collected(), vec![&['x', 'i'][..], &['x', 'j']]);
+    }
+
+    #[test]
+    fn trie_children_of() {
+        let words = ["car", "cart", "cow"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let trie = make_trie(&words);
+
+        assert_eq!(trie.children_of(&[]), Some(vec![(&'c', false)]));
+        assert_eq!(trie.children_of(&['c']), Some(vec![(&'a', false), (&'o', false)]));
+        assert_eq!(trie.children_of(&['c', 'a']), Some(vec![(&'r', true)]));
+        assert_eq!(trie.children_of(&['c', 'a', 'r']), Some(vec![(&'t', true)]));
+        assert_eq!(trie.children_of(&['c', 'a', 'r', 't']), Some(vec![]));
+        assert_eq!(trie.children_of(&['c', 'x']), None);
+    }
+
+    #[test]
+    fn search_includes_stored_prefix() {
+        let words = ["car", "cart", "carrot"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let trie = make_trie(&words);
+
+        // prefix is a word with extensions, the exact match comes first
+        assert_eq!(found_strings(&trie.search(&['c', 'a', 'r'])), &["car", "cart", "carrot"]);
+        // prefix is a word with no children
+        assert_eq!(found_strings(&trie.search(&['c', 'a', 'r', 't'])), &["cart"]);
+        // prefix is not a word
+        assert_eq!(found_strings(&trie.search(&['c', 'a', 'r', 'r'])), &["carrot"]);
+        assert_eq!(found_strings(&trie.search(&['c', 'a'])), &["car", "cart", "carrot"]);
+    }
+
+    #[test]
+    fn search_insertion_order() {
+        let mut trie = Trie::new();
+        for word in ["cow", "cat", "dog", "cub", "ca", "car", "do"].iter() {
+            trie.insert_str(word);
+        }
+        let ordered = |trie: &Trie<char>, prefix: &[char]| {
+            trie.search(prefix)
+                .as_collected_ordered()
+                .map(|seq| seq.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        };
+        // a stored prefix still comes before its extensions
+        assert_eq!(ordered(&trie, &['c']), &["cow", "ca", "cat", "car", "cub"][..]);
+        assert_eq!(trie.sequences(), ["cow", "ca", "cat", "car", "cub", "do", "dog"]
+            .iter()
+            .map(|w| w.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>());
+
+        // removing keeps the rest in place, re-inserting goes to the back
+        trie.remove_str("cat");
+        assert_eq!(ordered(&trie, &['c']), &["cow", "ca", "car", "cub"][..]);
+        trie.remove_str("cow");
+        trie.insert_str("cow");
+        assert_eq!(ordered(&trie, &['c']), &["ca", "car", "cub", "cow"][..]);
+        assert_eq!(
+            trie.iter().map(|n| n.val).collect::<String>(),
+            "carubowdog"
+        );
+    }
+
+    #[test]
+    fn trie_to_filtered() {
+        let trie = make_trie(&get_text(1));
+
+        let long = trie.to_filtered(|seq| seq.len() > 4);
+        let expected = trie.sequences().into_iter().filter(|seq| seq.len() > 4).collect::<Vec<_>>();
+        assert_eq!(long.sequences(), expected);
+        assert_eq!(long.sequence_count(), expected.len());
+        assert!(long.sequences().iter().all(|seq| trie.search(seq).as_collected().contains(&seq.as_slice())));
+
+        let t = trie.to_filtered(|seq| seq[0] == 't');
+        assert_eq!(t.num_starts(), 1);
+        assert_eq!(t.sequence_count(), trie.count_prefix(&['t']));
+        assert_eq!(t.check_invariants(), Ok(()));
+
+        assert!(trie.to_filtered(|_| false).is_empty());
+        assert_eq!(trie.to_filtered(|_| true), trie);
+    }
+
+    #[test]
+    fn trie_map() {
+        let trie = make_trie(&get_text(1));
+        let upper = trie.map(|c| c.to_ascii_uppercase());
+        let mut expected = trie.sequences()
+            .into_iter()
+            .map(|seq| seq.iter().map(|c| c.to_ascii_uppercase()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        expected.sort();
+        expected.dedup();
+        assert_eq!(upper.sequence_count(), expected.len());
+        assert!(expected.iter().all(|seq| upper.search(seq).as_collected().contains(&seq.as_slice())));
+
+        let ids = make_trie(&["cat".to_string(), "dog".to_string()]).map(|c| *c as u32);
+        assert!(ids.contains(&['d' as u32, 'o' as u32, 'g' as u32]));
+
+        // 'a' and 'b' collapse so "ab" and "ba" become the same sequence
+        let words = ["ab", "ba", "bb", "abc", "c"].iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        let collapsed = make_trie(&words).map(|c| if *c == 'a' || *c == 'b' { 'x' } else { *c });
+        assert_eq!(collapsed.sequence_count(), 3);
+        assert_eq!(found_strings(&collapsed.search(&['x'])), &["xx", "xxc"]);
+        assert_eq!(collapsed.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn trie_is_leaf() {
+        let words = ["car", "cart", "carrot"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>();
+        let trie = make_trie(&words);
+
+        // terminal with children
+        assert!(!trie.is_leaf(&['c', 'a', 'r']));
+        assert!(trie.has_extensions(&['c', 'a', 'r']));
+        // terminal without children
+        assert!(trie.is_leaf(&['c', 'a', 'r', 't']));
+        assert!(!trie.has_extensions(&['c', 'a', 'r', 't']));
+        // not terminal with children
+        assert!(!trie.is_leaf(&['c', 'a']));
+        assert!(trie.has_extensions(&['c', 'a']));
+        // not stored at all
+        assert!(!trie.is_leaf(&['c', 'o', 'w']));
+        assert!(!trie.has_extensions(&['c', 'o', 'w']));
+        assert!(!trie.is_leaf(&[]));
+    }
+
+    /// Recounts every node's terminals by walking its subtree.
+    fn assert_counts(trie: &Trie<char>) {
+        for node in trie.children.values() {
+            let mut count = 0;
+            let mut stack = vec![node];
+            while let Some(node) = stack.pop() {
+                if node.terminal {
+                    count += 1;
+                }
+                stack.extend(node.children(&trie.children));
+            }
+            assert_eq!(node.descendant_terminals, count, "{:?}", node.val);
+        }
+    }
+
+    #[test]
+    fn cached_terminal_counts() {
+        let words = get_words();
+        let mut trie = Trie::new();
+        let mut rand = xorshift(0x9e37_79b9);
+        for i in 0..4000 {
+            let word = words[rand() % words.len()].chars().collect::<Vec<_>>();
+            let prefix = &word[..1 + rand() % word.len()];
+            if rand().is_multiple_of(3) {
+                trie.remove(prefix);
+            } else {
+                trie.insert(prefix);
+            }
+            if i % 100 == 0 {
+                assert_counts(&trie);
+            }
+        }
+        assert_counts(&trie);
+    }
+
+    #[test]
+    fn counters_match_oracles() {
+        use std::collections::HashSet;
+
+        let words = get_words();
+        let mut trie = Trie::new();
+        let mut oracle = HashSet::new();
+        let mut rand = xorshift(0x2545_f491);
+        for _ in 0..4000 {
+            let word = words[rand() % words.len()].chars().collect::<Vec<_>>();
+            let prefix = word[..1 + rand() % word.len()].to_vec();
+            if rand().is_multiple_of(3) {
+                assert_eq!(trie.remove(&prefix), oracle.remove(&prefix));
+            } else {
+                trie.insert(&prefix);
+                oracle.insert(prefix);
+            }
+            assert_eq!(trie.sequence_count(), oracle.len());
+            assert_eq!(trie.node_count(), trie.children.len());
+            assert_eq!(trie.is_empty(), oracle.is_empty());
+        }
+    }
+
+    #[test]
+    fn repeated_elements_any_order() {
+        use std::collections::BTreeSet;
+
+        let corpus = ["aaaa", "aaab", "abab", "bbaa", "aa", "aab", "bb", "a", "b", "ba", "abba", "baab"];
+        let mut rand = xorshift(0x1b87_3593);
+        let mut shuffled = |words: &mut Vec<&'static str>| {
+            for i in (1..words.len()).rev() {
+                words.swap(i, rand() % (i + 1));
+            }
+        };
+        let check = |trie: &Trie<char>, stored: &BTreeSet<&str>| {
+            assert_no_dangling(trie);
+            assert_eq!(trie.sequence_count(), stored.len());
+            for prefix in ["a", "aa", "aaa", "ab", "b", "bb", "bba", "abab"].iter() {
+                let seq = prefix.chars().collect::<Vec<_>>();
+                let mut found = found_strings(&trie.search(&seq));
+                found.sort();
+                let expected = stored.iter().copied().filter(|w| w.starts_with(prefix)).collect::<Vec<_>>();
+                assert_eq!(found, expected, "search {}", prefix);
+                assert_eq!(trie.contains(&seq), !expected.is_empty(), "contains {}", prefix);
+            }
+        };
+
+        for _ in 0..50 {
+            let mut words = corpus.to_vec();
+            shuffled(&mut words);
+            let mut trie = Trie::new();
+            let mut stored = BTreeSet::new();
+            for w in words.iter() {
+                assert!(trie.insert_str(w));
+                stored.insert(*w);
+                check(&trie, &stored);
+            }
+
+            shuffled(&mut words);
+            for w in words.iter() {
+                assert!(trie.remove_str(w), "remove {}", w);
+                assert!(!trie.remove_str(w));
+                stored.remove(w);
+                check(&trie, &stored);
+            }
+            assert!(trie.is_empty() && trie.children.is_empty());
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Insert(Vec<char>),
+        InsertOwned(Vec<char>),
+        Remove(Vec<char>),
+    }
+
+    fn op() -> impl proptest::strategy::Strategy<Value = Op> {
+        use proptest::prelude::*;
+
+        let seq = || proptest::collection::vec(prop_oneof![Just('a'), Just('b'), Just('c')], 1..=6);
+        // removes twice as likely as either insert keeps the trie churning
+        prop_oneof![
+            2 => seq().prop_map(Op::Remove),
+            1 => seq().prop_map(Op::InsertOwned),
+            2 => seq().prop_map(Op::Insert),
+        ]
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(16))]
+
+        #[test]
+        fn interleaved_ops_small_alphabet(ops in proptest::collection::vec(op(), 0..10_000)) {
+            use std::collections::HashSet;
+
+            let mut trie = Trie::new();
+            let mut oracle = HashSet::new();
+            for (i, op) in ops.into_iter().enumerate() {
+                match op {
+                    Op::Remove(seq) => proptest::prop_assert_eq!(trie.remove(&seq), oracle.remove(&seq)),
+                    Op::InsertOwned(seq) => proptest::prop_assert_eq!(trie.insert_owned(seq.clone()), oracle.insert(seq)),
+                    Op::Insert(seq) => proptest::prop_assert_eq!(trie.insert(&seq), oracle.insert(seq)),
+                }
+                proptest::prop_assert_eq!(trie.sequence_count(), oracle.len());
+                proptest::prop_assert_eq!(trie.node_count(), trie.children.len());
+                if i % 500 == 0 {
+                    // one node per distinct prefix of the stored sequences
+                    let prefixes = oracle.iter()
+                        .flat_map(|seq| (1..=seq.len()).map(move |len| &seq[..len]))
+                        .collect::<HashSet<_>>();
+                    proptest::prop_assert_eq!(trie.node_count(), prefixes.len());
+                    assert_counts(&trie);
+                    assert_no_dangling(&trie);
+                }
+            }
+            let mut stored = trie.sequences();
+            let mut expected = oracle.into_iter().collect::<Vec<_>>();
+            stored.sort();
+            expected.sort();
+            proptest::prop_assert_eq!(stored, expected);
+            while trie.pop_sequence().is_some() {}
+            proptest::prop_assert_eq!(trie.node_count(), 0);
+            proptest::prop_assert!(trie.children.is_empty());
+        }
+    }
+
+    #[test]
+    fn match_depth() {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 't']);
+        trie.insert(&['c', 'a', 'r', 't']);
+        trie.insert(&['c', 'o', 'w']);
+
+        // whole query matches, stored or only a path
+        assert_eq!(trie.match_depth(&['c', 'a', 'r', 't']), 4);
+        assert_eq!(trie.match_depth(&['c', 'a', 'r']), 3);
+        // stops at the first element that isn't there
+        assert_eq!(trie.match_depth(&['c', 'o', 'o', 'w']), 2);
+        assert_eq!(trie.match_depth(&['c', 'u', 'b']), 1);
+        // longer than anything stored
+        assert_eq!(trie.match_depth(&['c', 'a', 't', 's', 'u', 'p']), 3);
+        assert_eq!(trie.match_depth(&['x', 'a', 't']), 0);
+        assert_eq!(trie.match_depth(&[]), 0);
+
+        trie.remove(&['c', 'a', 'r', 't']);
+        assert_eq!(trie.match_depth(&['c', 'a', 'r', 't']), 2);
+    }
+
+    #[test]
+    fn complete_remainders() {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a']);
+        trie.insert(&['c', 'a', 't']);
+        trie.insert(&['c', 'a', 't', 's']);
+        trie.insert(&['c', 'a', 'r', 't']);
+        trie.insert(&['c', 'o', 'w']);
+
+        // a stored prefix adds no empty remainder
+        assert_eq!(
+            trie.complete(&['c', 'a']),
+            vec![vec!['t'], vec!['t', 's'], vec!['r', 't']]
+        );
+        assert_eq!(trie.complete(&['c', 'a', 't']), vec![vec!['s']]);
+        assert_eq!(
+            trie.complete(&['c']),
+            vec![vec!['a'], vec!['a', 't'], vec!['a', 't', 's'], vec!['a', 'r', 't'], vec!['o', 'w']]
+        );
+        assert!(trie.complete(&['c', 'a', 't', 's']).is_empty());
+        assert!(trie.complete(&['d']).is_empty());
+        assert!(trie.complete(&[]).is_empty());
+
+        // the remainders are search's results without the prefix
+        let prefix = ['c', 'a'];
+        let searched = trie.search(&prefix).into_response_vec();
+        let completed = trie.complete(&prefix);
+        assert_eq!(searched.len(), completed.len() + 1);
+        assert!(searched[1..].iter().zip(completed.iter()).all(|(s, c)| s[2..] == c[..]));
+    }
+
+    #[test]
+    fn completions_by_next() {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'o', 'w']);
+        for w in ["cat", "car", "cart", "cab", "ca"].iter() {
+            trie.insert(&w.chars().collect::<Vec<_>>());
         }
-        trie.remove(&['c', 'o', 'w']);
-        trie.remove(&['c', 'a', 't']);
-        assert!(trie.is_empty());
+        trie.insert(&['c', 'u', 'b']);
+        trie.insert(&['d', 'o', 'g']);
+
+        // one child with many completions, ties keep insertion order
+        assert_eq!(trie.completions_by_next(&['c']), vec![('a', 5), ('o', 1), ('u', 1)]);
+        // "ca" is stored but counted under none of its children
+        assert_eq!(trie.completions_by_next(&['c', 'a']), vec![('r', 2), ('t', 1), ('b', 1)]);
+        assert_eq!(trie.completions_by_next(&[]), vec![('c', 7), ('d', 1)]);
+        assert!(trie.completions_by_next(&['c', 'o', 'w']).is_empty());
+        assert!(trie.completions_by_next(&['x']).is_empty());
+
+        trie.remove(&['c', 'a', 'r', 't']);
+        assert_eq!(trie.completions_by_next(&['c', 'a']), vec![('t', 1), ('r', 1), ('b', 1)]);
+    }
+
+    #[test]
+    fn search_within_depth() {
+        let mut trie = Trie::new();
+        for w in ["car", "cart", "carrot"].iter() {
+            trie.insert(&w.chars().collect::<Vec<_>>());
+        }
+        let within = |prefix: &str, max_extra| {
+            trie.search_within(&prefix.chars().collect::<Vec<_>>(), max_extra)
+                .as_collected()
+                .iter()
+                .map(|seq| seq.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(within("car", 0), vec!["car"]);
+        assert_eq!(within("car", 1), vec!["car", "cart"]);
+        assert_eq!(within("car", 2), vec!["car", "cart"]);
+        assert_eq!(within("car", 3), vec!["car", "cart", "carrot"]);
+        assert!(within("ca", 0).is_empty());
+        assert_eq!(within("ca", 1), vec!["car"]);
+        assert_eq!(within("ca", 2), vec!["car", "cart"]);
+        assert!(within("dog", 5).is_empty());
+
+        let prefix = ['c'];
+        assert_eq!(
+            trie.search_within(&prefix, usize::MAX).as_collected(),
+            trie.search(&prefix).as_collected()
+        );
+    }
+
+    #[test]
+    fn terminal_prefixes_of() {
+        let mut trie = Trie::new();
+        for w in ["a", "at", "ate", "cat", "at", "cats", "ca"].iter() {
+            trie.insert(&w.chars().collect::<Vec<_>>());
+        }
+        let prefixes = |input: &str| trie.terminal_prefixes_of(&input.chars().collect::<Vec<_>>());
+
+        assert_eq!(prefixes("ateliers"), vec![1, 2, 3]);
+        assert_eq!(prefixes("catskill"), vec![2, 3, 4]);
+        assert_eq!(prefixes("cab"), vec![2]);
+        assert_eq!(prefixes("c"), Vec::<usize>::new());
+        // the first element isn't a start
+        assert!(prefixes("tea").is_empty());
+        assert!(prefixes("").is_empty());
+    }
+
+    #[test]
+    fn segment_words() {
+        let chars = |w: &str| w.chars().collect::<Vec<_>>();
+        let split = |trie: &Trie<char>, input: &str| {
+            trie.segment(&chars(input)).map(|words| {
+                words.iter().map(|w| w.iter().collect::<String>()).collect::<Vec<_>>()
+            })
+        };
+
+        let trie = Trie::from(&["cat", "cow"][..]);
+        assert_eq!(split(&trie, "catcow"), Some(vec!["cat".to_string(), "cow".to_string()]));
+        assert_eq!(split(&trie, "catscow"), None);
+        assert_eq!(split(&trie, "catco"), None);
+        assert_eq!(split(&trie, ""), Some(vec![]));
+
+        // fewest words wins
+        let trie = Trie::from(&["a", "ab", "abc", "bc", "c"][..]);
+        assert_eq!(split(&trie, "abc"), Some(vec!["abc".to_string()]));
+        // equally few, the longest first word wins
+        let trie = Trie::from(&["ab", "cd", "abc", "d"][..]);
+        assert_eq!(split(&trie, "abcd"), Some(vec!["abc".to_string(), "d".to_string()]));
+        // the longest first word only if the rest still splits
+        let trie = Trie::from(&["ab", "abc", "cd"][..]);
+        assert_eq!(split(&trie, "abcd"), Some(vec!["ab".to_string(), "cd".to_string()]));
+
+        let trie = Trie::from(&["in", "put", "input", "s", "puts"][..]);
+        assert_eq!(split(&trie, "inputs"), Some(vec!["input".to_string(), "s".to_string()]));
+    }
+
+    #[test]
+    fn terminals_words() {
+        let mut trie = Trie::new();
+        for word in std::fs::read_to_string("words.txt").unwrap().split_whitespace() {
+            trie.insert(&word.chars().collect::<Vec<_>>());
+        }
+        trie.remove(&['a']);
+
+        let sequences = trie.sequences();
+        let mut terminals = trie.terminals();
+        let mut count = 0;
+        while let Some((path, last)) = terminals.next_terminal() {
+            assert!(trie.contains(path));
+            assert_eq!(path.last(), Some(last));
+            assert_eq!(path, &sequences[count][..]);
+            count += 1;
+        }
+        assert_eq!(count, trie.sequence_count());
+        assert!(terminals.next_terminal().is_none());
+        assert!(Trie::<char>::new().terminals().next_terminal().is_none());
+    }
+
+    #[test]
+    fn counts_by_start_words() {
+        use std::collections::HashSet;
+
+        let words = std::fs::read_to_string("words.txt").unwrap();
+        let unique = words.split_whitespace().collect::<HashSet<_>>();
+        let mut trie = Trie::new();
+        for word in words.split_whitespace() {
+            trie.insert(&word.chars().collect::<Vec<_>>());
+        }
+        let counts = trie.counts_by_start();
+        assert_eq!(counts.len(), trie.num_starts());
+        assert_eq!(counts.iter().map(|(_, n)| n).sum::<usize>(), trie.sequence_count());
+        for (start, n) in counts.iter() {
+            assert_eq!(*n, unique.iter().filter(|w| w.starts_with(*start)).count());
+        }
+
+        // removing every sequence under a start drops its entry
+        let (gone, _) = counts[0];
+        for word in unique.iter().filter(|w| w.starts_with(gone)) {
+            trie.remove(&word.chars().collect::<Vec<_>>());
+        }
+        let after = trie.counts_by_start();
+        assert_eq!(after, counts[1..].to_vec());
+    }
+
+    #[test]
+    fn stems_and_leaves() {
+        let mut trie = Trie::from(&["car", "cart", "carrot", "cow"][..]);
+        let words = |seqs: Vec<Vec<char>>| seqs.iter().map(|s| s.iter().collect()).collect::<Vec<String>>();
+
+        assert_eq!(words(trie.stems().collect()), vec!["car"]);
+        assert_eq!(words(trie.leaves().collect()), vec!["cart", "carrot", "cow"]);
+
+        // every stored sequence is exactly one of a stem or a leaf
+        let stems = trie.stems().count();
+        let leaves = trie.leaves().count();
+        assert_eq!(stems + leaves, trie.sequence_count());
+
+        trie.insert(&['c', 'a']);
+        trie.insert(&['c', 'a', 'r', 't', 's']);
+        assert_eq!(words(trie.stems().collect()), vec!["ca", "car", "cart"]);
+        assert_eq!(words(trie.leaves().collect()), vec!["carts", "carrot", "cow"]);
+
+        trie.remove(&['c', 'a', 'r', 't', 's']);
+        trie.remove(&['c', 'a', 'r', 'r', 'o', 't']);
+        assert_eq!(words(trie.stems().collect()), vec!["ca", "car"]);
+        assert!(Trie::<char>::new().stems().next().is_none());
+        assert!(Trie::<char>::new().leaves().next().is_none());
     }
 
     #[test]
@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+
+use crate::key::path_keys;
+use crate::Trie;
+
+/// The most nodes and sequences `Trie::try_insert` lets the trie hold, set by
+/// `TrieBuilder::node_budget` and `TrieBuilder::sequence_budget`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Budget {
+    pub(crate) nodes: Option<usize>,
+    pub(crate) sequences: Option<usize>,
+}
+
+/// Why `Trie::try_insert` didn't insert a sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertError {
+    EmptySequence,
+    /// Longer than `TrieBuilder::max_sequence_len`.
+    TooLong { max: usize, got: usize },
+    /// Inserting would go over `TrieBuilder::node_budget` or
+    /// `TrieBuilder::sequence_budget`.
+    CapacityExceeded,
+}
+
+impl fmt::Display for InsertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsertError::EmptySequence => write!(f, "can't insert an empty sequence"),
+            InsertError::TooLong { max, got } => {
+                write!(f, "sequence of {} elements is longer than the maximum {}", got, max)
+            }
+            InsertError::CapacityExceeded => write!(f, "inserting would exceed the trie's budget"),
+        }
+    }
+}
+
+impl Error for InsertError {}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Inserts `seq` returning `Ok(true)` if it wasn't already stored, or
+    /// leaves the trie as it was and returns why `seq` can't be inserted.
+    ///
+    /// Unlike `insert` a sequence longer than `TrieBuilder::max_sequence_len`
+    /// is an error whatever the policy, and the budgets of the builder are
+    /// checked before anything is changed. Inserting a sequence that is
+    /// already stored always succeeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::{InsertError, TrieBuilder};
+    /// let mut trie = TrieBuilder::new().node_budget(4).build();
+    ///
+    /// assert_eq!(trie.try_insert(&['c', 'a', 't']), Ok(true));
+    /// assert_eq!(trie.try_insert(&['c', 'o', 'w']), Err(InsertError::CapacityExceeded));
+    /// assert_eq!(trie.try_insert(&['c', 'a', 'r']), Ok(true));
+    /// assert_eq!(trie.try_insert(&[]), Err(InsertError::EmptySequence));
+    /// ```
+    pub fn try_insert(&mut self, seq: &[T]) -> Result<bool, InsertError> {
+        let res = self.try_insert_unobserved(seq);
+        self.notify_insert(seq, res == Ok(true));
+        res
+    }
+
+    fn try_insert_unobserved(&mut self, seq: &[T]) -> Result<bool, InsertError> {
+        let seq = &*self.normalize(seq);
+        if seq.is_empty() {
+            return Err(InsertError::EmptySequence);
+        }
+        if let Some((max, _)) = self.max_len.filter(|(max, _)| seq.len() > *max) {
+            return Err(InsertError::TooLong { max, got: seq.len() });
+        }
+
+        let keys = path_keys(seq).collect::<Vec<_>>();
+        if self.children.get(&keys[keys.len() - 1]).is_some_and(|node| node.is_terminal()) {
+            return Ok(false);
+        }
+        let new_nodes = keys.len() - keys.partition_point(|key| self.children.contains_key(key));
+        let over = |budget: Option<usize>, used: usize| budget.is_some_and(|max| used > max);
+        if over(self.budget.nodes, self.len + new_nodes) || over(self.budget.sequences, self.seq_len + 1) {
+            return Err(InsertError::CapacityExceeded);
+        }
+
+        let inserted = self.insert_keys(seq, &keys);
+        debug_assert_invariants!(self, &keys);
+        Ok(inserted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{LengthPolicy, TrieBuilder};
+
+    fn chars(w: &str) -> Vec<char> {
+        w.chars().collect()
+    }
+
+    #[test]
+    fn try_insert_errors() {
+        let mut trie = TrieBuilder::new()
+            .max_sequence_len(4, LengthPolicy::Truncate)
+            .sequence_budget(2)
+            .build();
+        assert_eq!(trie.try_insert(&[]), Err(InsertError::EmptySequence));
+        assert_eq!(trie.try_insert(&chars("carts")), Err(InsertError::TooLong { max: 4, got: 5 }));
+        assert!(trie.is_empty());
+        assert_eq!(trie.limited_count(), 0);
+
+        assert_eq!(trie.try_insert(&chars("cart")), Ok(true));
+        assert_eq!(trie.try_insert(&chars("car")), Ok(true));
+        assert_eq!(trie.try_insert(&chars("cow")), Err(InsertError::CapacityExceeded));
+        // already stored is fine when full
+        assert_eq!(trie.try_insert(&chars("car")), Ok(false));
+
+        // `insert` ignores the budget and truncates
+        assert!(trie.insert(&chars("cowboy")));
+        assert_eq!(trie.sequences(), vec![chars("car"), chars("cart"), chars("cowb")]);
+        assert_eq!(InsertError::TooLong { max: 4, got: 5 }.to_string(), "sequence of 5 elements is longer than the maximum 4");
+    }
+
+    #[test]
+    fn try_insert_leaves_nothing_behind() {
+        let mut trie = TrieBuilder::new().node_budget(6).suffix_index(true).build();
+        assert_eq!(trie.try_insert(&chars("cart")), Ok(true));
+        let before = trie.clone();
+        let nodes = trie.node_count();
+
+        // "cab" fits, "cabin" shares "ca" but needs 3 more nodes than are left
+        assert_eq!(trie.try_insert(&chars("cabin")), Err(InsertError::CapacityExceeded));
+        assert_eq!(trie.node_count(), nodes);
+        assert!(!trie.contains(&chars("cab")));
+        assert_eq!(trie, before);
+        assert!(!trie.ends_with(&chars("in")));
+        assert_eq!(trie.check_invariants(), Ok(()));
+
+        assert_eq!(trie.try_insert(&chars("cab")), Ok(true));
+        assert_eq!(trie.node_count(), 5);
+        assert_eq!(trie.try_insert(&chars("ca")), Ok(true));
+        assert_eq!(trie.try_insert(&chars("cob")), Err(InsertError::CapacityExceeded));
+        assert_eq!(trie.try_insert(&chars("co")), Ok(true));
+        assert_eq!(trie.node_count(), 6);
+    }
+
+    #[test]
+    fn try_insert_unlimited() {
+        let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        assert_eq!(trie.try_insert(&chars("CAT")), Ok(true));
+        assert_eq!(trie.try_insert(&chars("cat")), Ok(false));
+        assert_eq!(trie.try_insert(&chars(&"x".repeat(1000))), Ok(true));
+        assert_eq!(trie.sequence_count(), 2);
+    }
+}
@@ -0,0 +1,418 @@
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{self, BufWriter, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{CompiledTrie, DecodeError, Found};
+
+const MAGIC: &[u8; 8] = b"FTRIMAP\0";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 32;
+const NODE_LEN: usize = 16;
+const TERMINAL: u32 = 0b1;
+
+/// An element a `MappedTrie` can hold, stored as a little endian `u32`.
+pub trait MappedElement: Copy + Eq + Hash + Debug {
+    /// Written to the header so a file isn't read as the wrong element type.
+    const KIND: u32;
+    fn to_bits(self) -> u32;
+    /// `None` if `bits` isn't a valid element.
+    fn from_bits(bits: u32) -> Option<Self>;
+}
+
+macro_rules! mapped_int {
+    ($($int:ty => $kind:expr),*) => {$(
+        impl MappedElement for $int {
+            const KIND: u32 = $kind;
+
+            fn to_bits(self) -> u32 {
+                self as u32
+            }
+
+            fn from_bits(bits: u32) -> Option<Self> {
+                <$int>::try_from(bits).ok()
+            }
+        }
+    )*};
+}
+
+mapped_int!(u8 => 1, u16 => 2, u32 => 3);
+
+impl MappedElement for char {
+    const KIND: u32 = 4;
+
+    fn to_bits(self) -> u32 {
+        self as u32
+    }
+
+    fn from_bits(bits: u32) -> Option<Self> {
+        std::char::from_u32(bits)
+    }
+}
+
+/// A `CompiledTrie` written by `CompiledTrie::write_to` and answered straight
+/// from the memory mapped file, nothing is deserialized.
+///
+/// The file is a 32 byte header followed by the compiled nodes, 16 bytes each,
+/// in the same order as in memory. Every field is read as little endian bytes
+/// so the mapping needs no particular alignment. The compiled trie's
+/// normalizer isn't written, queries must already be normalized.
+#[derive(Debug)]
+pub struct MappedTrie<T> {
+    map: Mmap,
+    starts: usize,
+    nodes: usize,
+    _elements: PhantomData<T>,
+}
+
+/// One node read out of the mapping.
+struct MappedNode<T> {
+    val: T,
+    terminal: bool,
+    children: Range<usize>,
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(buf)
+}
+
+impl<T: MappedElement> MappedTrie<T> {
+    /// Checks the header and that every node's element and children are
+    /// valid, once, so lookups can't read past the file or find a bad element.
+    ///
+    /// `compile` gives every node its children right after the children of
+    /// the nodes before it, so the spans are checked to follow one another
+    /// from the end of the starts and to cover every other node. Each node
+    /// then has a single parent and a file can't make a lookup visit a node
+    /// twice.
+    fn new(map: Mmap) -> Result<Self, DecodeError> {
+        if map.len() < HEADER_LEN {
+            return Err(if map.starts_with(&MAGIC[..map.len().min(MAGIC.len())]) {
+                DecodeError::Truncated
+            } else {
+                DecodeError::BadMagic
+            });
+        }
+        if &map[..8] != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        let version = read_u32(&map, 8);
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(u8::try_from(version).unwrap_or(u8::MAX)));
+        }
+        if read_u32(&map, 12) != T::KIND {
+            return Err(DecodeError::Corrupt("written with another element type"));
+        }
+        let starts = read_u32(&map, 16) as usize;
+        let mut nodes = [0; 8];
+        nodes.copy_from_slice(&map[24..32]);
+        let nodes = usize::try_from(u64::from_le_bytes(nodes))
+            .map_err(|_| DecodeError::Corrupt("too many nodes"))?;
+
+        let expected = nodes.checked_mul(NODE_LEN).and_then(|len| len.checked_add(HEADER_LEN));
+        match expected {
+            Some(len) if len > map.len() => return Err(DecodeError::Truncated),
+            Some(len) if len == map.len() => {}
+            _ => return Err(DecodeError::Corrupt("length doesn't match the header")),
+        }
+        if starts > nodes {
+            return Err(DecodeError::Corrupt("more starts than nodes"));
+        }
+
+        let trie = Self { map, starts, nodes, _elements: PhantomData };
+        let mut next = starts as u64;
+        for idx in 0..nodes {
+            let at = HEADER_LEN + idx * NODE_LEN;
+            if T::from_bits(read_u32(&trie.map, at)).is_none() {
+                return Err(DecodeError::Corrupt("invalid element"));
+            }
+            let start = read_u32(&trie.map, at + 8) as u64;
+            let len = read_u32(&trie.map, at + 12) as u64;
+            if start + len > nodes as u64 || (len > 0 && start <= idx as u64) {
+                return Err(DecodeError::Corrupt("children out of bounds"));
+            }
+            if len > 0 {
+                if start != next {
+                    return Err(DecodeError::Corrupt("children overlap or leave a gap"));
+                }
+                next += len;
+            }
+        }
+        if next != nodes as u64 {
+            return Err(DecodeError::Corrupt("nodes that are no one's child"));
+        }
+        Ok(trie)
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes == 0
+    }
+
+    fn node(&self, idx: usize) -> MappedNode<T> {
+        let at = HEADER_LEN + idx * NODE_LEN;
+        let start = read_u32(&self.map, at + 8) as usize;
+        MappedNode {
+            val: T::from_bits(read_u32(&self.map, at)).expect("elements are checked on open"),
+            terminal: read_u32(&self.map, at + 4) & TERMINAL != 0,
+            children: start..start + read_u32(&self.map, at + 12) as usize,
+        }
+    }
+
+    fn child(&self, mut span: Range<usize>, val: T) -> Option<usize> {
+        span.find(|idx| self.node(*idx).val == val)
+    }
+
+    /// The index of the node at the end of `seq`, `None` for an empty or
+    /// missing path.
+    fn find(&self, seq: &[T]) -> Option<usize> {
+        let (first, rest) = seq.split_first()?;
+        let mut idx = self.child(0..self.starts, *first)?;
+        for val in rest {
+            idx = self.child(self.node(idx).children, *val)?;
+        }
+        Some(idx)
+    }
+
+    /// Returns `true` if the path `seq` exists, like `CompiledTrie::contains`.
+    pub fn contains(&self, seq: &[T]) -> bool {
+        self.find(seq).is_some()
+    }
+
+    /// The length of the longest stored sequence that is a prefix of `seq`,
+    /// like `CompiledTrie::longest_prefix`.
+    pub fn longest_prefix(&self, seq: &[T]) -> Option<usize> {
+        let mut span = 0..self.starts;
+        let mut longest = None;
+        for (i, val) in seq.iter().enumerate() {
+            let node = match self.child(span, *val) {
+                Some(idx) => self.node(idx),
+                None => break,
+            };
+            if node.terminal {
+                longest = Some(i + 1);
+            }
+            span = node.children;
+        }
+        longest
+    }
+
+    /// Returns all of the stored sequences starting with `prefix`, like
+    /// `CompiledTrie::search`.
+    pub fn search(&self, prefix: &[T]) -> Found<T> {
+        let mut found = Found::new();
+        found.extend(prefix.iter().copied());
+        if let Some(idx) = self.find(prefix) {
+            self._search(idx, &mut found);
+        }
+        found
+    }
+
+    /// Walks below `idx` with an explicit stack, a file may hold a sequence
+    /// too long to recurse down. `None` rolls back the element of a node once
+    /// its children are done.
+    fn _search(&self, idx: usize, found: &mut Found<T>) {
+        let mut stack = vec![Some(idx)];
+        while let Some(entry) = stack.pop() {
+            let at = match entry {
+                Some(at) => at,
+                None => {
+                    found.branch_split();
+                    continue;
+                }
+            };
+            let node = self.node(at);
+            if at != idx {
+                found.push_val(node.val);
+                stack.push(None);
+            }
+            if node.terminal {
+                found.branch_end();
+            }
+            stack.extend(node.children.rev().map(Some));
+        }
+    }
+}
+
+impl<T> CompiledTrie<T>
+where
+    T: MappedElement,
+{
+    /// Writes the compiled nodes to `path` for `open_mmap`, the normalizer
+    /// isn't written.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&T::KIND.to_le_bytes())?;
+        w.write_all(&(self.start_map.len() as u32).to_le_bytes())?;
+        w.write_all(&0u32.to_le_bytes())?;
+        w.write_all(&(self.nodes.len() as u64).to_le_bytes())?;
+        for node in self.nodes.iter() {
+            let flags = if node.terminal { TERMINAL } else { 0 };
+            for field in [node.val.to_bits(), flags, node.start, node.len].iter() {
+                w.write_all(&field.to_le_bytes())?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Maps a file written by `write_to` without reading it into memory. The
+    /// header and every node are checked once, lookups then read the mapping
+    /// directly.
+    ///
+    /// The file must not be changed while it is mapped, the lookups would see
+    /// the changes and a shorter file makes them fault.
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<MappedTrie<T>, DecodeError> {
+        let file = File::open(path)?;
+        // safe as long as nothing changes the file while it is mapped, see above
+        let map = unsafe { Mmap::map(&file)? };
+        MappedTrie::new(map)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::Trie;
+
+    /// A file in the temp dir removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("ecs-trie-{}-{}", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn words(path: &str) -> Vec<Vec<char>> {
+        fs::read_to_string(path)
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect())
+            .collect()
+    }
+
+    #[test]
+    fn mapped_matches_compiled() {
+        let words = words("data/1984.txt");
+        let trie = words.iter().fold(Trie::new(), |mut trie, w| {
+            trie.insert(w);
+            trie
+        });
+        let compiled = trie.compile();
+        let file = TempFile::new("1984");
+        compiled.write_to(&file.0).unwrap();
+        let mapped = CompiledTrie::<char>::open_mmap(&file.0).unwrap();
+        assert_eq!(mapped.len(), compiled.len());
+
+        for (i, w) in words.iter().step_by(words.len() / 1000).enumerate() {
+            let miss = [&w[..], &['#']].concat();
+            assert!(mapped.contains(w));
+            assert!(!mapped.contains(&miss));
+            assert_eq!(mapped.longest_prefix(&miss), compiled.longest_prefix(&miss));
+            if i % 10 == 0 {
+                let prefix = &w[..w.len().min(2)];
+                assert_eq!(mapped.search(prefix).as_collected(), compiled.search(prefix).as_collected());
+            }
+        }
+        assert!(!mapped.contains(&[]));
+        assert_eq!(mapped.longest_prefix(&[]), None);
+    }
+
+    #[test]
+    fn mapped_bytes() {
+        let mut trie = Trie::new();
+        trie.insert(&[1u8, 2, 3]);
+        trie.insert(&[1u8, 255]);
+        let file = TempFile::new("bytes");
+        trie.compile().write_to(&file.0).unwrap();
+
+        let mapped = CompiledTrie::<u8>::open_mmap(&file.0).unwrap();
+        assert_eq!(mapped.search(&[1]).as_collected(), vec![&[1, 2, 3][..], &[1, 255]]);
+        assert!(Trie::<u8>::new().compile().write_to(&file.0).is_ok());
+        assert!(CompiledTrie::<u8>::open_mmap(&file.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn mapped_long_sequence() {
+        // deeper than recursion would manage on a test thread's stack
+        let mut trie = Trie::new();
+        trie.insert(&vec![7u8; 100_000]);
+        trie.insert(&[7u8, 8]);
+        let file = TempFile::new("long");
+        trie.compile().write_to(&file.0).unwrap();
+
+        let mapped = CompiledTrie::<u8>::open_mmap(&file.0).unwrap();
+        let found = mapped.search(&[7]);
+        assert_eq!(found.as_collected().iter().map(|seq| seq.len()).collect::<Vec<_>>(), vec![100_000, 2]);
+        assert_eq!(mapped.search(&[7, 8]).exact(), Some(&[7u8, 8][..]));
+    }
+
+    #[test]
+    fn mapped_bad_files() {
+        let mut trie = Trie::new();
+        trie.insert(&['c', 'a', 't']);
+        trie.insert(&['c', 'o', 'w']);
+        let file = TempFile::new("bad");
+        trie.compile().write_to(&file.0).unwrap();
+        let bytes = fs::read(&file.0).unwrap();
+
+        let open = |bytes: &[u8]| {
+            fs::write(&file.0, bytes).unwrap();
+            CompiledTrie::<char>::open_mmap(&file.0)
+        };
+        assert!(matches!(open(&bytes[..bytes.len() - 1]), Err(DecodeError::Truncated)));
+        assert!(matches!(open(&bytes[..20]), Err(DecodeError::Truncated)));
+        assert!(matches!(open(&[bytes.clone(), vec![0]].concat()), Err(DecodeError::Corrupt(_))));
+        assert!(matches!(open(b"not a trie at all, no it isn't.."), Err(DecodeError::BadMagic)));
+        assert!(matches!(CompiledTrie::<u16>::open_mmap(&file.0), Err(DecodeError::BadMagic)));
+
+        let mut other = bytes.clone();
+        other[8] = 2;
+        assert!(matches!(open(&other), Err(DecodeError::UnsupportedVersion(2))));
+        fs::write(&file.0, &bytes).unwrap();
+        assert!(matches!(CompiledTrie::<u16>::open_mmap(&file.0), Err(DecodeError::Corrupt(_))));
+
+        // a child span past the end of the nodes
+        let mut other = bytes.clone();
+        other[HEADER_LEN + 12] = 200;
+        assert!(matches!(open(&other), Err(DecodeError::Corrupt(_))));
+        // "o" sharing the children of "a", a node with two parents
+        let o = HEADER_LEN + 2 * NODE_LEN;
+        let mut other = bytes.clone();
+        other[o + 8] = 3;
+        assert!(matches!(open(&other), Err(DecodeError::Corrupt("children overlap or leave a gap"))));
+        // "a" and "o" both childless leave "t" and "w" without a parent
+        let mut other = bytes.clone();
+        other[HEADER_LEN + NODE_LEN + 12] = 0;
+        other[o + 12] = 0;
+        assert!(matches!(open(&other), Err(DecodeError::Corrupt("nodes that are no one's child"))));
+        // an invalid char
+        let mut other = bytes;
+        other[HEADER_LEN + 3] = 0xff;
+        assert!(matches!(open(&other), Err(DecodeError::Corrupt(_))));
+        assert!(matches!(CompiledTrie::<char>::open_mmap("/no/such/trie"), Err(DecodeError::Io(_))));
+    }
+}
@@ -1,43 +1,68 @@
-// use std::collections::{HashMap, VecDeque};
-use std::hash::Hash;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hash};
 use std::fmt::Debug;
-use crate::{make_key, Trie, PreHashedMap};
+use crate::{NoopBuildHasher, Trie};
 
-#[derive(Debug, Clone, Eq)]
-pub struct Node<T> {
-    pub(crate) key: u64,
+/// A node of a trie, `children` holds whatever its storage finds each child
+/// by, the key of the child's path for `Trie`.
+#[derive(Debug, Eq)]
+pub struct Node<T, K = u64> {
     pub(crate) val: T,
-    pub(crate) children: Vec<u64>,
+    pub(crate) children: Vec<K>,
     pub(crate) child_size: usize,
     pub(crate) terminal: bool,
+    /// Number of terminal nodes in this subtree, including this node.
+    pub(crate) descendant_terminals: usize,
+    /// Weight of the sequence ending here, see `Trie::insert_weighted`.
+    pub(crate) weight: u32,
 }
 
-impl<T: PartialEq> PartialEq for Node<T> {
+impl<T: Clone, K: Clone> Clone for Node<T, K> {
+    fn clone(&self) -> Self {
+        Self {
+            val: self.val.clone(),
+            children: self.children.clone(),
+            child_size: self.child_size,
+            terminal: self.terminal,
+            descendant_terminals: self.descendant_terminals,
+            weight: self.weight,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.val.clone_from(&source.val);
+        self.children.clone_from(&source.children);
+        self.child_size = source.child_size;
+        self.terminal = source.terminal;
+        self.descendant_terminals = source.descendant_terminals;
+        self.weight = source.weight;
+    }
+}
+
+impl<T: PartialEq, K> PartialEq for Node<T, K> {
     fn eq(&self, other: &Self) -> bool {
         self.val == other.val
     }
 }
 
-impl<T> Node<T> 
-where
-    T: Eq + Hash + Clone + Debug,
-{
-    pub(crate) fn new(val: T, seq: &[T], idx: usize, terminal: bool) -> Node<T> {
-        let key = make_key((&seq[..idx], &seq[idx]));
-        let i = idx + 1;
-        let mut children = Vec::new();
-        if let Some(ele) = seq.get(i) {
-            children.push(make_key((&seq[..i], ele)));
-        }
+impl<T, K> Node<T, K> {
+    /// A node with no children that doesn't end a sequence yet.
+    pub(crate) fn new(val: T) -> Self {
         Self {
-            key,
             val,
-            children,
+            children: Vec::new(),
             child_size: 0,
-            terminal,
+            terminal: false,
+            descendant_terminals: 0,
+            weight: 0,
         }
     }
+}
 
+impl<T> Node<T> 
+where
+    T: Eq + Hash + Clone + Debug,
+{
     pub(crate) fn as_value(&self) -> &T {
         &self.val
     }
@@ -57,73 +82,178 @@ where
     pub(crate) fn remove_child(&mut self, key: &u64) -> bool {
         if let Some(idx) = self.children.iter().position(|c| c == key) {
             self.children.remove(idx);
-            if self.child_len() > 0 { self.child_size -= 1 };
+            self.child_size -= 1;
+            // a node that lost most of its children gives the room back
+            if self.children.capacity() >= 4 * self.children.len() {
+                self.children.shrink_to_fit();
+            }
             true
         } else {
             false
         }
     }
 
-    pub(crate) fn children<'a, 'b: 'a>(&'b self, map: &'b PreHashedMap<u64, Node<T>>) -> Vec<&Node<T>> {
+    pub(crate) fn children<'a, 'b: 'a, S: BuildHasher>(&'b self, map: &'b HashMap<u64, Node<T>, S>) -> Vec<&'b Node<T>> {
         self.children.iter().map(|key| map.get(key).unwrap()).collect()
     }
 
-    pub(crate) fn update_children(&mut self, seq: &[T], idx: usize) {
-        let i = idx + 1;
-        if let Some(ele) = seq.get(i) {
-            let key = make_key((&seq[..i], ele));
-            if !self.children.contains(&key) {
-                self.child_size += 1;
-                self.children.push(key);
-            }
+    /// Links the child whose key is `key` unless it already is.
+    pub(crate) fn add_child(&mut self, key: u64) {
+        if !self.children.contains(&key) {
+            self.child_size += 1;
+            self.children.push(key);
         }
     }
-    /// Depth first iteration of a node and its children.
-    pub(crate) fn walk<'a>(&'a self, trie: &'a Trie<T>) -> NodeIter<'a, T> {
+
+    /// Depth first iteration of the node's descendants, not the node itself,
+    /// children in insertion order.
+    pub(crate) fn walk<'a, S>(&'a self, trie: &'a Trie<T, S>) -> NodeIter<'a, T, S> {
         NodeIter {
             map: &trie.children,
-            current: self,
-            next: None,
-            all_kids: Vec::new(),
+            queue: self.children.iter().copied().collect(),
         }
     }
 }
 
-pub(crate) struct NodeIter<'a, T> {
-    map: &'a PreHashedMap<u64, Node<T>>,
-    current: &'a Node<T>,
-    next: Option<&'a Node<T>>,
-    // TODO try using VecDeque
-    all_kids: Vec<u64>,
+/// Depth first walk from every start yielding each node with its depth, a
+/// node always comes before its descendants and children in insertion order.
+#[derive(Debug, Clone)]
+pub(crate) struct DepthFirst<'a, T, S = NoopBuildHasher> {
+    map: &'a HashMap<u64, Node<T>, S>,
+    stack: Vec<(usize, &'a Node<T>)>,
 }
-impl<'a, T> Iterator for NodeIter<'a, T> {
-    type Item = &'a Node<T>;
+
+impl<'a, T, S: BuildHasher> DepthFirst<'a, T, S> {
+    pub(crate) fn new(trie: &'a Trie<T, S>) -> Self {
+        let stack = trie.starts.iter()
+            .rev()
+            .filter_map(|key| trie.children.get(key))
+            .map(|node| (1, node))
+            .collect();
+        Self { map: &trie.children, stack }
+    }
+}
+
+impl<'a, T, S: BuildHasher> Iterator for DepthFirst<'a, T, S> {
+    type Item = (usize, &'a Node<T>);
     fn next(&mut self) -> Option<Self::Item> {
-        // return first child
-        if self.next.is_none() {
-            self.all_kids.extend(self.current.children.iter().cloned());
-
-            if !self.all_kids.is_empty() {
-                let key = self.all_kids.remove(0);
-                let next = self.map.get(&key);
-                self.next = next;
-                self.next
-            } else {
-                None
-            }
-            
-        // iterate depth first through children
-        } else {
-            // next is always Some
-            self.current = self.next.unwrap();
-            // all kids will be empty for the end case
-            self.all_kids.splice(0..0, self.current.children.iter().rev().copied());
+        let (depth, node) = self.stack.pop()?;
+        let map = self.map;
+        self.stack.extend(node.children.iter().rev().map(|key| (depth + 1, &map[key])));
+        Some((depth, node))
+    }
+}
 
-            if self.all_kids.is_empty() { return None };
+/// Depth first walk below a prefix returned by `Trie::walk_subtree`, yields
+/// `(path, value, is_terminal)` for every descendant where path is the full
+/// sequence ending in value.
+#[derive(Debug, Clone)]
+pub struct SubtreeIter<'a, T, S = NoopBuildHasher> {
+    map: &'a HashMap<u64, Node<T>, S>,
+    stack: Vec<(usize, &'a Node<T>)>,
+    path: Vec<T>,
+}
+
+impl<'a, T, S> SubtreeIter<'a, T, S> {
+    /// Walks `nodes` and their descendants, `path` leads to each of `nodes`.
+    pub(crate) fn new(map: &'a HashMap<u64, Node<T>, S>, nodes: Vec<&'a Node<T>>, path: Vec<T>) -> Self {
+        let depth = path.len() + 1;
+        let stack = nodes.into_iter().rev().map(|node| (depth, node)).collect();
+        Self { map, stack, path }
+    }
+}
+
+impl<'a, T: Clone, S: BuildHasher> Iterator for SubtreeIter<'a, T, S> {
+    type Item = (Vec<T>, &'a T, bool);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, node) = self.stack.pop()?;
+        let map = self.map;
+        self.stack.extend(node.children.iter().rev().map(|key| (depth + 1, &map[key])));
+        self.path.truncate(depth - 1);
+        self.path.push(node.val.clone());
+        Some((self.path.clone(), &node.val, node.terminal))
+    }
+}
 
-            let key = self.all_kids.remove(0);
-            self.next = self.map.get(&key);
-            self.next
+/// Stored sequences in traversal order returned by `Trie::terminals`, each
+/// with the value of its last node.
+///
+/// The path borrows a buffer the walk reuses for every sequence so nothing is
+/// copied, which is also why this is not an `Iterator`, each path must be
+/// dropped before asking for the next.
+#[derive(Debug, Clone)]
+pub struct Terminals<'a, T, S = NoopBuildHasher> {
+    nodes: DepthFirst<'a, T, S>,
+    path: Vec<T>,
+}
+
+impl<'a, T: Clone, S: BuildHasher> Terminals<'a, T, S> {
+    pub(crate) fn new(trie: &'a Trie<T, S>) -> Self {
+        Self { nodes: DepthFirst::new(trie), path: Vec::new() }
+    }
+
+    /// The next stored sequence and its last value, `None` once the walk is
+    /// done.
+    pub fn next_terminal(&mut self) -> Option<(&[T], &'a T)> {
+        loop {
+            let (depth, node) = self.nodes.next()?;
+            self.path.truncate(depth - 1);
+            self.path.push(node.val.clone());
+            if node.terminal {
+                return Some((&self.path, &node.val));
+            }
         }
     }
 }
+
+pub(crate) struct NodeIter<'a, T, S = NoopBuildHasher> {
+    map: &'a HashMap<u64, Node<T>, S>,
+    /// Keys still to be visited, the front is next.
+    queue: VecDeque<u64>,
+}
+impl<'a, T, S: BuildHasher> Iterator for NodeIter<'a, T, S> {
+    type Item = &'a Node<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        // the walk only ends once nothing is queued, a leaf just means the
+        // next node is a sibling or an ancestor's sibling
+        let key = self.queue.pop_front()?;
+        let node = &self.map[&key];
+        for child in node.children.iter().rev() {
+            self.queue.push_front(*child);
+        }
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Trie;
+    use crate::key::sequence_key;
+
+    fn walk(trie: &Trie<char>, seq: &[char]) -> String {
+        trie.children[&sequence_key(seq)].walk(trie).map(|n| n.val).collect()
+    }
+
+    #[test]
+    fn walk_past_early_leaf() {
+        // 'a' is a leaf before its sibling 'o' is walked
+        let trie = ["ca", "cow", "cob", "cart"].iter().copied().collect::<Trie<char>>();
+        assert_eq!(walk(&trie, &['c']), "artowb");
+        assert_eq!(walk(&trie, &['c', 'o']), "wb");
+        assert_eq!(walk(&trie, &['c', 'o', 'w']), "");
+
+        let trie = ["ab", "ac", "adef", "ag"].iter().copied().collect::<Trie<char>>();
+        assert_eq!(walk(&trie, &['a']), "bcdefg");
+    }
+
+    #[test]
+    fn walk_matches_depth_first() {
+        let text = std::fs::read_to_string("data/sun-rising.txt").unwrap();
+        let trie = text.split_whitespace().collect::<Trie<char>>();
+        let walked = trie.starts.iter()
+            .map(|key| &trie.children[key])
+            .map(|node| 1 + node.walk(&trie).count())
+            .sum::<usize>();
+        assert_eq!(walked, trie.node_count());
+    }
+}
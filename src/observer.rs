@@ -0,0 +1,229 @@
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::Trie;
+
+/// Told about every sequence a trie stores or drops, see `Trie::set_observer`.
+/// Each call comes after the change is complete. An insert or remove reports
+/// the sequence as the caller passed it, a sequence dropped or stored by the
+/// trie itself is reported as it was stored.
+pub trait TrieObserver<T>: Send + Sync {
+    /// `newly_added` is `false` if `seq` was already stored or wasn't inserted.
+    fn on_insert(&mut self, seq: &[T], newly_added: bool);
+    /// `removed` is `false` if `seq` wasn't stored.
+    fn on_remove(&mut self, seq: &[T], removed: bool);
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Calls `observer` once for every insert or remove, including each op of
+    /// a successful `apply_batch`. A sequence evicted by `with_max_sequences`
+    /// is reported as removed right after the insert that evicted it, `clear`
+    /// reports every stored sequence as removed and `prune_below_depth` each
+    /// sequence it cuts off or stores.
+    ///
+    /// The observer isn't cloned with the trie, a clone has none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::{Trie, TrieObserver};
+    ///
+    /// struct Count(usize);
+    /// impl TrieObserver<char> for Count {
+    ///     fn on_insert(&mut self, _: &[char], added: bool) {
+    ///         self.0 += added as usize;
+    ///     }
+    ///     fn on_remove(&mut self, _: &[char], _: bool) {}
+    /// }
+    ///
+    /// let mut trie = Trie::new();
+    /// trie.set_observer(Box::new(Count(0)));
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'a', 't']);
+    /// ```
+    pub fn set_observer(&mut self, observer: Box<dyn TrieObserver<T>>) {
+        self.observer = Some(observer);
+    }
+
+    /// Removes and returns the observer.
+    pub fn take_observer(&mut self) -> Option<Box<dyn TrieObserver<T>>> {
+        self.observer.take()
+    }
+
+    /// Also reports the sequences the insert evicted.
+    pub(crate) fn notify_insert(&mut self, seq: &[T], newly_added: bool) {
+        let evicted = self.take_evicted();
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_insert(seq, newly_added);
+            for seq in evicted.iter() {
+                observer.on_remove(seq, true);
+            }
+        }
+    }
+
+    pub(crate) fn notify_remove(&mut self, seq: &[T], removed: bool) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_remove(seq, removed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{BatchError, InsertError, Op, TrieBuilder};
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Call {
+        Insert(String, bool),
+        Remove(String, bool),
+    }
+
+    /// Records every call where the test can still see them.
+    struct Recorder(Arc<Mutex<Vec<Call>>>);
+
+    impl TrieObserver<char> for Recorder {
+        fn on_insert(&mut self, seq: &[char], newly_added: bool) {
+            self.0.lock().unwrap().push(Call::Insert(seq.iter().collect(), newly_added));
+        }
+
+        fn on_remove(&mut self, seq: &[char], removed: bool) {
+            self.0.lock().unwrap().push(Call::Remove(seq.iter().collect(), removed));
+        }
+    }
+
+    fn observed(trie: &mut Trie<char>) -> Arc<Mutex<Vec<Call>>> {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        trie.set_observer(Box::new(Recorder(Arc::clone(&calls))));
+        calls
+    }
+
+    fn chars(w: &str) -> Vec<char> {
+        w.chars().collect()
+    }
+
+    #[test]
+    fn observer_call_sequence() {
+        let mut trie = Trie::new();
+        let calls = observed(&mut trie);
+
+        trie.insert(&chars("cat"));
+        trie.insert(&chars("cat"));
+        trie.insert_str("cow");
+        trie.insert_owned(chars("car"));
+        trie.insert_weighted(&chars("cart"), 3);
+        assert!(!trie.remove(&chars("dog")));
+        assert!(trie.remove(&chars("cat")));
+        trie.remove_explain(&chars("cat"));
+        assert_eq!(trie.pop_sequence(), Some(chars("car")));
+        assert_eq!(trie.try_insert(&[]), Err(InsertError::EmptySequence));
+
+        use Call::*;
+        assert_eq!(*calls.lock().unwrap(), vec![
+            Insert("cat".into(), true),
+            Insert("cat".into(), false),
+            Insert("cow".into(), true),
+            Insert("car".into(), true),
+            Insert("cart".into(), true),
+            Remove("dog".into(), false),
+            Remove("cat".into(), true),
+            Remove("cat".into(), false),
+            Remove("car".into(), true),
+            Insert("".into(), false),
+        ]);
+
+        // nothing is reported once it's taken
+        assert!(trie.take_observer().is_some());
+        trie.insert(&chars("dog"));
+        assert_eq!(calls.lock().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn observer_sees_only_applied_batches() {
+        let mut trie = Trie::new();
+        trie.insert(&chars("cat"));
+        let calls = observed(&mut trie);
+
+        let failing = [Op::Insert(chars("cow")), Op::Remove(chars("dog"))];
+        assert_eq!(trie.apply_batch(&failing), Err(BatchError::NotStored { index: 1 }));
+        assert!(calls.lock().unwrap().is_empty());
+
+        let ops = [Op::Insert(chars("cow")), Op::Insert(chars("cat")), Op::Remove(chars("cat"))];
+        assert!(trie.apply_batch(&ops).is_ok());
+        assert_eq!(*calls.lock().unwrap(), vec![
+            Call::Insert("cow".into(), true),
+            Call::Insert("cat".into(), false),
+            Call::Remove("cat".into(), true),
+        ]);
+    }
+
+    #[test]
+    fn observer_with_limits_and_eviction() {
+        let mut trie = Trie::with_max_sequences(1);
+        let calls = observed(&mut trie);
+        trie.insert(&chars("cat"));
+        trie.insert_owned(chars("cow"));
+        assert_eq!(*calls.lock().unwrap(), vec![
+            Call::Insert("cat".into(), true),
+            Call::Insert("cow".into(), true),
+            Call::Remove("cat".into(), true),
+        ]);
+
+        // a batch applied to a copy keeps the observer, a failed one too
+        calls.lock().unwrap().clear();
+        assert!(trie.apply_batch(&[Op::Insert(chars("dog")), Op::Insert(chars("emu"))]).is_ok());
+        assert_eq!(*calls.lock().unwrap(), vec![
+            Call::Insert("dog".into(), true),
+            Call::Remove("cow".into(), true),
+            Call::Insert("emu".into(), true),
+            Call::Remove("dog".into(), true),
+        ]);
+        let failing = [Op::Insert(chars("fox")), Op::Remove(chars("cat"))];
+        assert!(trie.apply_batch(&failing).is_err());
+        assert_eq!(calls.lock().unwrap().len(), 4);
+        assert!(trie.take_observer().is_some());
+        assert!(trie.clone().take_observer().is_none());
+
+        let mut trie = TrieBuilder::new().node_budget(3).build();
+        let calls = observed(&mut trie);
+        assert!(trie.try_insert(&chars("cats")).is_err());
+        assert_eq!(*calls.lock().unwrap(), vec![Call::Insert("cats".into(), false)]);
+    }
+
+    #[test]
+    fn observer_sees_clear_and_prune() {
+        let mut trie = Trie::from(&["cat", "carrot", "cartoon", "cow"][..]);
+        let calls = observed(&mut trie);
+        assert_eq!(trie.prune_below_depth(4, true), 2);
+        assert_eq!(*calls.lock().unwrap(), vec![
+            Call::Remove("carrot".into(), true),
+            Call::Remove("cartoon".into(), true),
+            Call::Insert("carr".into(), true),
+            Call::Insert("cart".into(), true),
+        ]);
+
+        calls.lock().unwrap().clear();
+        trie.clear();
+        assert_eq!(*calls.lock().unwrap(), vec![
+            Call::Remove("cat".into(), true),
+            Call::Remove("carr".into(), true),
+            Call::Remove("cart".into(), true),
+            Call::Remove("cow".into(), true),
+        ]);
+
+        // removing the last sequence reports it once
+        calls.lock().unwrap().clear();
+        trie.insert(&chars("dog"));
+        assert!(trie.remove(&chars("dog")));
+        assert_eq!(*calls.lock().unwrap(), vec![
+            Call::Insert("dog".into(), true),
+            Call::Remove("dog".into(), true),
+        ]);
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+use std::ops::{Bound, RangeBounds};
+
+use crate::node::Node;
+use crate::Trie;
+
+impl<T, S> Trie<T, S>
+where
+    T: Ord + Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Returns the lexicographically smallest stored sequence.
+    ///
+    /// Follows the smallest element at each level and stops at the first
+    /// terminal, a stored prefix is smaller than all of its extensions.
+    pub fn first_sequence(&self) -> Option<Vec<T>> {
+        self.first_from(self.start_nodes(), Vec::new())
+    }
+
+    /// Returns the lexicographically largest stored sequence.
+    ///
+    /// Follows the largest element at each level to the deepest terminal, an
+    /// extension is larger than its stored prefix ("cart" > "car").
+    pub fn last_sequence(&self) -> Option<Vec<T>> {
+        self.last_from(self.start_nodes(), Vec::new())
+    }
+
+    /// Returns the lexicographically smallest stored sequence beginning with
+    /// `start`, `first_sequence` of the sequences under one start.
+    ///
+    /// See `first_inserted_under` for elements that aren't `Ord`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["mole", "cat", "mat", "mast", "max"][..]);
+    ///
+    /// assert_eq!(trie.first_under(&'m'), Some(vec!['m', 'a', 's', 't']));
+    /// assert_eq!(trie.last_under(&'m'), Some(vec!['m', 'o', 'l', 'e']));
+    /// assert_eq!(trie.first_under(&'z'), None);
+    /// ```
+    pub fn first_under(&self, start: &T) -> Option<Vec<T>> {
+        let node = self.node(&self.normalize(std::slice::from_ref(start)))?;
+        self.first_from(vec![node], Vec::new())
+    }
+
+    /// Returns the lexicographically largest stored sequence beginning with
+    /// `start`, see `first_under`.
+    pub fn last_under(&self, start: &T) -> Option<Vec<T>> {
+        let node = self.node(&self.normalize(std::slice::from_ref(start)))?;
+        self.last_from(vec![node], Vec::new())
+    }
+
+    /// Returns the stored sequences within `range` in lexicographic order,
+    /// bounds work like `BTreeMap::range` and need not be stored themselves.
+    ///
+    /// The walk is lazy and skips subtrees that fall entirely outside the
+    /// range, once past the upper bound it stops.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ops::Bound;
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// for word in ["cow", "cat", "dog", "car"].iter() {
+    ///     trie.insert_str(word);
+    /// }
+    ///
+    /// let range = trie.range((Bound::Included(&['c', 'a', 't'][..]), Bound::Excluded(&['d'][..])));
+    /// assert_eq!(range.collect::<Vec<_>>(), vec![vec!['c', 'a', 't'], vec!['c', 'o', 'w']]);
+    /// ```
+    pub fn range<R: RangeBounds<[T]>>(&self, range: R) -> impl Iterator<Item = Vec<T>> + '_ {
+        let bound = |b: Bound<&[T]>| match b {
+            Bound::Included(seq) => Bound::Included(self.normalize(seq).into_owned()),
+            Bound::Excluded(seq) => Bound::Excluded(self.normalize(seq).into_owned()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let mut stack = self.start_nodes().into_iter().map(|n| (1, n)).collect::<Vec<_>>();
+        stack.sort_by(|a, b| b.1.val.cmp(&a.1.val));
+        Range {
+            trie: self,
+            stack,
+            path: Vec::new(),
+            start: bound(range.start_bound()),
+            end: bound(range.end_bound()),
+        }
+    }
+
+    fn start_nodes(&self) -> Vec<&Node<T>> {
+        self.starts.iter().filter_map(|key| self.children.get(key)).collect()
+    }
+
+    /// Smallest sequence reachable from `level`, `path` is the path above it.
+    pub(crate) fn first_from<'a>(
+        &'a self,
+        mut level: Vec<&'a Node<T>>,
+        mut path: Vec<T>,
+    ) -> Option<Vec<T>> {
+        loop {
+            let node = level.into_iter().min_by(|a, b| a.val.cmp(&b.val))?;
+            path.push(node.to_value());
+            if node.is_terminal() {
+                return Some(path);
+            }
+            level = node.children(&self.children);
+        }
+    }
+
+    /// Largest sequence reachable from `level`, `path` is the path above it.
+    pub(crate) fn last_from<'a>(
+        &'a self,
+        mut level: Vec<&'a Node<T>>,
+        mut path: Vec<T>,
+    ) -> Option<Vec<T>> {
+        let mut last = None;
+        while let Some(node) = level.into_iter().max_by(|a, b| a.val.cmp(&b.val)) {
+            path.push(node.to_value());
+            if node.is_terminal() {
+                last = Some(path.clone());
+            }
+            level = node.children(&self.children);
+        }
+        last
+    }
+}
+
+/// Lazy ordered walk behind `Trie::range`.
+struct Range<'a, T, S> {
+    trie: &'a Trie<T, S>,
+    /// `(depth, node)` with the smallest sibling on top.
+    stack: Vec<(usize, &'a Node<T>)>,
+    path: Vec<T>,
+    start: Bound<Vec<T>>,
+    end: Bound<Vec<T>>,
+}
+
+impl<'a, T, S> Iterator for Range<'a, T, S>
+where
+    T: Ord + Eq + Hash + Clone + Debug,
+    S: BuildHasher,
+{
+    type Item = Vec<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((depth, node)) = self.stack.pop() {
+            self.path.truncate(depth - 1);
+            self.path.push(node.to_value());
+
+            let past_end = match &self.end {
+                Bound::Included(end) => self.path.as_slice() > end.as_slice(),
+                Bound::Excluded(end) => self.path.as_slice() >= end.as_slice(),
+                Bound::Unbounded => false,
+            };
+            // everything left is larger still
+            if past_end {
+                self.stack.clear();
+                return None;
+            }
+            let (before_start, in_start) = match &self.start {
+                Bound::Included(start) => (self.path < *start, self.path >= *start),
+                Bound::Excluded(start) => (self.path < *start, self.path > *start),
+                Bound::Unbounded => (false, true),
+            };
+            // below the start and not on the way to it, so are its extensions
+            if before_start && !self.start_is_ahead() {
+                continue;
+            }
+
+            let mut children = node.children(&self.trie.children);
+            children.sort_by(|a, b| b.val.cmp(&a.val));
+            self.stack.extend(children.into_iter().map(|n| (depth + 1, n)));
+
+            if in_start && node.is_terminal() {
+                return Some(self.path.clone());
+            }
+        }
+        None
+    }
+}
+
+impl<'a, T: Ord, S> Range<'a, T, S> {
+    /// `true` if the start bound extends the current path.
+    fn start_is_ahead(&self) -> bool {
+        match &self.start {
+            Bound::Included(start) | Bound::Excluded(start) => start.starts_with(&self.path),
+            Bound::Unbounded => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Bound;
+
+    use crate::Trie;
+
+    fn make(words: &[&str]) -> Trie<char> {
+        let mut trie = Trie::new();
+        for w in words {
+            trie.insert_str(w);
+        }
+        trie
+    }
+
+    fn string(seq: Option<Vec<char>>) -> Option<String> {
+        seq.map(|s| s.into_iter().collect())
+    }
+
+    #[test]
+    fn first_last() {
+        let trie = make(&["cow", "cart", "car", "bat", "dog", "do", "dot"]);
+        assert_eq!(string(trie.first_sequence()).as_deref(), Some("bat"));
+        assert_eq!(string(trie.last_sequence()).as_deref(), Some("dot"));
+
+        // a stored prefix of the max path
+        let trie = make(&["car", "cart", "ca", "b"]);
+        assert_eq!(string(trie.first_sequence()).as_deref(), Some("b"));
+        assert_eq!(string(trie.last_sequence()).as_deref(), Some("cart"));
+
+        let trie = make(&["cart", "car"]);
+        assert_eq!(string(trie.first_sequence()).as_deref(), Some("car"));
+        assert_eq!(string(trie.last_sequence()).as_deref(), Some("cart"));
+
+        let empty = Trie::<char>::new();
+        assert_eq!(empty.range(..).count(), 0);
+        assert_eq!(empty.first_sequence(), None);
+        assert_eq!(empty.last_sequence(), None);
+    }
+
+    #[test]
+    fn first_last_under() {
+        let trie = make(&["cow", "cart", "car", "bat", "dog", "do", "dot", "d"]);
+        assert_eq!(string(trie.first_under(&'c')).as_deref(), Some("car"));
+        assert_eq!(string(trie.last_under(&'c')).as_deref(), Some("cow"));
+        // the start itself is the smallest under it
+        assert_eq!(string(trie.first_under(&'d')).as_deref(), Some("d"));
+        assert_eq!(string(trie.last_under(&'d')).as_deref(), Some("dot"));
+        assert_eq!(string(trie.first_under(&'b')), string(trie.last_under(&'b')));
+        assert_eq!(trie.first_under(&'a'), None);
+        assert_eq!(trie.last_under(&'a'), None);
+
+        // the global extremes are the extremes under the smallest and largest start
+        assert_eq!(trie.first_under(&'b'), trie.first_sequence());
+        assert_eq!(trie.last_under(&'d'), trie.last_sequence());
+
+        let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        trie.insert_str("Moat");
+        trie.insert_str("mile");
+        assert_eq!(string(trie.first_under(&'M')).as_deref(), Some("mile"));
+    }
+
+    fn range(trie: &Trie<char>, start: Bound<&str>, end: Bound<&str>) -> Vec<String> {
+        let chars = |b: Bound<&str>| match b {
+            Bound::Included(s) => Bound::Included(s.chars().collect::<Vec<_>>()),
+            Bound::Excluded(s) => Bound::Excluded(s.chars().collect::<Vec<_>>()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let (start, end) = (chars(start), chars(end));
+        let bounds = (
+            start.as_ref().map(|s| s.as_slice()),
+            end.as_ref().map(|s| s.as_slice()),
+        );
+        trie.range(bounds).map(|seq| seq.into_iter().collect()).collect()
+    }
+
+    #[test]
+    fn range_bounds() {
+        use Bound::*;
+
+        let trie = make(&["cow", "cart", "car", "bat", "dog", "do", "dot", "ca"]);
+        let all = range(&trie, Unbounded, Unbounded);
+        assert_eq!(all, &["bat", "ca", "car", "cart", "cow", "do", "dog", "dot"]);
+
+        assert_eq!(range(&trie, Included("car"), Included("do")), &["car", "cart", "cow", "do"]);
+        assert_eq!(range(&trie, Excluded("car"), Excluded("do")), &["cart", "cow"]);
+        // bounds that aren't stored
+        assert_eq!(range(&trie, Included("cb"), Unbounded), &["cow", "do", "dog", "dot"]);
+        assert_eq!(range(&trie, Unbounded, Excluded("caz")), &["bat", "ca", "car", "cart"]);
+        assert_eq!(range(&trie, Included("c"), Excluded("d")), &["ca", "car", "cart", "cow"]);
+        // reversed
+        assert!(range(&trie, Included("dog"), Included("bat")).is_empty());
+        assert!(range(&trie, Included("car"), Excluded("car")).is_empty());
+        assert_eq!(range(&trie, Included("car"), Included("car")), &["car"]);
+    }
+}
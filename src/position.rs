@@ -0,0 +1,114 @@
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::Trie;
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Returns every stored sequence with the element at each index of
+    /// `constraints` equal to its value, and exactly `exact_len` elements when
+    /// given, in traversal order.
+    ///
+    /// A branch is left as soon as a node breaks the constraint on its index
+    /// so with a constraint on index 0 only that start is walked. Two different
+    /// values for one index, or an index past `exact_len`, match nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["cart", "curt", "cat", "dirt"][..]);
+    ///
+    /// let found = trie.filter_position(&[(0, 'c'), (2, 'r')], Some(4));
+    /// assert_eq!(found, vec![vec!['c', 'a', 'r', 't'], vec!['c', 'u', 'r', 't']]);
+    /// ```
+    pub fn filter_position(&self, constraints: &[(usize, T)], exact_len: Option<usize>) -> Vec<Vec<T>> {
+        let mut found = Vec::new();
+        let vals = constraints.iter().map(|(_, val)| val.clone()).collect::<Vec<_>>();
+        let vals = self.normalize(&vals);
+
+        // the value each index must hold, sequences must reach the last one
+        let needed = constraints.iter().map(|(index, _)| index + 1).max().unwrap_or(0);
+        if exact_len.is_some_and(|len| len < needed.max(1)) {
+            return found;
+        }
+        let mut at = vec![None; needed];
+        for ((index, _), val) in constraints.iter().zip(vals.iter()) {
+            match at[*index] {
+                Some(other) if other != val => return found,
+                _ => at[*index] = Some(val),
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut stack = self.starts.iter().rev().map(|key| (1, *key)).collect::<Vec<_>>();
+        while let Some((depth, key)) = stack.pop() {
+            let node = &self.children[&key];
+            if let Some(Some(val)) = at.get(depth - 1) {
+                if *val != node.as_value() {
+                    continue;
+                }
+            }
+            path.truncate(depth - 1);
+            path.push(node.to_value());
+            let end = exact_len.is_some_and(|len| depth == len);
+            if node.is_terminal() && depth >= needed && (end || exact_len.is_none()) {
+                found.push(path.clone());
+            }
+            if !end {
+                stack.extend(node.children.iter().rev().map(|key| (depth + 1, *key)));
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn words(found: Vec<Vec<char>>) -> Vec<String> {
+        found.iter().map(|seq| seq.iter().collect()).collect()
+    }
+
+    #[test]
+    fn position_constraints() {
+        let trie = Trie::from(&["cart", "curt", "cat", "carts", "dirt", "care", "ca"][..]);
+
+        assert_eq!(words(trie.filter_position(&[(2, 'r')], Some(4))), vec!["cart", "care", "curt", "dirt"]);
+        assert_eq!(words(trie.filter_position(&[(2, 'r'), (3, 't')], Some(4))), vec!["cart", "curt", "dirt"]);
+        assert_eq!(words(trie.filter_position(&[(0, 'c'), (3, 't')], None)), vec!["cart", "carts", "curt"]);
+        assert_eq!(words(trie.filter_position(&[(4, 's')], None)), vec!["carts"]);
+        // the same constraint twice is fine
+        assert_eq!(words(trie.filter_position(&[(1, 'i'), (1, 'i')], None)), vec!["dirt"]);
+    }
+
+    #[test]
+    fn position_no_length() {
+        let trie = Trie::from(&["ca", "cat", "cow", "dog"][..]);
+
+        // sequences too short for the constraint don't match
+        assert_eq!(words(trie.filter_position(&[(1, 'a')], None)), vec!["ca", "cat"]);
+        assert_eq!(words(trie.filter_position(&[(2, 't')], None)), vec!["cat"]);
+        assert_eq!(trie.filter_position(&[], None), trie.sequences());
+        assert_eq!(words(trie.filter_position(&[], Some(2))), vec!["ca"]);
+        assert!(trie.filter_position(&[], Some(0)).is_empty());
+    }
+
+    #[test]
+    fn position_contradictions() {
+        let trie = Trie::from(&["cat", "cot"][..]);
+
+        assert!(trie.filter_position(&[(1, 'a'), (1, 'o')], None).is_empty());
+        assert!(trie.filter_position(&[(3, 's')], Some(3)).is_empty());
+        assert!(trie.filter_position(&[(0, 'x')], None).is_empty());
+        assert!(trie.filter_position(&[(5, 't')], None).is_empty());
+
+        let mut lower = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        lower.insert(&['c', 'a', 't']);
+        assert_eq!(words(lower.filter_position(&[(0, 'C')], None)), vec!["cat"]);
+    }
+}
@@ -0,0 +1,354 @@
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::ops::Index;
+
+use crate::node::{DepthFirst, Node};
+use crate::storage::Storage;
+use crate::{Found, NoopBuildHasher, Trie};
+
+/// A read only handle to the node at the end of a path, returned by
+/// `Trie::get`.
+///
+/// The handle borrows the trie so it always describes the trie as it was when
+/// the handle was made, to see a later insert or remove ask for a new handle.
+///
+/// One lookup tells a stored sequence, a prefix of one and a missing path
+/// apart.
+///
+/// ```
+/// use ecs_trie::Trie;
+/// let mut trie = Trie::new();
+/// trie.insert(&['c', 'a', 't']);
+///
+/// let describe = |seq: &[char]| match trie.get(seq) {
+///     Some(node) if node.is_terminal() => "stored",
+///     Some(_) => "prefix only",
+///     None => "absent",
+/// };
+/// assert_eq!(describe(&['c', 'a', 't']), "stored");
+/// assert_eq!(describe(&['c', 'a']), "prefix only");
+/// assert_eq!(describe(&['c', 'o']), "absent");
+/// ```
+pub struct PrefixRef<'a, T, S = NoopBuildHasher> {
+    trie: &'a Trie<T, S>,
+    node: &'a Node<T>,
+    path: Vec<T>,
+}
+
+impl<T: Clone, S> Clone for PrefixRef<'_, T, S> {
+    fn clone(&self) -> Self {
+        Self { trie: self.trie, node: self.node, path: self.path.clone() }
+    }
+}
+
+impl<T: Debug, S: BuildHasher> Debug for PrefixRef<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrefixRef")
+            .field("trie", self.trie)
+            .field("node", self.node)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+/// The node at the end of a path, another name for `PrefixRef`.
+pub type NodeRef<'a, T, S = NoopBuildHasher> = PrefixRef<'a, T, S>;
+
+impl<'a, T, S> PrefixRef<'a, T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// The element at the end of the path.
+    pub fn value(&self) -> &'a T {
+        self.node.as_value()
+    }
+
+    /// The path leading to this node, normalized if the trie has a normalizer.
+    pub fn path(&self) -> &[T] {
+        &self.path
+    }
+
+    /// Returns `true` if the path is a stored sequence.
+    pub fn is_terminal(&self) -> bool {
+        self.node.is_terminal()
+    }
+
+    /// Number of distinct elements that can follow the path.
+    pub fn child_count(&self) -> usize {
+        self.node.child_len()
+    }
+
+    /// The elements that can follow the path and whether each ends a stored
+    /// sequence, in insertion order.
+    pub fn children(&self) -> impl Iterator<Item = (&'a T, bool)> + 'a {
+        let map = &self.trie.children;
+        self.node.children.iter()
+            .map(move |key| &map[key])
+            .map(|node| (node.as_value(), node.is_terminal()))
+    }
+
+    /// The elements that can follow the path in insertion order.
+    pub fn child_values(&self) -> Vec<&'a T> {
+        self.children().map(|(val, _)| val).collect()
+    }
+
+    /// Every node below the path depth first with whether it ends a stored
+    /// sequence, children in insertion order.
+    pub fn descendants(&self) -> impl Iterator<Item = (&'a T, bool)> + 'a {
+        self.node.walk(self.trie).map(|node| (node.as_value(), node.is_terminal()))
+    }
+
+    /// Number of stored sequences beginning with the path, including the path
+    /// itself.
+    pub fn count(&self) -> usize {
+        self.node.descendant_terminals
+    }
+
+    /// Every stored sequence beginning with the path, the same as
+    /// `Trie::search` without looking the path up again.
+    pub fn completions(&self) -> Found<T> {
+        let mut found = Found::new();
+        found.extend(self.path.iter().cloned());
+        self.trie.children.search_below(self.node, &mut found, usize::MAX);
+        found
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Returns a handle to the node at the end of `seq`, `None` if the path is
+    /// empty or not in the trie. The path need not be a stored sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 't']);
+    /// trie.insert(&['c', 'a', 'r']);
+    ///
+    /// let ca = trie.get(&['c', 'a']).unwrap();
+    /// assert!(!ca.is_terminal());
+    /// assert_eq!(ca.child_count(), 2);
+    /// assert_eq!(ca.completions().as_collected(), vec![&['c', 'a', 't'], &['c', 'a', 'r']]);
+    /// ```
+    pub fn get(&self, seq: &[T]) -> Option<PrefixRef<'_, T, S>> {
+        let seq = self.normalize(seq);
+        let node = self.node(&seq)?;
+        Some(PrefixRef { trie: self, node, path: seq.into_owned() })
+    }
+
+    /// A handle to every node in traversal order, each with its full path.
+    ///
+    /// Keys are hashes of the path so the path of a node is only known by
+    /// walking down to it, each `PrefixRef` owns a copy of its path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["ca", "cow"][..]);
+    ///
+    /// let paths = trie.nodes().map(|n| n.path().iter().collect()).collect::<Vec<String>>();
+    /// assert_eq!(paths, vec!["c", "ca", "co", "cow"]);
+    /// ```
+    pub fn nodes(&self) -> impl Iterator<Item = PrefixRef<'_, T, S>> + '_ {
+        DepthFirst::new(self).scan(Vec::new(), move |path, (depth, node)| {
+            path.truncate(depth - 1);
+            path.push(node.to_value());
+            Some(PrefixRef { trie: self, node, path: path.clone() })
+        })
+    }
+
+    /// Every parent to child edge as `(parent path, child path)`, each once,
+    /// in traversal order of the parents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["ca", "co"][..]);
+    ///
+    /// assert_eq!(trie.adjacency(), vec![
+    ///     (vec!['c'], vec!['c', 'a']),
+    ///     (vec!['c'], vec!['c', 'o']),
+    /// ]);
+    /// ```
+    pub fn adjacency(&self) -> Vec<(Vec<T>, Vec<T>)> {
+        self.adjacency_by(|_, _, _| true)
+    }
+
+    /// The edges of `adjacency` that `keep` returns `true` for, it is given
+    /// the parent path, the child path and whether the child is terminal.
+    /// `|_, _, terminal| terminal` gives only the edges into stored sequences.
+    pub fn adjacency_by<F>(&self, mut keep: F) -> Vec<(Vec<T>, Vec<T>)>
+    where
+        F: FnMut(&[T], &[T], bool) -> bool,
+    {
+        let mut edges = Vec::new();
+        let mut child = Vec::new();
+        for parent in self.nodes() {
+            for (val, terminal) in parent.children() {
+                child.clear();
+                child.extend_from_slice(parent.path());
+                child.push(val.clone());
+                if keep(parent.path(), &child, terminal) {
+                    edges.push((parent.path().to_vec(), child.clone()));
+                }
+            }
+        }
+        edges
+    }
+}
+
+/// Returns the element at the end of the path `seq`.
+///
+/// # Panics
+///
+/// Panics if `seq` is empty or not in the trie, use `Trie::get` to check.
+impl<T, S> Index<&[T]> for Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    type Output = T;
+
+    fn index(&self, seq: &[T]) -> &T {
+        self.node(&self.normalize(seq))
+            .map(|node| node.as_value())
+            .expect("path not in trie")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Trie;
+
+    fn words() -> Trie<char> {
+        ["car", "cart", "carrot", "cab", "cow"].iter().copied().collect()
+    }
+
+    #[test]
+    fn get_handle() {
+        let trie = words();
+        assert!(trie.get(&[]).is_none());
+        assert!(trie.get(&['c', 'x']).is_none());
+
+        let car = trie.get(&['c', 'a', 'r']).unwrap();
+        assert_eq!(car.value(), &'r');
+        assert_eq!(car.path(), &['c', 'a', 'r']);
+        assert!(car.is_terminal());
+        assert_eq!(car.child_count(), 2);
+        assert_eq!(car.count(), 3);
+        assert_eq!(car.children().collect::<Vec<_>>(), vec![(&'t', true), (&'r', false)]);
+        assert_eq!(
+            car.completions().as_collected(),
+            trie.search(&['c', 'a', 'r']).as_collected()
+        );
+        assert_eq!(trie[&['c', 'o'][..]], 'o');
+
+        let vals = car.descendants().map(|(v, _)| *v).collect::<String>();
+        assert_eq!(vals, "trot");
+        assert_eq!(car.descendants().filter(|(_, t)| *t).count(), car.count() - 1);
+    }
+
+    #[test]
+    fn nodes_edges() {
+        use std::collections::HashSet;
+
+        let mut trie = words();
+        trie.remove_str("cart");
+        let nodes = trie.nodes().collect::<Vec<_>>();
+        assert_eq!(nodes.len(), trie.node_count());
+        assert_eq!(nodes.iter().filter(|n| n.is_terminal()).count(), trie.sequence_count());
+        assert!(nodes.iter().all(|n| n.path().last() == Some(n.value())));
+
+        let from_nodes = nodes.iter()
+            .flat_map(|n| n.child_values().into_iter().map(move |c| (n.path().to_vec(), [n.path(), &[*c]].concat())))
+            .collect::<HashSet<_>>();
+        let from_sequences = trie.sequences().iter()
+            .flat_map(|seq| (1..seq.len()).map(move |i| (seq[..i].to_vec(), seq[..=i].to_vec())))
+            .collect::<HashSet<_>>();
+        assert_eq!(from_nodes, from_sequences);
+        assert_eq!(from_nodes.len(), trie.node_count() - trie.num_starts());
+        assert_eq!(Trie::<char>::new().nodes().count(), 0);
+    }
+
+    #[test]
+    fn adjacency_counts() {
+        use petgraph::{algo, graph::DiGraph};
+        use std::collections::HashMap;
+
+        let mut trie = Trie::new();
+        for word in std::fs::read_to_string("words.txt").unwrap().split_whitespace() {
+            trie.insert_str(word);
+        }
+        let stats = trie.stats();
+
+        let mut graph = DiGraph::<Vec<char>, ()>::new();
+        let mut index = HashMap::new();
+        // a start with no children shows up in no edge
+        for start in trie.nodes().filter(|n| n.path().len() == 1) {
+            index.insert(start.path().to_vec(), graph.add_node(start.path().to_vec()));
+        }
+        for (parent, child) in trie.adjacency() {
+            assert!(child.starts_with(&parent) && child.len() == parent.len() + 1);
+            let from = *index.entry(parent.clone()).or_insert_with(|| graph.add_node(parent));
+            let to = *index.entry(child.clone()).or_insert_with(|| graph.add_node(child));
+            graph.add_edge(from, to, ());
+        }
+        assert_eq!(graph.node_count(), stats.nodes);
+        // a tree per start so every node but the starts has one parent
+        assert_eq!(graph.edge_count(), stats.nodes - trie.num_starts());
+        assert!(!algo::is_cyclic_directed(&graph));
+        assert_eq!(algo::connected_components(&graph), trie.num_starts());
+        assert!(graph
+            .node_indices()
+            .all(|n| graph.neighbors_directed(n, petgraph::Incoming).count() == (graph[n].len() > 1) as usize));
+
+        let into_stored = trie.adjacency_by(|_, _, terminal| terminal);
+        let multi_letter = trie.sequences().iter().filter(|seq| seq.len() > 1).count();
+        assert_eq!(into_stored.len(), multi_letter);
+        assert!(trie.adjacency_by(|parent, _, _| parent.len() > stats.max_depth).is_empty());
+    }
+
+    #[test]
+    fn get_after_mutation() {
+        let mut trie = words();
+        assert!(!trie.get(&['c', 'a']).unwrap().is_terminal());
+
+        // handles borrow the trie, a fresh one sees each change
+        trie.insert_str("ca");
+        let ca = trie.get(&['c', 'a']).unwrap();
+        assert!(ca.is_terminal());
+        assert_eq!(ca.count(), 5);
+
+        trie.remove_str("car");
+        let car = trie.get(&['c', 'a', 'r']).unwrap();
+        assert!(!car.is_terminal());
+        assert_eq!(car.count(), 2);
+
+        trie.remove_str("cow");
+        assert!(trie.get(&['c', 'o']).is_none());
+        assert_eq!(trie.get(&['c']).unwrap().child_count(), 1);
+    }
+
+    #[test]
+    fn get_normalized() {
+        let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        trie.insert_str("Cat");
+        let ca = trie.get(&['C', 'A']).unwrap();
+        assert_eq!(ca.path(), &['c', 'a']);
+        assert_eq!(trie[&['C'][..]], 'c');
+    }
+
+    #[test]
+    #[should_panic(expected = "path not in trie")]
+    fn index_missing() {
+        let _ = words()[&['d'][..]];
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::Trie;
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Removes every node whose path is longer than `max_len`, returning the
+    /// number of stored sequences removed with them.
+    ///
+    /// With `keep_prefixes` a node at `max_len` that had sequences below it
+    /// becomes stored itself, each cut off sequence is kept truncated to
+    /// `max_len`, otherwise only the sequences of at most `max_len` remain.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::from(&["cat", "carrot", "cartoon"][..]);
+    ///
+    /// assert_eq!(trie.prune_below_depth(4, true), 2);
+    /// assert_eq!(
+    ///     trie.search(&['c']).as_collected(),
+    ///     vec![&['c', 'a', 't'][..], &['c', 'a', 'r', 'r'], &['c', 'a', 'r', 't']]
+    /// );
+    /// ```
+    pub fn prune_below_depth(&mut self, max_len: usize, keep_prefixes: bool) -> usize {
+        if max_len == 0 {
+            let dropped = self.seq_len;
+            self.clear();
+            return dropped;
+        }
+
+        // the nodes kept in pre-order, the nodes at `max_len` with children
+        // and the removed nodes with the path of each removed sequence
+        let mut kept = Vec::new();
+        let mut cut = Vec::new();
+        let mut removed = Vec::new();
+        let mut path = Vec::new();
+        let mut stack = self.starts.iter().rev().map(|key| (1, *key)).collect::<Vec<_>>();
+        while let Some((depth, key)) = stack.pop() {
+            let node = &self.children[&key];
+            path.truncate(depth - 1);
+            path.push(node.to_value());
+            if depth > max_len {
+                removed.push((key, if node.is_terminal() { Some(path.clone()) } else { None }));
+            } else {
+                kept.push(key);
+                if depth == max_len && node.child_len() > 0 {
+                    cut.push((key, path.clone()));
+                }
+            }
+            stack.extend(node.children.iter().rev().map(|key| (depth + 1, *key)));
+        }
+
+        // the sequences to report once the trie is consistent
+        let observed = self.observer.is_some();
+        let mut gone = Vec::new();
+        let mut added = Vec::new();
+
+        let nodes = self.len;
+        let mut dropped = 0;
+        for (key, seq) in removed {
+            self.children.remove(&key);
+            self.len -= 1;
+            if let Some(seq) = seq {
+                dropped += 1;
+                if let Some(lru) = self.lru.as_mut() {
+                    lru.forget(key);
+                }
+                self.unindex_suffix(&seq);
+                if observed {
+                    gone.push(seq);
+                }
+            }
+        }
+        self.seq_len -= dropped;
+        for (key, seq) in cut {
+            let node = self.children.get_mut(&key).expect("cut nodes are kept");
+            node.children.clear();
+            node.child_size = 0;
+            if keep_prefixes && !node.terminal {
+                node.terminal = true;
+                self.seq_len += 1;
+                if let Some(lru) = self.lru.as_mut() {
+                    lru.touch(key, &seq);
+                }
+                self.index_suffix(&seq);
+                if observed {
+                    added.push(seq);
+                }
+            }
+        }
+
+        // children come after their parent so in reverse each node's children
+        // already have their counts, a node left with no sequence below it is
+        // removed too
+        for key in kept.iter().rev() {
+            let live = self.children[key].children.iter()
+                .filter(|child| self.children.contains_key(child))
+                .copied()
+                .collect::<Vec<_>>();
+            let below = live.iter()
+                .map(|child| self.children[child].descendant_terminals)
+                .sum::<usize>();
+            let node = self.children.get_mut(key).expect("kept nodes are in the map");
+            node.descendant_terminals = below + node.terminal as usize;
+            node.child_size = live.len();
+            node.children = live;
+            if node.descendant_terminals == 0 {
+                self.children.remove(key);
+                self.len -= 1;
+            }
+        }
+        let children = &self.children;
+        self.starts.retain(|key| children.contains_key(key));
+        self.bloom_forget(nodes - self.len);
+        debug_assert_invariants!(self);
+        for seq in gone {
+            self.notify_remove(&seq, true);
+        }
+        for seq in added {
+            self.notify_insert(&seq, true);
+        }
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn words(path: &str) -> Vec<Vec<char>> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect())
+            .collect()
+    }
+
+    #[test]
+    fn prune_1984() {
+        let words = words("data/1984.txt");
+        let mut trie = Trie::new();
+        for w in words.iter() {
+            trie.insert(w);
+        }
+        let long = trie.sequences().iter().filter(|seq| seq.len() > 5).count();
+        let short = trie.sequence_count() - long;
+
+        let mut dropping = trie.clone();
+        assert_eq!(dropping.prune_below_depth(5, false), long);
+        assert_eq!(dropping.sequence_count(), short);
+        assert_eq!(dropping.check_invariants(), Ok(()));
+        assert!(dropping.iter_with_depth().all(|(depth, _, _)| depth <= 5));
+        for w in words.iter() {
+            assert_eq!(dropping.contains(w), w.len() <= 5);
+        }
+
+        let mut keeping = trie.clone();
+        assert_eq!(keeping.prune_below_depth(5, true), long);
+        assert_eq!(keeping.check_invariants(), Ok(()));
+        for w in words.iter() {
+            let truncated = &w[..w.len().min(5)];
+            assert!(keeping.get(truncated).is_some_and(|n| n.is_terminal()));
+        }
+        assert!(keeping.sequences().iter().all(|seq| seq.len() <= 5));
+        assert!(keeping.node_count() >= dropping.node_count());
+    }
+
+    #[test]
+    fn prune_edges() {
+        let mut trie = Trie::from(&["cat", "cart", "car"][..]);
+        // nothing deeper than the longest sequence
+        assert_eq!(trie.prune_below_depth(4, true), 0);
+        assert_eq!(trie.sequence_count(), 3);
+
+        // "car" is already stored, "cart" is dropped into it
+        assert_eq!(trie.prune_below_depth(3, true), 1);
+        assert_eq!(trie.sequences(), vec![vec!['c', 'a', 't'], vec!['c', 'a', 'r']]);
+        assert_eq!(trie.count_prefix(&['c']), 2);
+
+        assert_eq!(trie.prune_below_depth(1, false), 2);
+        assert!(trie.is_empty());
+        assert_eq!(trie.num_starts(), 0);
+        assert_eq!(trie.check_invariants(), Ok(()));
+
+        let mut trie = Trie::from(&["cat", "dog"][..]);
+        assert_eq!(trie.prune_below_depth(0, true), 2);
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn prune_keeps_indexes() {
+        let mut trie = crate::TrieBuilder::new().suffix_index(true).build();
+        for w in ["cart", "start", "art"].iter() {
+            trie.insert(&w.chars().collect::<Vec<_>>());
+        }
+        trie.prune_below_depth(3, true);
+        assert!(trie.ends_with(&['a', 'r', 't']));
+        assert!(trie.ends_with(&['t', 'a']));
+        assert!(!trie.ends_with(&['r', 't', 'x']));
+        assert_eq!(trie.search_suffix(&['a']).as_collected(), vec![&['s', 't', 'a'][..]]);
+
+        let mut bounded = Trie::with_max_sequences(2);
+        bounded.insert(&['c', 'a', 't', 's']);
+        bounded.insert(&['d', 'o', 'g']);
+        bounded.prune_below_depth(3, false);
+        bounded.insert(&['c', 'o', 'w']);
+        bounded.insert(&['e', 'l', 'k']);
+        assert_eq!(bounded.sequences(), vec![vec!['c', 'o', 'w'], vec!['e', 'l', 'k']]);
+    }
+}
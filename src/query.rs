@@ -0,0 +1,140 @@
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::key::path_keys;
+use crate::storage::Storage;
+use crate::{Found, Trie};
+
+/// A sequence normalized and hashed once by `Trie::prepare` so it can be
+/// looked up again and again without redoing either.
+///
+/// A key is worked out from the values of the path alone, it doesn't point
+/// into the trie, so a `QueryKey` stays valid while the trie is changed and
+/// gives the same answers as the unprepared lookups would. It is only tied to
+/// the normalizer of the trie that prepared it, used with a trie that
+/// normalizes differently it looks up the sequence as the first trie saw it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryKey<T> {
+    /// The normalized sequence.
+    seq: Vec<T>,
+    /// The key of every prefix of `seq` shortest first.
+    keys: Vec<u64>,
+}
+
+impl<T> QueryKey<T> {
+    /// The normalized sequence this looks up.
+    pub fn as_slice(&self) -> &[T] {
+        &self.seq
+    }
+
+    pub fn len(&self) -> usize {
+        self.seq.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seq.is_empty()
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Normalizes and hashes `seq` for the `_prepared` lookups.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// let query = trie.prepare(&['c', 'a', 't']);
+    /// assert!(!trie.contains_prepared(&query));
+    ///
+    /// trie.insert(&['c', 'a', 'r']);
+    /// assert_eq!(trie.match_depth_prepared(&query), 2);
+    /// trie.insert(&['c', 'a', 't', 's']);
+    /// assert!(trie.contains_prepared(&query));
+    /// assert_eq!(trie.search_prepared(&query).as_collected(), vec![&['c', 'a', 't', 's']]);
+    /// ```
+    pub fn prepare(&self, seq: &[T]) -> QueryKey<T> {
+        let seq = self.normalize(seq).into_owned();
+        let keys = path_keys(&seq).collect();
+        QueryKey { seq, keys }
+    }
+
+    /// Like `contains` for a prepared `query`.
+    pub fn contains_prepared(&self, query: &QueryKey<T>) -> bool {
+        let key = match query.keys.last() {
+            Some(key) => *key,
+            None => return false,
+        };
+        if !self.bloom.as_ref().is_none_or(|bloom| bloom.might_contain(key)) {
+            return false;
+        }
+        self.children.contains_key(&key)
+    }
+
+    /// Like `match_depth` for a prepared `query`.
+    pub fn match_depth_prepared(&self, query: &QueryKey<T>) -> usize {
+        // every prefix of a path is a path
+        query.keys.partition_point(|key| self.children.contains_key(key))
+    }
+
+    /// Like `search` for a prepared `query`.
+    pub fn search_prepared(&self, query: &QueryKey<T>) -> Found<T> {
+        let mut res = Found::new();
+        res.extend(query.seq.iter().cloned());
+        if let Some(node) = query.keys.last().and_then(|key| self.children.get(key)) {
+            self.children.search_below(node, &mut res, usize::MAX)
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prepared_matches_unprepared() {
+        let words = std::fs::read_to_string("data/1984.txt")
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut trie = Trie::new();
+        for w in words.iter().step_by(2) {
+            trie.insert(w);
+        }
+
+        for w in words.iter().skip(1).step_by(5) {
+            let query = trie.prepare(w);
+            let prefix = trie.prepare(&w[..w.len().min(3)]);
+            assert_eq!(trie.contains_prepared(&query), trie.contains(w));
+            assert_eq!(trie.match_depth_prepared(&query), trie.match_depth(w));
+            assert_eq!(trie.search_prepared(&prefix).into_response_vec(), trie.search(prefix.as_slice()).into_response_vec());
+        }
+        let empty = trie.prepare(&[]);
+        assert!(empty.is_empty());
+        assert!(!trie.contains_prepared(&empty));
+        assert_eq!(trie.match_depth_prepared(&empty), 0);
+    }
+
+    #[test]
+    fn prepared_survives_changes() {
+        let mut trie = Trie::with_normalizer(|c: &char| c.to_ascii_lowercase());
+        let query = trie.prepare(&['C', 'a', 'T']);
+        assert_eq!(query.as_slice(), &['c', 'a', 't']);
+        assert_eq!(query.len(), 3);
+
+        trie.insert(&['c', 'a', 't']);
+        assert!(trie.contains_prepared(&query));
+        trie.remove(&['c', 'a', 't']);
+        assert!(!trie.contains_prepared(&query));
+        trie.insert(&['C', 'A', 'T', 'S']);
+        assert_eq!(trie.match_depth_prepared(&query), 3);
+        trie.compact();
+        assert_eq!(trie.search_prepared(&query).into_response_vec(), vec![vec!['c', 'a', 't', 's']]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+
+use crate::{Found, NoopBuildHasher, Trie};
+
+/// A read only view of a `Trie` made by `Trie::reader`, only the lookups are
+/// available.
+///
+/// It is a shared reference so it is `Copy` and, when `T` is `Sync`, can be
+/// handed to any number of scoped threads while the trie can't be changed.
+/// Unlike `FrozenTrie` nothing is moved or reference counted, the trie must
+/// just outlive the reader.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_trie::Trie;
+/// let mut trie = Trie::new();
+/// trie.insert(&['c', 'a', 't']);
+/// let reader = trie.reader();
+///
+/// std::thread::scope(|s| {
+///     let found = s.spawn(move || reader.contains(&['c', 'a']));
+///     assert!(found.join().unwrap());
+/// });
+/// ```
+pub struct TrieReader<'a, T, S = NoopBuildHasher> {
+    trie: &'a Trie<T, S>,
+}
+
+impl<T, S> Clone for TrieReader<'_, T, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, S> Copy for TrieReader<'_, T, S> {}
+
+impl<T: Debug, S: BuildHasher> Debug for TrieReader<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.trie, f)
+    }
+}
+
+impl<'a, T, S> TrieReader<'a, T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// See `Trie::contains`.
+    pub fn contains(&self, seq: &[T]) -> bool {
+        self.trie.contains(seq)
+    }
+
+    /// See `Trie::starts_with`.
+    pub fn starts_with(&self, prefix: &[T]) -> bool {
+        self.trie.starts_with(prefix)
+    }
+
+    /// See `Trie::search`.
+    pub fn search(&self, prefix: &[T]) -> Found<T> {
+        self.trie.search(prefix)
+    }
+
+    /// See `Trie::count_prefix`.
+    pub fn count_prefix(&self, prefix: &[T]) -> usize {
+        self.trie.count_prefix(prefix)
+    }
+
+    /// See `Trie::sequences`.
+    pub fn sequences(&self) -> Vec<Vec<T>> {
+        self.trie.sequences()
+    }
+
+    pub fn sequence_count(&self) -> usize {
+        self.trie.sequence_count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// A read only handle to the trie for sharing lookups between threads.
+    pub fn reader(&self) -> TrieReader<'_, T, S> {
+        TrieReader { trie: self }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::*;
+
+    /// Fails to compile unless a trie of any `Sync` element is `Sync`.
+    #[allow(dead_code)]
+    fn trie_is_sync<T: Sync>() {
+        fn sync<S: Sync>() {}
+        sync::<Trie<T>>();
+        sync::<TrieReader<'_, T>>();
+    }
+
+    #[allow(dead_code)]
+    fn reader_is_send<T: Sync>() {
+        fn send<S: Send + Clone>() {}
+        send::<TrieReader<'_, T>>();
+    }
+
+    #[test]
+    fn reader_across_threads() {
+        let words = std::fs::read_to_string("data/1984.txt")
+            .unwrap()
+            .split_whitespace()
+            .map(|w| w.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut trie = Trie::new();
+        for w in words.iter() {
+            trie.insert(w);
+        }
+        let reader = trie.reader();
+
+        let queries = words.iter().step_by(7).cloned().collect::<Vec<_>>();
+        let chunks = queries.chunks(queries.len() / 4 + 1).collect::<Vec<_>>();
+        let results = thread::scope(|s| {
+            let handles = chunks.iter()
+                .map(|chunk| s.spawn(move || {
+                    chunk.iter()
+                        .map(|w| (reader.contains(w), reader.search(&w[..w.len().min(3)]).as_collected().len()))
+                        .collect::<Vec<_>>()
+                }))
+                .collect::<Vec<_>>();
+            handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+        });
+
+        for (chunk, found) in chunks.iter().zip(results.iter()) {
+            for (w, (contains, searched)) in chunk.iter().zip(found.iter()) {
+                assert!(contains);
+                assert_eq!(*searched, trie.count_prefix(&w[..w.len().min(3)]));
+            }
+        }
+        assert_eq!(reader.sequence_count(), trie.sequence_count());
+    }
+}
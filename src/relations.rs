@@ -0,0 +1,141 @@
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::Trie;
+
+/// Comparing tries as sets of sequences. Node keys are hashes of the path so
+/// the same key names the same path in both tries, nodes are compared by key
+/// without rebuilding any sequence.
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Returns `true` if every sequence stored in `self` is stored in `other`.
+    ///
+    /// Stops at the first path of `self` that `other` doesn't have or that has
+    /// more sequences below it in `self` than in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let small = Trie::from(&["cat"][..]);
+    /// let big = Trie::from(&["cat", "cow"][..]);
+    ///
+    /// assert!(small.is_subset(&big));
+    /// assert!(!big.is_subset(&small));
+    /// ```
+    pub fn is_subset(&self, other: &Self) -> bool {
+        if self.seq_len > other.seq_len {
+            return false;
+        }
+        let mut stack = self.starts.clone();
+        while let Some(key) = stack.pop() {
+            let node = &self.children[&key];
+            let theirs = match other.children.get(&key) {
+                Some(theirs) => theirs,
+                None => return false,
+            };
+            if node.descendant_terminals > theirs.descendant_terminals
+                || (node.is_terminal() && !theirs.is_terminal())
+            {
+                return false;
+            }
+            stack.extend(node.children.iter().copied());
+        }
+        true
+    }
+
+    /// Returns `true` if every sequence stored in `other` is stored in `self`.
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns `true` if no sequence is stored in both.
+    ///
+    /// Only the paths the two share are walked and it stops at the first
+    /// sequence they both store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let cats = Trie::from(&["cat", "cats"][..]);
+    ///
+    /// assert!(cats.is_disjoint(&Trie::from(&["ca", "cow"][..])));
+    /// assert!(!cats.is_disjoint(&Trie::from(&["cats"][..])));
+    /// ```
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        let (small, big) = if self.len <= other.len { (self, other) } else { (other, self) };
+        let mut stack = small.starts.clone();
+        while let Some(key) = stack.pop() {
+            let node = &small.children[&key];
+            let theirs = match big.children.get(&key) {
+                Some(theirs) => theirs,
+                // nothing below a path only one of them has is shared
+                None => continue,
+            };
+            if node.is_terminal() && theirs.is_terminal() {
+                return false;
+            }
+            stack.extend(node.children.iter().copied());
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_relations() {
+        let commands = Trie::from(&["commit", "checkout", "cherry-pick", "clone"][..]);
+        let abbreviations = Trie::from(&["commit", "checkout", "cherry-pick", "clone", "ci", "co"][..]);
+
+        // subset
+        assert!(commands.is_subset(&abbreviations));
+        assert!(abbreviations.is_superset(&commands));
+        assert!(!abbreviations.is_subset(&commands));
+        assert!(!commands.is_superset(&abbreviations));
+
+        // equal, inserted in another order
+        let same = Trie::from(&["clone", "cherry-pick", "checkout", "commit"][..]);
+        assert!(commands.is_subset(&same) && commands.is_superset(&same));
+
+        // overlapping, a stored sequence only a path in the other
+        let overlapping = Trie::from(&["commit", "che", "push"][..]);
+        assert!(!overlapping.is_subset(&commands));
+        assert!(!commands.is_subset(&overlapping));
+        assert!(!overlapping.is_disjoint(&commands));
+
+        // disjoint though sharing paths
+        let prefixes = Trie::from(&["com", "check", "cl", "cherry"][..]);
+        assert!(prefixes.is_disjoint(&commands));
+        assert!(commands.is_disjoint(&prefixes));
+        assert!(!prefixes.is_subset(&commands));
+        assert!(Trie::from(&["push", "pull"][..]).is_disjoint(&commands));
+        assert!(!commands.is_disjoint(&commands));
+    }
+
+    #[test]
+    fn set_relations_empty() {
+        let empty = Trie::<char>::new();
+        let words = Trie::from(&["cat"][..]);
+
+        assert!(empty.is_subset(&words));
+        assert!(empty.is_subset(&empty));
+        assert!(!words.is_subset(&empty));
+        assert!(words.is_superset(&empty));
+        assert!(empty.is_disjoint(&words));
+        assert!(words.is_disjoint(&empty));
+        assert!(empty.is_disjoint(&empty));
+
+        // emptied by removal
+        let mut emptied = words.clone();
+        emptied.remove(&['c', 'a', 't']);
+        assert!(emptied.is_subset(&empty) && emptied.is_superset(&empty));
+        assert!(emptied.is_disjoint(&words));
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt::{self, Debug, Display};
+use std::hash::{BuildHasher, Hash};
+
+use crate::node::DepthFirst;
+use crate::Trie;
+
+/// A summary of a trie's shape, see `Trie::stats`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrieStats {
+    pub nodes: usize,
+    pub sequences: usize,
+    /// Length of the longest stored sequence.
+    pub max_depth: usize,
+    /// Mean number of children of the nodes that have any, 0 for an empty
+    /// trie.
+    pub avg_branching: f64,
+    /// Nodes without children, each ends a stored sequence.
+    pub leaves: usize,
+    /// Stored sequences that other stored sequences extend, "car" with "cart".
+    pub stems: usize,
+    /// Mean length of the stored sequences, 0 for an empty trie.
+    pub avg_len: f64,
+}
+
+/// One line for logging, averages to two decimal places.
+impl Display for TrieStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "nodes: {}, sequences: {}, max depth: {}, avg branching: {:.2}, leaves: {}, stems: {}, avg length: {:.2}",
+            self.nodes,
+            self.sequences,
+            self.max_depth,
+            self.avg_branching,
+            self.leaves,
+            self.stems,
+            self.avg_len,
+        )
+    }
+}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Measures the trie's shape in one walk over its nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert(&['c', 'a', 'r']);
+    /// trie.insert(&['c', 'a', 'r', 't']);
+    /// trie.insert(&['c', 'o', 'w']);
+    ///
+    /// let stats = trie.stats();
+    /// assert_eq!(stats.max_depth, 4);
+    /// assert_eq!(stats.stems, 1);
+    /// assert_eq!(
+    ///     stats.to_string(),
+    ///     "nodes: 6, sequences: 3, max depth: 4, avg branching: 1.25, leaves: 2, stems: 1, avg length: 3.33"
+    /// );
+    /// ```
+    pub fn stats(&self) -> TrieStats {
+        let mut max_depth = 0;
+        let mut internal = 0;
+        let mut edges = 0;
+        let mut leaves = 0;
+        let mut stems = 0;
+        let mut total_len = 0;
+        for (depth, node) in DepthFirst::new(self) {
+            let children = node.child_len();
+            if children == 0 {
+                leaves += 1;
+            } else {
+                internal += 1;
+                edges += children;
+            }
+            if node.is_terminal() {
+                max_depth = max_depth.max(depth);
+                total_len += depth;
+                if children > 0 {
+                    stems += 1;
+                }
+            }
+        }
+        let mean = |sum: usize, count: usize| if count == 0 { 0.0 } else { sum as f64 / count as f64 };
+        TrieStats {
+            nodes: self.len,
+            sequences: self.seq_len,
+            max_depth,
+            avg_branching: mean(edges, internal),
+            leaves,
+            stems,
+            avg_len: mean(total_len, self.seq_len),
+        }
+    }
+
+    /// The number of nodes at each depth, the first is the number of starts
+    /// and there is one per level down to `stats().max_depth`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let trie = Trie::from(&["cat", "cow", "d"][..]);
+    ///
+    /// assert_eq!(trie.level_sizes(), vec![2, 2, 2]);
+    /// assert_eq!(trie.widest_level(), Some(1));
+    /// ```
+    pub fn level_sizes(&self) -> Vec<usize> {
+        let mut sizes = Vec::new();
+        for (depth, _) in DepthFirst::new(self) {
+            if sizes.len() < depth {
+                sizes.resize(depth, 0);
+            }
+            sizes[depth - 1] += 1;
+        }
+        sizes
+    }
+
+    /// The depth with the most nodes, the shallowest of any that tie, `None`
+    /// for an empty trie.
+    pub fn widest_level(&self) -> Option<usize> {
+        let sizes = self.level_sizes();
+        // `max_by_key` keeps the last of equal maximums
+        sizes.iter().enumerate().rev().max_by_key(|(_, size)| **size).map(|(level, _)| level + 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stats_small() {
+        let trie = Trie::<char>::new();
+        assert_eq!(
+            trie.stats(),
+            TrieStats {
+                nodes: 0,
+                sequences: 0,
+                max_depth: 0,
+                avg_branching: 0.0,
+                leaves: 0,
+                stems: 0,
+                avg_len: 0.0,
+            }
+        );
+
+        let mut trie = Trie::new();
+        trie.insert(&['a']);
+        trie.insert(&['a', 'b']);
+        trie.insert(&['a', 'c']);
+        trie.insert(&['d', 'e', 'f']);
+        let stats = trie.stats();
+        assert_eq!(
+            stats,
+            TrieStats {
+                nodes: 6,
+                sequences: 4,
+                max_depth: 3,
+                // a has 2 children, d and e one each
+                avg_branching: 4.0 / 3.0,
+                leaves: 3,
+                stems: 1,
+                avg_len: 8.0 / 4.0,
+            }
+        );
+
+        trie.remove(&['a', 'b']);
+        trie.remove(&['a', 'c']);
+        let stats = trie.stats();
+        assert_eq!(stats.stems, 0);
+        assert_eq!(stats.leaves, 2);
+        assert_eq!(stats.nodes, 4);
+    }
+
+    #[test]
+    fn level_sizes_after_removal() {
+        let mut trie = Trie::<char>::new();
+        assert!(trie.level_sizes().is_empty());
+        assert_eq!(trie.widest_level(), None);
+
+        for w in ["a", "ab", "ac", "def", "degh"] {
+            trie.insert(&w.chars().collect::<Vec<_>>());
+        }
+        assert_eq!(trie.level_sizes(), vec![2, 3, 2, 1]);
+        assert_eq!(trie.widest_level(), Some(2));
+
+        // the whole "de" subtree goes
+        trie.remove(&['d', 'e', 'f']);
+        trie.remove(&['d', 'e', 'g', 'h']);
+        assert_eq!(trie.level_sizes(), vec![1, 2]);
+        assert_eq!(trie.widest_level(), Some(2));
+        trie.remove(&['a', 'c']);
+        assert_eq!(trie.level_sizes(), vec![1, 1]);
+        assert_eq!(trie.widest_level(), Some(1));
+    }
+
+    #[test]
+    fn level_sizes_words() {
+        let mut trie = Trie::new();
+        for word in std::fs::read_to_string("words.txt").unwrap().split_whitespace() {
+            trie.insert(&word.chars().collect::<Vec<_>>());
+        }
+        let sizes = trie.level_sizes();
+        let stats = trie.stats();
+        assert_eq!(sizes.iter().sum::<usize>(), stats.nodes);
+        assert_eq!(sizes.len(), stats.max_depth);
+        assert_eq!(sizes[0], trie.num_starts());
+        let widest = trie.widest_level().unwrap();
+        assert!(sizes.iter().all(|size| *size <= sizes[widest - 1]));
+    }
+
+    #[test]
+    fn stats_words() {
+        let mut trie = Trie::new();
+        for word in std::fs::read_to_string("words.txt").unwrap().split_whitespace() {
+            trie.insert(&word.chars().collect::<Vec<_>>());
+        }
+        let stats = trie.stats();
+        assert_eq!(stats.nodes, trie.node_count());
+        assert_eq!(stats.sequences, trie.sequence_count());
+        assert!(stats.leaves > 0 && stats.leaves <= stats.sequences);
+        assert!(stats.stems < stats.sequences);
+        assert_eq!(stats.leaves + stats.stems, stats.sequences);
+        assert!(stats.avg_branching >= 1.0);
+        assert!(stats.avg_len >= 1.0 && stats.avg_len <= stats.max_depth as f64);
+        assert!(stats.max_depth <= stats.nodes);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+use crate::key::sequence_key;
+use crate::{Found, Node};
+
+/// Where a trie keeps its nodes. The walks that only read nodes are written
+/// once here, `Trie` keys its nodes by path hash and `OrderedTrie` by the path
+/// itself.
+pub(crate) trait Storage<T: Clone + PartialEq> {
+    /// What a node's `children` holds to find each child.
+    type Key;
+
+    /// The node at the end of `path`, `None` if it is empty or not in the trie.
+    fn node(&self, path: &[T]) -> Option<&Node<T, Self::Key>>;
+
+    /// The child `key` of the node at the end of `path`, `path` is left as it
+    /// was. An empty `path` gives a start.
+    fn child(&self, path: &mut Vec<T>, key: &Self::Key) -> &Node<T, Self::Key>;
+
+    /// Every stored sequence beginning with `prefix`, children in the order
+    /// the node keeps them.
+    fn search(&self, prefix: &[T]) -> Found<T> {
+        let mut found = Found::new();
+        found.extend(prefix.iter().cloned());
+        if let Some(node) = self.node(prefix) {
+            self.search_below(node, &mut found, usize::MAX);
+        }
+        found
+    }
+
+    /// Collects `node` and its descendants at most `levels` below it,
+    /// `found.temp` holds the path to `node`.
+    fn search_below(&self, node: &Node<T, Self::Key>, found: &mut Found<T>, levels: usize) {
+        if node.terminal {
+            found.branch_end();
+        }
+        if levels == 0 {
+            return;
+        }
+        // each child's branch is rolled back before the next sibling is pushed
+        for key in node.children.iter() {
+            let child = self.child(&mut found.temp, key);
+            found.push_val(child.val.clone());
+            self.search_below(child, found, levels - 1);
+            found.branch_split();
+        }
+    }
+
+    /// Every stored sequence below the starts `starts`, in order.
+    fn sequences(&self, starts: &[Self::Key]) -> Vec<Vec<T>> {
+        let mut found = Found::new();
+        for key in starts {
+            let node = self.child(&mut found.temp, key);
+            found.push_val(node.val.clone());
+            self.search_below(node, &mut found, usize::MAX);
+            found.branch_split();
+        }
+        found.into_response_vec()
+    }
+}
+
+/// A node is found by the hash of its path, see `sequence_key`.
+impl<T, S> Storage<T> for HashMap<u64, Node<T>, S>
+where
+    T: Hash + Clone + PartialEq,
+    S: BuildHasher,
+{
+    type Key = u64;
+
+    fn node(&self, path: &[T]) -> Option<&Node<T>> {
+        if path.is_empty() {
+            return None;
+        }
+        self.get(&sequence_key(path))
+    }
+
+    fn child(&self, _path: &mut Vec<T>, key: &u64) -> &Node<T> {
+        &self[key]
+    }
+}
+
+/// A node is found by its path, a child's path is its parent's with the
+/// child's element pushed.
+impl<T> Storage<T> for BTreeMap<Vec<T>, Node<T, T>>
+where
+    T: Ord + Clone,
+{
+    type Key = T;
+
+    fn node(&self, path: &[T]) -> Option<&Node<T, T>> {
+        self.get(path)
+    }
+
+    fn child(&self, path: &mut Vec<T>, key: &T) -> &Node<T, T> {
+        path.push(key.clone());
+        let node = &self[path.as_slice()];
+        path.pop();
+        node
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::{Found, Trie};
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Adds the newly stored, normalized `seq` to the suffix index if there is
+    /// one.
+    pub(crate) fn index_suffix(&mut self, seq: &[T]) {
+        if let Some(suffixes) = self.suffixes.as_mut() {
+            suffixes.insert_owned(seq.iter().rev().cloned().collect());
+        }
+    }
+
+    /// Drops the removed, normalized `seq` from the suffix index if there is
+    /// one.
+    pub(crate) fn unindex_suffix(&mut self, seq: &[T]) {
+        if let Some(suffixes) = self.suffixes.as_mut() {
+            suffixes.remove(&seq.iter().rev().cloned().collect::<Vec<_>>());
+        }
+    }
+
+    /// Returns `true` if a stored sequence ends with `suffix`, the empty suffix
+    /// ends every sequence.
+    ///
+    /// With `TrieBuilder::suffix_index` this is a prefix lookup in the reversed
+    /// sequences, without it every stored sequence is checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::TrieBuilder;
+    /// let mut trie = TrieBuilder::new().suffix_index(true).build();
+    /// trie.insert(&['c', 'a', 'r', 't']);
+    ///
+    /// assert!(trie.ends_with(&['r', 't']));
+    /// assert!(!trie.ends_with(&['c', 'a']));
+    /// ```
+    pub fn ends_with(&self, suffix: &[T]) -> bool {
+        let suffix = &*self.normalize(suffix);
+        match &self.suffixes {
+            Some(suffixes) => suffixes.starts_with(&reversed(suffix)),
+            None => {
+                let mut found = false;
+                self.for_each_sequence(|seq| found |= seq.ends_with(suffix));
+                found
+            }
+        }
+    }
+
+    /// Returns every stored sequence ending with `suffix`.
+    ///
+    /// With `TrieBuilder::suffix_index` the sequences come in the traversal
+    /// order of the reversed sequences, sharing the longest endings first,
+    /// otherwise in traversal order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::TrieBuilder;
+    /// let mut trie = TrieBuilder::new().suffix_index(true).build();
+    /// trie.insert(&['c', 'a', 'r', 't']);
+    /// trie.insert(&['s', 't', 'a', 'r', 't']);
+    /// trie.insert(&['c', 'a', 't']);
+    ///
+    /// assert_eq!(
+    ///     trie.search_suffix(&['a', 'r', 't']).as_collected(),
+    ///     vec![&['c', 'a', 'r', 't'][..], &['s', 't', 'a', 'r', 't']]
+    /// );
+    /// ```
+    pub fn search_suffix(&self, suffix: &[T]) -> Found<T> {
+        let suffix = &*self.normalize(suffix);
+        let mut found = Found::new();
+        match &self.suffixes {
+            Some(suffixes) => {
+                found.collected = suffixes.search(&reversed(suffix))
+                    .into_response_vec()
+                    .into_iter()
+                    .map(|mut seq| {
+                        seq.reverse();
+                        seq
+                    })
+                    .collect();
+            }
+            None => self.for_each_sequence(|seq| {
+                if seq.ends_with(suffix) {
+                    found.collected.push(seq.to_vec());
+                }
+            }),
+        }
+        found
+    }
+}
+
+fn reversed<T: Clone>(seq: &[T]) -> Vec<T> {
+    seq.iter().rev().cloned().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::TrieBuilder;
+
+    use super::*;
+
+    fn chars(w: &str) -> Vec<char> {
+        w.chars().collect()
+    }
+
+    fn strings(found: Found<char>) -> Vec<String> {
+        found.as_collected().iter().map(|seq| seq.iter().collect()).collect()
+    }
+
+    #[test]
+    fn suffix_index_shared_endings() {
+        let mut trie = TrieBuilder::new().suffix_index(true).build();
+        for w in ["cart", "start", "art", "car", "star"].iter() {
+            trie.insert(&chars(w));
+        }
+        assert!(trie.ends_with(&chars("art")));
+        assert!(trie.ends_with(&chars("tart")));
+        assert!(trie.ends_with(&chars("start")));
+        assert!(!trie.ends_with(&chars("carts")));
+        assert!(!trie.ends_with(&chars("sta")));
+        assert!(trie.ends_with(&[]));
+
+        assert_eq!(strings(trie.search_suffix(&chars("art"))), vec!["art", "cart", "start"]);
+        assert_eq!(strings(trie.search_suffix(&chars("ar"))), vec!["car", "star"]);
+        assert!(trie.search_suffix(&chars("x")).as_collected().is_empty());
+
+        // a duplicate isn't indexed twice
+        trie.insert(&chars("cart"));
+        assert_eq!(strings(trie.search_suffix(&chars("rt"))), vec!["art", "cart", "start"]);
+    }
+
+    #[test]
+    fn suffix_index_follows_removal() {
+        let mut trie = TrieBuilder::new().suffix_index(true).build();
+        for w in ["cart", "start", "art"].iter() {
+            trie.insert(&chars(w));
+        }
+        assert!(trie.remove(&chars("start")));
+        assert!(!trie.ends_with(&chars("start")));
+        assert_eq!(strings(trie.search_suffix(&chars("art"))), vec!["art", "cart"]);
+
+        assert!(!trie.remove(&chars("tart")));
+        assert!(trie.remove(&chars("art")));
+        assert_eq!(strings(trie.search_suffix(&chars("art"))), vec!["cart"]);
+        assert!(trie.remove(&chars("cart")));
+        assert!(!trie.ends_with(&[]));
+
+        trie.insert(&chars("dart"));
+        trie.clear();
+        assert!(!trie.ends_with(&chars("t")));
+        let suffixes = trie.suffixes.as_ref().unwrap();
+        assert!(suffixes.is_empty());
+        assert_eq!(suffixes.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn suffix_index_with_other_options() {
+        let mut trie = TrieBuilder::new()
+            .suffix_index(true)
+            .normalizer(|c: &char| c.to_ascii_lowercase())
+            .build_from_iter(vec![chars("CART"), chars("Start")]);
+        assert!(trie.ends_with(&chars("ART")));
+        trie.insert_owned(chars("Dart"));
+        assert_eq!(strings(trie.search_suffix(&chars("aRt"))), vec!["cart", "start", "dart"]);
+
+        let mut bounded = Trie::with_max_sequences(1);
+        bounded.suffixes = Some(Box::new(Trie::new()));
+        bounded.insert(&chars("cart"));
+        bounded.insert(&chars("start"));
+        assert_eq!(strings(bounded.search_suffix(&chars("art"))), vec!["start"]);
+    }
+
+    #[test]
+    fn suffix_without_index() {
+        let mut trie = Trie::new();
+        for w in ["cart", "start", "art", "cow"].iter() {
+            trie.insert(&chars(w));
+        }
+        assert!(trie.ends_with(&chars("tart")));
+        assert!(!trie.ends_with(&chars("tarts")));
+        assert_eq!(strings(trie.search_suffix(&chars("art"))), vec!["cart", "start", "art"]);
+    }
+}
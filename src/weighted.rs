@@ -0,0 +1,214 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::key::path_keys;
+use crate::node::Node;
+use crate::Trie;
+
+/// A completion in the `top_k` heap, ordered so the heap's top is the worst
+/// kept completion, the lowest weight and of those the latest found.
+struct Ranked<T> {
+    weight: u32,
+    order: usize,
+    seq: Vec<T>,
+}
+
+impl<T> Ord for Ranked<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.weight.cmp(&self.weight).then(self.order.cmp(&other.order))
+    }
+}
+
+impl<T> PartialOrd for Ranked<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Ranked<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Ranked<T> {}
+
+impl<T, S> Trie<T, S>
+where
+    T: Eq + Hash + Clone + Debug,
+    S: BuildHasher + Default,
+{
+    /// Inserts `seq` with `weight`, re-inserting a stored sequence updates its
+    /// weight. Sequences added by `insert` have a weight of 0 and `insert`
+    /// leaves the weight of an already stored sequence alone.
+    pub fn insert_weighted(&mut self, seq: &[T], weight: u32) {
+        let inserted = self.insert_weighted_unobserved(seq, weight);
+        self.notify_insert(seq, inserted);
+    }
+
+    fn insert_weighted_unobserved(&mut self, seq: &[T], weight: u32) -> bool {
+        let seq = &*self.normalize(seq);
+        if seq.is_empty() {
+            return false;
+        }
+        // the sequence as it is stored after any length limit
+        let seq = match self.limit(seq) {
+            Some(seq) => seq,
+            None => return false,
+        };
+        let keys = path_keys(seq).collect::<Vec<_>>();
+        let inserted = self.insert_keys(seq, &keys);
+        self.children.get_mut(&keys[keys.len() - 1]).expect("seq was just inserted").weight = weight;
+        debug_assert_invariants!(self, &keys);
+        inserted
+    }
+
+    /// The weight of `seq`, `None` if it isn't stored.
+    pub fn weight(&self, seq: &[T]) -> Option<u32> {
+        let seq = &*self.normalize(seq);
+        self.node(seq).filter(|node| node.is_terminal()).map(|node| node.weight)
+    }
+
+    /// Returns the `k` highest weighted sequences beginning with `prefix`,
+    /// heaviest first, equal weights keep traversal order. The empty prefix
+    /// ranks every sequence.
+    ///
+    /// Only the best `k` seen so far are kept while walking the subtree so
+    /// memory is O(k) however many sequences match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_trie::Trie;
+    /// let mut trie = Trie::new();
+    /// trie.insert_weighted(&['c', 'a', 't'], 3);
+    /// trie.insert_weighted(&['c', 'o', 'w'], 7);
+    /// trie.insert_weighted(&['c', 'a', 'r'], 5);
+    ///
+    /// assert_eq!(
+    ///     trie.top_k(&['c'], 2),
+    ///     vec![(vec!['c', 'o', 'w'], 7), (vec!['c', 'a', 'r'], 5)]
+    /// );
+    /// ```
+    pub fn top_k(&self, prefix: &[T], k: usize) -> Vec<(Vec<T>, u32)> {
+        let prefix = self.normalize(prefix);
+        if k == 0 {
+            return Vec::new();
+        }
+        let depth = prefix.len();
+        let mut stack: Vec<(usize, &Node<T>)> = if prefix.is_empty() {
+            self.starts.iter().rev().map(|key| (1, &self.children[key])).collect()
+        } else {
+            match self.node(&prefix) {
+                Some(node) => vec![(depth, node)],
+                None => return Vec::new(),
+            }
+        };
+
+        let mut path = prefix.into_owned();
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        let mut order = 0;
+        while let Some((depth, node)) = stack.pop() {
+            path.truncate(depth - 1);
+            path.push(node.to_value());
+            stack.extend(node.children.iter().rev().map(|key| (depth + 1, &self.children[key])));
+            if !node.is_terminal() {
+                continue;
+            }
+            order += 1;
+            // only clone the path if it makes the cut
+            let beats_worst = heap.peek().is_none_or(|worst: &Ranked<T>| node.weight > worst.weight);
+            if heap.len() < k || beats_worst {
+                heap.push(Ranked { weight: node.weight, order, seq: path.clone() });
+                if heap.len() > k {
+                    heap.pop();
+                }
+            }
+        }
+        heap.into_sorted_vec().into_iter().map(|r| (r.seq, r.weight)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Trie;
+
+    fn weighted(words: &[(&str, u32)]) -> Trie<char> {
+        let mut trie = Trie::new();
+        for (word, weight) in words {
+            trie.insert_weighted(&word.chars().collect::<Vec<_>>(), *weight);
+        }
+        trie
+    }
+
+    fn strings(ranked: Vec<(Vec<char>, u32)>) -> Vec<(String, u32)> {
+        ranked.into_iter().map(|(seq, w)| (seq.into_iter().collect(), w)).collect()
+    }
+
+    #[test]
+    fn top_k_order() {
+        let trie = weighted(&[
+            ("car", 10), ("cart", 40), ("carrot", 40), ("cab", 25), ("cow", 5), ("dog", 99),
+        ]);
+        assert_eq!(
+            strings(trie.top_k(&['c'], 3)),
+            vec![("cart".to_string(), 40), ("carrot".to_string(), 40), ("cab".to_string(), 25)]
+        );
+        // the prefix itself counts
+        assert_eq!(strings(trie.top_k(&['c', 'a', 'r'], 10)).last().unwrap(), &("car".to_string(), 10));
+        assert_eq!(trie.top_k(&[], 1), vec![(vec!['d', 'o', 'g'], 99)]);
+        assert_eq!(trie.top_k(&['c'], 100).len(), 5);
+        assert!(trie.top_k(&['c'], 0).is_empty());
+        assert!(trie.top_k(&['x'], 3).is_empty());
+    }
+
+    #[test]
+    fn top_k_ties_keep_traversal_order() {
+        let trie = weighted(&[("b", 1), ("ba", 1), ("bat", 1), ("be", 1), ("bee", 1)]);
+        let ranked = trie.top_k(&['b'], 3).into_iter().map(|(seq, _)| seq).collect::<Vec<_>>();
+        assert_eq!(ranked, trie.sequences()[..3].to_vec());
+    }
+
+    #[test]
+    fn reweight_and_remove() {
+        let mut trie = weighted(&[("car", 10), ("cart", 40)]);
+        assert_eq!(trie.weight(&['c', 'a', 'r']), Some(10));
+        assert_eq!(trie.weight(&['c', 'a']), None);
+
+        trie.insert_weighted(&['c', 'a', 'r'], 50);
+        assert_eq!(trie.sequence_count(), 2);
+        assert_eq!(trie.top_k(&['c'], 1), vec![(vec!['c', 'a', 'r'], 50)]);
+
+        // a plain insert keeps the weight
+        trie.insert(&['c', 'a', 'r']);
+        assert_eq!(trie.weight(&['c', 'a', 'r']), Some(50));
+
+        // removing forgets it
+        trie.remove(&['c', 'a', 'r']);
+        trie.insert(&['c', 'a', 'r']);
+        assert_eq!(trie.weight(&['c', 'a', 'r']), Some(0));
+    }
+
+    #[test]
+    fn top_k_matches_sort() {
+        let text = std::fs::read_to_string("data/sun-rising.txt").unwrap();
+        let mut trie = Trie::new();
+        for (i, word) in text.split_whitespace().enumerate() {
+            trie.insert_weighted(&word.chars().collect::<Vec<_>>(), (i * 7919 % 101) as u32);
+        }
+        for prefix in ["t", "s", "w"].iter() {
+            let prefix = prefix.chars().collect::<Vec<_>>();
+            let mut all = trie.search(&prefix)
+                .as_collected()
+                .into_iter()
+                .map(|seq| (seq.to_vec(), trie.weight(seq).unwrap()))
+                .collect::<Vec<_>>();
+            // stable so ties keep traversal order
+            all.sort_by_key(|seq| std::cmp::Reverse(seq.1));
+            all.truncate(5);
+            assert_eq!(trie.top_k(&prefix, 5), all);
+        }
+    }
+}